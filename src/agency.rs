@@ -0,0 +1,115 @@
+//! Lookup of producer agency codes. The AGEN subfield (in DSID and in the LNAM of every
+//! feature) is a numeric code from the IHO S-62 register, this maps it to the two letter
+//! producer code used in cell names and to the name of the agency.
+//!
+//! The register is loaded from a CSV with the columns `AGEN,producer code,agency`. With the
+//! `agencies` feature an extract of the register is embedded in the crate and used where AGEN
+//! values are displayed, see [`producer`]. Load the whole register for agencies it lacks.
+use crate::catalog::Result;
+use crate::error::ErrorKind;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+#[cfg(feature = "agencies")]
+use lazy_static::lazy_static;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Agency {
+    pub code: u16,        // AGEN
+    pub producer: String, // Producer code, e.g. "GB"
+    pub name: String,
+}
+
+#[derive(Debug, Default)]
+pub struct AgencyTable(HashMap<u16, Agency>);
+
+#[cfg(feature = "agencies")]
+lazy_static! {
+    static ref BUILTIN: AgencyTable =
+        AgencyTable::from_csv(&include_bytes!("../data/agencies.csv")[..]).unwrap();
+}
+
+impl AgencyTable {
+    /// Reads a table from CSV, skipping empty lines and lines starting with '#'.
+    pub fn from_csv<R: BufRead>(rdr: R) -> Result<AgencyTable> {
+        let mut table = HashMap::new();
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line.map_err(|err| ErrorKind::IOError(err.kind()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.splitn(3, ',');
+            let agency = match (columns.next(), columns.next(), columns.next()) {
+                (Some(code), Some(producer), Some(name)) => Agency {
+                    code: code
+                        .trim()
+                        .parse()
+                        .map_err(|_| ErrorKind::BadAgencyTable(i + 1))?,
+                    producer: producer.trim().to_string(),
+                    name: name.trim().to_string(),
+                },
+                _ => return Err(ErrorKind::BadAgencyTable(i + 1).into()),
+            };
+            table.insert(agency.code, agency);
+        }
+        Ok(AgencyTable(table))
+    }
+
+    /// The table embedded with the `agencies` feature.
+    #[cfg(feature = "agencies")]
+    pub fn builtin() -> &'static AgencyTable {
+        &BUILTIN
+    }
+
+    pub fn get(&self, code: u16) -> Option<&Agency> {
+        self.0.get(&code)
+    }
+
+    pub fn by_producer(&self, producer: &str) -> Option<&Agency> {
+        self.0.values().find(|a| a.producer == producer)
+    }
+}
+
+/// How the agency `agen` is displayed: its producer code from the embedded table, or the code
+/// itself when the table doesn't have it or the `agencies` feature is off.
+pub fn producer(agen: u16) -> String {
+    #[cfg(feature = "agencies")]
+    {
+        if let Some(agency) = AgencyTable::builtin().get(agen) {
+            return agency.producer.clone();
+        }
+    }
+    agen.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_csv() {
+        let csv = "# comment\n\n540,GB,United Kingdom Hydrographic Office\n";
+        let table = AgencyTable::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(table.get(540).unwrap().producer, "GB");
+        assert_eq!(table.by_producer("GB").unwrap().code, 540);
+        assert!(table.get(550).is_none());
+        assert!(AgencyTable::from_csv("GB,540".as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "agencies")]
+    #[test]
+    fn builtin() {
+        let table = AgencyTable::builtin();
+        assert_eq!(table.get(550).unwrap().producer, "US");
+        assert_eq!(table.by_producer("GB").unwrap().code, 540);
+        assert_eq!(
+            table.get(540).unwrap().name,
+            "United Kingdom Hydrographic Office"
+        );
+        assert!(table.get(0).is_none());
+        assert_eq!(producer(540), "GB");
+        assert_eq!(producer(550), "US");
+        assert_eq!(producer(65535), "65535");
+    }
+}
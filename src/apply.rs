@@ -0,0 +1,528 @@
+//! Applying update files (.001, .002, ...) to a [`DataSet`]. Every feature and vector record of
+//! an update carries an update instruction (RUIN): insert a new record, delete a record, or
+//! modify one. A modification replaces the attributes it gives, deletes the attributes whose
+//! value is the delete character (0x7f), and changes pointers and coordinates as told by the
+//! control fields FSPC, FFPC, VRPC and SGCC. The record version (RVER) of a deleted or modified
+//! record must be one more than that of the record it updates.
+//!
+//! Before any of its records are applied, the DSID record of an update is checked to be the next
+//! update of the data set: the same edition, the update number one higher and an update date
+//! (UADT) no earlier than that of the data set.
+use crate::catalog::{Catalog, Record, Result, Rows};
+use crate::data_parser::Data;
+use crate::dataset::{record_name, rows, subfield_number, DataSet, RecordName};
+use crate::dsid::DataSetIdentification;
+use crate::error::ErrorKind;
+use crate::feature::code;
+use crate::history::UpdateInstruction;
+use crate::updates::{check_next, CellState, UpdateSequence};
+use failure::ResultExt;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::Read;
+use std::path::Path;
+
+pub(crate) const DELETE: &str = "\u{7f}";
+
+// A control field, the tag of the field it controls and the labels of its update instruction,
+// index and number of entries.
+pub(crate) const CONTROLS: &[(&str, &str, [&str; 3])] = &[
+    ("FSPC", "FSPT", ["FSUI", "FSIX", "NSPT"]),
+    ("FFPC", "FFPT", ["FFUI", "FFIX", "NFPT"]),
+    ("VRPC", "VRPT", ["VPUI", "VPIX", "NVPT"]),
+];
+
+// The labels and rows of the fields `tag` of `record`, empty if it has none.
+fn table(record: &Record, tag: &str) -> Result<(Vec<String>, Rows)> {
+    let labels: Vec<String> = match record.field(tag) {
+        Some(field) => field.labels().into_iter().map(String::from).collect(),
+        None => return Ok((Vec::new(), Rows::new())),
+    };
+    let names: Vec<&str> = labels.iter().map(String::as_str).collect();
+    let rows = rows(record, tag, &names)?
+        .into_iter()
+        .map(|row| row.into_iter().cloned().collect())
+        .collect();
+    Ok((labels, rows))
+}
+
+fn set_table(record: &mut Record, tag: &str, labels: &[String], rows: Rows) {
+    if rows.is_empty() {
+        record.remove_field(tag);
+    } else {
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        record.insert_rows(tag, &labels, rows);
+    }
+}
+
+// Replaces, adds or deletes the attributes of `base` the attribute field `tag` of `update` gives.
+fn update_attributes(base: &mut Record, update: &Record, tag: &str) -> Result<()> {
+    let (labels, mut attributes) = table(base, tag)?;
+    let (update_labels, changes) = table(update, tag)?;
+    let labels = if labels.is_empty() {
+        update_labels
+    } else {
+        labels
+    };
+    let column = |label: &str| {
+        labels
+            .iter()
+            .position(|l| l == label)
+            .ok_or_else(|| ErrorKind::MissingSubfield(tag.to_string(), label.to_string()))
+    };
+    let (attl, atvl) = (column("ATTL")?, column("ATVL")?);
+    for change in changes {
+        let existing = attributes.iter().position(|row| row[attl] == change[attl]);
+        match (existing, &change[atvl]) {
+            (Some(i), Data::String(s)) if s == DELETE => {
+                attributes.remove(i);
+            }
+            (None, Data::String(s)) if s == DELETE => (),
+            (Some(i), _) => attributes[i] = change,
+            (None, _) => attributes.push(change),
+        }
+    }
+    set_table(base, tag, &labels, attributes);
+    Ok(())
+}
+
+// Inserts, deletes or replaces entries of the field `tag` of `base` as told by the control field
+// `control` of `update`. The index in the control field counts from 1.
+fn update_entries(
+    base: &mut Record,
+    update: &Record,
+    control: &str,
+    tag: &str,
+    labels: &[&str; 3],
+) -> Result<()> {
+    let instruction = code(
+        labels[0],
+        update
+            .get(control)
+            .and_then(|field| field.get(labels[0]))
+            .unwrap_or(&Data::Missing),
+        UpdateInstruction::from_code,
+    )?;
+    let index = subfield_number::<i64>(update, control, labels[1])?;
+    let count = subfield_number::<i64>(update, control, labels[2])?;
+    let (base_labels, mut entries) = table(base, tag)?;
+    let (update_labels, new) = table(update, tag)?;
+    let out_of_range = || ErrorKind::BadUpdateControl(control.to_string(), index, count);
+    // The index is 1-based; negative or oversized values can not address an entry
+    let (start, count) = usize::try_from(index)
+        .ok()
+        .and_then(|index| index.checked_sub(1))
+        .zip(usize::try_from(count).ok())
+        .ok_or_else(out_of_range)?;
+    let end = start.checked_add(count).ok_or_else(out_of_range)?;
+    match instruction {
+        UpdateInstruction::Insert if start <= entries.len() && new.len() >= count => {
+            entries.splice(start..start, new.into_iter().take(count));
+        }
+        UpdateInstruction::Delete if end <= entries.len() => {
+            entries.drain(start..end);
+        }
+        UpdateInstruction::Modify if end <= entries.len() && new.len() >= count => {
+            entries.splice(start..end, new.into_iter().take(count));
+        }
+        _ => return Err(out_of_range().into()),
+    }
+    let labels = if base_labels.is_empty() {
+        update_labels
+    } else {
+        base_labels
+    };
+    set_table(base, tag, &labels, entries);
+    Ok(())
+}
+
+// Applies the modification `update` to `base`, whose identifier field is `id`.
+fn modify(base: &mut Record, update: &Record, id: &str) -> Result<()> {
+    for tag in &["ATTF", "NATF", "ATTV"] {
+        if update.get(tag).is_some() {
+            update_attributes(base, update, tag)?;
+        }
+    }
+    for (control, tag, labels) in CONTROLS {
+        if update.get(control).is_some() {
+            update_entries(base, update, control, tag, labels)?;
+        }
+    }
+    if update.get("SGCC").is_some() {
+        let tag = if base.get("SG3D").is_some() || update.get("SG3D").is_some() {
+            "SG3D"
+        } else {
+            "SG2D"
+        };
+        update_entries(base, update, "SGCC", tag, &["CCUI", "CCIX", "CCNC"])?;
+    }
+    let rver = update.get(id).and_then(|field| field.get("RVER")).cloned();
+    if let Some(rver) = rver {
+        base.set(id, "RVER", rver);
+    }
+    Ok(())
+}
+
+// Applies the update `update` to the records `records`, indexed by record name.
+fn apply(records: &mut BTreeMap<RecordName, Record>, update: &Record, id: &str) -> Result<()> {
+    let name = record_name(update, id)?;
+    let ruin = update
+        .get(id)
+        .and_then(|field| field.get("RUIN"))
+        .unwrap_or(&Data::Missing);
+    let instruction = code("RUIN", ruin, UpdateInstruction::from_code)?;
+    if instruction == UpdateInstruction::Insert {
+        if records.contains_key(&name) {
+            return Err(ErrorKind::DuplicateRecord(name.0, name.1).into());
+        }
+        records.insert(name, update.clone());
+        return Ok(());
+    }
+    let base = records
+        .get_mut(&name)
+        .ok_or(ErrorKind::MissingRecord(name.0, name.1))?;
+    let (version, expected) = (
+        subfield_number::<i64>(base, id, "RVER")?,
+        subfield_number::<i64>(update, id, "RVER")?,
+    );
+    if expected != version + 1 {
+        return Err(ErrorKind::RecordVersionMismatch(name.0, name.1, version, expected).into());
+    }
+    match instruction {
+        UpdateInstruction::Delete => {
+            records.remove(&name);
+            Ok(())
+        }
+        _ => modify(base, update, id),
+    }
+}
+
+impl DataSet {
+    // Checks that the update with the DSID record `update` is the next update of the data set.
+    fn check_update(&self, update: &Record) -> Result<()> {
+        let current = self.identification()?;
+        let next = DataSetIdentification::try_from(update)?;
+        if next.edition == 0 {
+            return Err(ErrorKind::CellCancelled(current.name, next.update).into());
+        }
+        let file = Path::new(&current.name)
+            .with_extension(format!("{:03}", next.update))
+            .display()
+            .to_string();
+        check_next(&current.name, &file, current.version(), next.version())?;
+        if next.update_date < current.update_date {
+            return Err(
+                ErrorKind::UpdateDateMismatch(file, next.update_date, current.update_date).into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Applies a single record of an update file. The DSID record of the update is checked to be
+    /// the next update and sets the update number and dates of the data set, other records
+    /// without a FRID or VRID are ignored.
+    pub fn apply_record(&mut self, update: &Record) -> Result<()> {
+        if update.get("FRID").is_some() {
+            apply(&mut self.features, update, "FRID")?;
+            if let Some(history) = self.history.as_mut() {
+                let number = subfield_number(&self.general, "DSID", "UPDN")?;
+                history.ingest(number, update)?;
+            }
+            Ok(())
+        } else if update.get("VRID").is_some() {
+            apply(&mut self.vectors, update, "VRID")
+        } else if let Some(dsid) = update.get("DSID") {
+            self.check_update(update)?;
+            for label in &["UPDN", "UADT", "ISDT"] {
+                if let Some(data) = dsid.get(*label) {
+                    self.general.set("DSID", label, data.clone());
+                }
+            }
+            Ok(())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Applies every record of the update file read from `rdr`, in order. The update is applied
+    /// as a whole: if any record fails, the data set is left as it was.
+    pub fn apply_update<R: Read>(&mut self, rdr: R) -> Result<()> {
+        let mut updated = self.clone();
+        for record in Catalog::new(rdr)? {
+            updated.apply_record(&record?)?;
+        }
+        *self = updated;
+        Ok(())
+    }
+
+    /// Reads the base cell of `seq` and applies its updates in order. A sequence with a gap or
+    /// for a cancelled cell is refused, as the result would not be up to date.
+    pub fn from_sequence(seq: &UpdateSequence) -> Result<DataSet> {
+        let cell = seq.base.display().to_string();
+        if let CellState::Cancelled { update } = seq.state {
+            return Err(ErrorKind::CellCancelled(cell, update).into());
+        }
+        if let Some(gap) = seq.gap {
+            return Err(ErrorKind::MissingUpdate(cell, gap).into());
+        }
+        let mut data_set = DataSet::open(&seq.base)?;
+        for path in &seq.updates {
+            let file =
+                std::fs::File::open(path).with_context(|err| ErrorKind::IOError(err.kind()))?;
+            data_set.apply_update(std::io::BufReader::new(file))?;
+        }
+        Ok(data_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FileBuilder;
+
+    fn int(i: i64) -> Data {
+        Data::Integer(Some(i))
+    }
+
+    fn string(s: &str) -> Data {
+        Data::String(s.to_string())
+    }
+
+    fn pointer(rcid: i64) -> Vec<Data> {
+        vec![int(130 | rcid << 8), int(1), int(1), int(255)]
+    }
+
+    fn feature(rcid: i64, rver: i64, ruin: i64) -> Record {
+        let mut record = Record::new();
+        for (label, value) in [
+            ("RCNM", 100),
+            ("RCID", rcid),
+            ("RVER", rver),
+            ("RUIN", ruin),
+        ] {
+            record.set("FRID", label, int(value));
+        }
+        record
+    }
+
+    fn dsid(edition: &str, update: &str, date: &str) -> Record {
+        let mut record = Record::new();
+        record.set("DSID", "EXPP", int(if update == "0" { 1 } else { 2 }));
+        record.set("DSID", "INTU", int(5));
+        record.set("DSID", "DSNM", string("GB4X0000.000"));
+        for (label, value) in [
+            ("EDTN", edition),
+            ("UPDN", update),
+            ("UADT", date),
+            ("ISDT", date),
+        ] {
+            record.set("DSID", label, string(value));
+        }
+        record.set("DSID", "AGEN", int(540));
+        record
+    }
+
+    fn data_set() -> DataSet {
+        let bytes = FileBuilder::new()
+            .field("DSID", "Data Set Identification Field", "UPDN!UADT", "(2A)")
+            .record(&[(
+                "DSID",
+                &[("UPDN", string("0")), ("UADT", string("20200101"))],
+            )])
+            .build();
+        let mut data_set = DataSet::read(&bytes[..]).unwrap();
+        data_set.general = dsid("2", "0", "20200101");
+        let mut base = feature(1, 1, 1);
+        base.insert_rows(
+            "ATTF",
+            &["ATTL", "ATVL"],
+            vec![vec![int(87), string("0")], vec![int(88), string("5")]],
+        );
+        base.insert_rows(
+            "FSPT",
+            &["NAME", "ORNT", "USAG", "MASK"],
+            vec![pointer(1), pointer(2), pointer(3)],
+        );
+        data_set.features.insert((100, 1), base);
+        data_set.features.insert((100, 3), feature(3, 1, 1));
+        let mut edge = Record::new();
+        for (label, value) in [("RCNM", 130), ("RCID", 1), ("RVER", 1), ("RUIN", 1)] {
+            edge.set("VRID", label, int(value));
+        }
+        let coordinates = (1..=3).map(|i| vec![int(i), int(i)]).collect();
+        edge.insert_rows("SG2D", &["YCOO", "XCOO"], coordinates);
+        data_set.vectors.insert((130, 1), edge);
+        data_set
+    }
+
+    #[test]
+    fn apply_updates() {
+        let mut data_set = data_set();
+        data_set.apply_record(&dsid("2", "1", "20200201")).unwrap();
+        assert_eq!(data_set.general.get_str("DSID", "UPDN").unwrap(), "1");
+
+        let mut update = feature(1, 2, 3);
+        update.insert_rows(
+            "ATTF",
+            &["ATTL", "ATVL"],
+            vec![
+                vec![int(88), string("10")],
+                vec![int(87), string(DELETE)],
+                vec![int(116), string("Name")],
+            ],
+        );
+        for (label, value) in [("FSUI", 2), ("FSIX", 2), ("NSPT", 1)] {
+            update.set("FSPC", label, int(value));
+        }
+        data_set.apply_record(&update).unwrap();
+        let base = data_set.feature((100, 1)).unwrap();
+        assert_eq!(base.get_i64("FRID", "RVER").unwrap(), Some(2));
+        let (_, attributes) = table(base, "ATTF").unwrap();
+        assert_eq!(
+            attributes,
+            vec![vec![int(88), string("10")], vec![int(116), string("Name")]]
+        );
+        assert_eq!(base.rows("FSPT").unwrap(), &vec![pointer(1), pointer(3)]);
+        // The same update again is out of sequence
+        match data_set.apply_record(&update).unwrap_err().kind() {
+            ErrorKind::RecordVersionMismatch(100, 1, 2, 2) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+
+        let mut update = Record::new();
+        for (label, value) in [("RCNM", 130), ("RCID", 1), ("RVER", 2), ("RUIN", 3)] {
+            update.set("VRID", label, int(value));
+        }
+        for (label, value) in [("CCUI", 1), ("CCIX", 2), ("CCNC", 1)] {
+            update.set("SGCC", label, int(value));
+        }
+        update.insert_rows("SG2D", &["YCOO", "XCOO"], vec![vec![int(9), int(9)]]);
+        data_set.apply_record(&update).unwrap();
+        let ycoo: Vec<_> = data_set
+            .vector((130, 1))
+            .unwrap()
+            .rows("SG2D")
+            .unwrap()
+            .iter()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(ycoo, vec![int(1), int(9), int(2), int(3)]);
+
+        data_set.apply_record(&feature(2, 1, 1)).unwrap();
+        assert!(data_set.apply_record(&feature(2, 1, 1)).is_err());
+        data_set.apply_record(&feature(3, 2, 2)).unwrap();
+        assert!(data_set.feature((100, 3)).is_none());
+        assert!(data_set.apply_record(&feature(3, 3, 2)).is_err());
+
+        let mut update = feature(2, 2, 3);
+        for (label, value) in [("FSUI", 2), ("FSIX", 1), ("NSPT", 1)] {
+            update.set("FSPC", label, int(value));
+        }
+        match data_set.apply_record(&update).unwrap_err().kind() {
+            ErrorKind::BadUpdateControl(control, 1, 1) => assert_eq!(control, "FSPC"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        for (index, count) in [(-1, 1), (1, -1), (1, i64::MAX)] {
+            let mut update = feature(2, 2, 3);
+            for (label, value) in [("FSUI", 3), ("FSIX", index), ("NSPT", count)] {
+                update.set("FSPC", label, int(value));
+            }
+            match data_set.apply_record(&update).unwrap_err().kind() {
+                ErrorKind::BadUpdateControl(_, i, c) => assert_eq!((*i, *c), (index, count)),
+                kind => panic!("unexpected error: {}", kind),
+            }
+        }
+    }
+
+    #[test]
+    fn keep_history() {
+        let mut data_set = data_set();
+        assert!(data_set.history().is_none());
+        data_set.apply_record(&feature(3, 2, 2)).unwrap();
+        data_set.keep_history();
+        data_set.apply_record(&dsid("2", "1", "20200201")).unwrap();
+        let mut update = feature(1, 2, 3);
+        update.insert_rows("ATTF", &["ATTL", "ATVL"], vec![vec![int(88), string("10")]]);
+        for (label, value) in [("FSUI", 2), ("FSIX", 2), ("NSPT", 1)] {
+            update.set("FSPC", label, int(value));
+        }
+        data_set.apply_record(&update).unwrap();
+        // Records that fail to apply leave no history
+        assert!(data_set.apply_record(&feature(9, 2, 2)).is_err());
+
+        let history = data_set.history().unwrap();
+        let changed: Vec<_> = history
+            .changes_in(1)
+            .map(|(name, change)| (name, change.version, change.instruction))
+            .collect();
+        assert_eq!(changed, vec![((100, 1), 2, UpdateInstruction::Modify)]);
+        let change = &history.feature((100, 1))[0];
+        assert_eq!(change.attributes, vec![(88, Some("10".to_string()))]);
+        assert_eq!(change.pointers[0].tag, "FSPT");
+        assert_eq!(change.pointers[0].instruction, UpdateInstruction::Delete);
+        assert_eq!((change.pointers[0].index, change.pointers[0].count), (2, 1));
+        assert!(history.feature((100, 3)).is_empty());
+        assert!(history.feature((100, 9)).is_empty());
+    }
+
+    #[test]
+    fn apply_update_as_a_whole() {
+        let mut data_set = data_set();
+        let bytes = FileBuilder::new()
+            .field(
+                "DSID",
+                "Data Set Identification Field",
+                "EXPP!INTU!DSNM!EDTN!UPDN!UADT!ISDT!AGEN",
+                "(b11,b11,A,A,A,A,A,b12)",
+            )
+            .field(
+                "FRID",
+                "Feature Record Identifier Field",
+                "RCNM!RCID!RVER!RUIN",
+                "(b11,b14,b12,b11)",
+            )
+            .raw_record(dsid("2", "1", "20200201"))
+            .raw_record(feature(3, 2, 2))
+            .raw_record(feature(9, 2, 2))
+            .build();
+        match data_set.apply_update(&bytes[..]).unwrap_err().kind() {
+            ErrorKind::MissingRecord(100, 9) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        assert_eq!(data_set.identification().unwrap().update, 0);
+        assert!(data_set.feature((100, 3)).is_some());
+    }
+
+    #[test]
+    fn check_updates() {
+        let mut data_set = data_set();
+        let error = |data_set: &mut DataSet, update| data_set.apply_record(&update).unwrap_err();
+        match error(&mut data_set, dsid("2", "2", "20200201")).kind() {
+            ErrorKind::MissingUpdate(cell, 1) => assert_eq!(cell, "GB4X0000.000"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        match error(&mut data_set, dsid("3", "1", "20200201")).kind() {
+            ErrorKind::EditionMismatch(file, base, edition) => {
+                assert_eq!(
+                    (&file[..], &base[..], &edition[..]),
+                    ("GB4X0000.001", "2", "3")
+                )
+            }
+            kind => panic!("unexpected error: {}", kind),
+        }
+        match error(&mut data_set, dsid("2", "1", "20191231")).kind() {
+            ErrorKind::UpdateDateMismatch(file, ..) => assert_eq!(file, "GB4X0000.001"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        match error(&mut data_set, dsid("0", "1", "20200201")).kind() {
+            ErrorKind::CellCancelled(cell, 1) => assert_eq!(cell, "GB4X0000.000"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        assert_eq!(data_set.identification().unwrap().update, 0);
+        data_set.apply_record(&dsid("2", "1", "20200201")).unwrap();
+        match error(&mut data_set, dsid("2", "1", "20200201")).kind() {
+            ErrorKind::UpdateOutOfOrder(file, 1, 2) => assert_eq!(file, "GB4X0000.001"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Lookup of attributes. The ATTL subfield of ATTF, NATF and ATTV is a numeric code from the
+//! S-57 attribute catalogue, this maps it to the acronym (e.g. "DRVAL1") and tells what kind of
+//! value ATVL holds.
+//!
+//! The catalogue is loaded from a CSV in the layout of the widely distributed
+//! `s57attributes.csv`. With the `attributes` feature a table of every attribute is embedded in
+//! the crate. The expected input lists of enumerated and list attributes (e.g. 1 for white in
+//! COLOUR) are added from a CSV in the layout of `s57expectedinput.csv`, after which values are
+//! checked against them when decoded.
+use crate::catalog::Result;
+use crate::error::ErrorKind;
+use crate::objects::split_csv;
+use crate::units::{Units, DEPTH_ATTRIBUTES, HEIGHT_ATTRIBUTES};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+
+#[cfg(feature = "attributes")]
+use lazy_static::lazy_static;
+
+/// The type of the value of an attribute, from the Attributetype column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeType {
+    Enumerated,  // E, one code from the expected input list
+    List,        // L, codes from the expected input list separated by commas
+    Float,       // F
+    Integer,     // I
+    CodedString, // A, a string in a given format, e.g. a date
+    FreeText,    // S
+}
+
+/// Where an attribute is used, from the Class column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeClass {
+    Feature,      // F, in ATTF
+    National,     // N, in NATF
+    Spatial,      // S, in ATTV
+    Cartographic, // $
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDefinition {
+    pub code: u16,                       // ATTL
+    pub acronym: String,                 // e.g. "DRVAL1"
+    pub name: String,                    // e.g. "Depth range value 1"
+    pub kind: AttributeType,             // Attributetype
+    pub class: AttributeClass,           // Class
+    pub expected: BTreeMap<u16, String>, // The meaning of every expected input, if known
+}
+
+/// An ATVL decoded according to the type of its attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Unknown, // An empty ATVL, the value is not known
+    Enumerated(u16),
+    List(Vec<u16>), // In the order given, which matters for e.g. COLOUR
+    Float(f64),
+    Integer(i64),
+    Text(String),
+    Raw(String), // A value that does not match its type or expected input list
+}
+
+impl AttributeDefinition {
+    /// Whether the value is a depth, in the depth units (DUNI) of the data set.
+    pub fn is_depth(&self) -> bool {
+        DEPTH_ATTRIBUTES.contains(&self.code)
+    }
+
+    /// Whether the value is a height, in the height units (HUNI) of the data set.
+    pub fn is_height(&self) -> bool {
+        HEIGHT_ATTRIBUTES.contains(&self.code)
+    }
+
+    /// The meaning of the expected input `code`, e.g. "white" for 1 in COLOUR.
+    pub fn meaning(&self, code: u16) -> Option<&str> {
+        self.expected.get(&code).map(String::as_str)
+    }
+
+    // An expected input code, checked against the expected input list if there is one.
+    fn expected_code(&self, value: &str) -> Option<u16> {
+        let code = value.trim().parse().ok()?;
+        if self.expected.is_empty() || self.expected.contains_key(&code) {
+            Some(code)
+        } else {
+            None
+        }
+    }
+
+    /// Decodes the ATVL `atvl` of the attribute. A value that can not be decoded is returned as
+    /// [`AttributeValue::Raw`].
+    pub fn decode(&self, atvl: &str) -> AttributeValue {
+        let raw = || AttributeValue::Raw(atvl.to_string());
+        if atvl.is_empty() {
+            return AttributeValue::Unknown;
+        }
+        match self.kind {
+            AttributeType::Enumerated => self
+                .expected_code(atvl)
+                .map_or_else(raw, AttributeValue::Enumerated),
+            AttributeType::List => atvl
+                .split(',')
+                .map(|value| self.expected_code(value))
+                .collect::<Option<Vec<u16>>>()
+                .map_or_else(raw, AttributeValue::List),
+            AttributeType::Float => atvl
+                .trim()
+                .parse()
+                .map_or_else(|_| raw(), AttributeValue::Float),
+            AttributeType::Integer => atvl
+                .trim()
+                .parse()
+                .map_or_else(|_| raw(), AttributeValue::Integer),
+            AttributeType::CodedString | AttributeType::FreeText => {
+                AttributeValue::Text(atvl.to_string())
+            }
+        }
+    }
+
+    /// The value `value` of the attribute in metres if it is a depth or height, with `units`
+    /// the units of the data set, otherwise the value as is.
+    pub fn to_metres(&self, units: &Units, value: f64) -> f64 {
+        units.to_metres(self.code, value)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AttributeCatalog(HashMap<u16, AttributeDefinition>);
+
+#[cfg(feature = "attributes")]
+lazy_static! {
+    static ref BUILTIN: AttributeCatalog =
+        AttributeCatalog::from_csv(&include_bytes!("../data/attributes.csv")[..]).unwrap();
+}
+
+impl AttributeCatalog {
+    /// Reads a catalogue from CSV with the columns Code, Attribute, Acronym, Attributetype and
+    /// Class. The header line, empty lines and lines starting with '#' are skipped.
+    pub fn from_csv<R: BufRead>(rdr: R) -> Result<AttributeCatalog> {
+        let mut catalog = HashMap::new();
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line.map_err(|err| ErrorKind::IOError(err.kind()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || ErrorKind::BadAttributeCatalog(i + 1);
+            let columns = split_csv(line);
+            if columns[0] == "Code" {
+                continue;
+            }
+            if columns.len() < 5 {
+                return Err(bad().into());
+            }
+            let kind = match columns[3].trim() {
+                "E" => AttributeType::Enumerated,
+                "L" => AttributeType::List,
+                "F" => AttributeType::Float,
+                "I" => AttributeType::Integer,
+                "A" => AttributeType::CodedString,
+                "S" => AttributeType::FreeText,
+                _ => return Err(bad().into()),
+            };
+            let class = match columns[4].trim() {
+                "F" => AttributeClass::Feature,
+                "N" => AttributeClass::National,
+                "S" => AttributeClass::Spatial,
+                "$" => AttributeClass::Cartographic,
+                _ => return Err(bad().into()),
+            };
+            let attribute = AttributeDefinition {
+                code: columns[0].trim().parse().map_err(|_| bad())?,
+                acronym: columns[2].trim().to_string(),
+                name: columns[1].trim().to_string(),
+                kind,
+                class,
+                expected: BTreeMap::new(),
+            };
+            catalog.insert(attribute.code, attribute);
+        }
+        Ok(AttributeCatalog(catalog))
+    }
+
+    /// Adds expected input lists from CSV with the columns Code, ID and Meaning, where Code is
+    /// the ATTL of an attribute already in the catalogue. The header line, empty lines and lines
+    /// starting with '#' are skipped.
+    pub fn add_expected_input<R: BufRead>(&mut self, rdr: R) -> Result<()> {
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line.map_err(|err| ErrorKind::IOError(err.kind()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || ErrorKind::BadAttributeCatalog(i + 1);
+            let columns = split_csv(line);
+            if columns[0] == "Code" {
+                continue;
+            }
+            if columns.len() < 3 {
+                return Err(bad().into());
+            }
+            let attl: u16 = columns[0].trim().parse().map_err(|_| bad())?;
+            let id = columns[1].trim().parse().map_err(|_| bad())?;
+            let attribute = self.0.get_mut(&attl).ok_or_else(bad)?;
+            attribute.expected.insert(id, columns[2].trim().to_string());
+        }
+        Ok(())
+    }
+
+    /// Decodes the ATVL `atvl` of the attribute `attl`. Values of attributes not in the
+    /// catalogue are returned as [`AttributeValue::Raw`].
+    pub fn decode(&self, attl: u16, atvl: &str) -> AttributeValue {
+        match self.get(attl) {
+            Some(attribute) => attribute.decode(atvl),
+            None => AttributeValue::Raw(atvl.to_string()),
+        }
+    }
+
+    /// The catalogue embedded with the `attributes` feature.
+    #[cfg(feature = "attributes")]
+    pub fn builtin() -> &'static AttributeCatalog {
+        &BUILTIN
+    }
+
+    pub fn get(&self, attl: u16) -> Option<&AttributeDefinition> {
+        self.0.get(&attl)
+    }
+
+    /// Every attribute of the catalogue, in no particular order.
+    pub fn attributes(&self) -> impl Iterator<Item = &AttributeDefinition> {
+        self.0.values()
+    }
+
+    pub fn by_acronym(&self, acronym: &str) -> Option<&AttributeDefinition> {
+        self.0
+            .values()
+            .find(|attribute| attribute.acronym == acronym)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{DepthUnit, HeightUnit, PositionalUnit};
+
+    #[test]
+    fn read_csv() {
+        let csv = concat!(
+            "\"Code\",\"Attribute\",\"Acronym\",\"Attributetype\",\"Class\"\n",
+            "75,Colour,COLOUR,L,F\n",
+            "87,Depth range value 1,DRVAL1,F,F\n",
+            "301,Object name in national language,NOBJNM,S,N\n",
+        );
+        let catalog = AttributeCatalog::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(catalog.get(75).unwrap().kind, AttributeType::List);
+        assert_eq!(catalog.get(301).unwrap().class, AttributeClass::National);
+        let drval1 = catalog.by_acronym("DRVAL1").unwrap();
+        assert!(drval1.is_depth() && !drval1.is_height());
+        let units = Units {
+            depth: DepthUnit::Feet,
+            height: HeightUnit::Metres,
+            positional: PositionalUnit::Metres,
+        };
+        assert!((drval1.to_metres(&units, 10.0) - 3.048).abs() < 1e-9);
+        assert!(AttributeCatalog::from_csv("75,Colour,COLOUR,X,F".as_bytes()).is_err());
+        assert!(AttributeCatalog::from_csv("75,Colour,COLOUR".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn decode_values() {
+        let csv = "75,Colour,COLOUR,L,F\n87,Depth range value 1,DRVAL1,F,F\n\
+                   133,Scale minimum,SCAMIN,I,F\n116,Object name,OBJNAM,S,F\n\
+                   187,Water level effect,WATLEV,E,F\n";
+        let mut catalog = AttributeCatalog::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(catalog.decode(75, "2,4"), AttributeValue::List(vec![2, 4]));
+        catalog
+            .add_expected_input("\"Code\",\"ID\",\"Meaning\"\n75,1,white\n75,2,black\n".as_bytes())
+            .unwrap();
+        assert_eq!(catalog.decode(75, "2,1"), AttributeValue::List(vec![2, 1]));
+        assert_eq!(
+            catalog.decode(75, "2,4"),
+            AttributeValue::Raw("2,4".to_string())
+        );
+        assert_eq!(catalog.get(75).unwrap().meaning(1), Some("white"));
+        assert_eq!(catalog.decode(187, "3"), AttributeValue::Enumerated(3));
+        assert_eq!(catalog.decode(87, "5.2"), AttributeValue::Float(5.2));
+        assert_eq!(catalog.decode(133, "22000"), AttributeValue::Integer(22000));
+        assert_eq!(
+            catalog.decode(133, "x"),
+            AttributeValue::Raw("x".to_string())
+        );
+        assert_eq!(
+            catalog.decode(116, "Sm\u{f6}gen"),
+            AttributeValue::Text("Sm\u{f6}gen".to_string())
+        );
+        assert_eq!(catalog.decode(87, ""), AttributeValue::Unknown);
+        assert_eq!(
+            catalog.decode(999, "1"),
+            AttributeValue::Raw("1".to_string())
+        );
+        assert!(catalog.add_expected_input("76,1,plain".as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn builtin() {
+        let catalog = AttributeCatalog::builtin();
+        assert_eq!(catalog.get(116).unwrap().acronym, "OBJNAM");
+        assert_eq!(catalog.get(87).unwrap().name, "Depth range value 1");
+        assert_eq!(
+            catalog.by_acronym("NOBJNM").unwrap().name,
+            "Object name in national language"
+        );
+        assert_eq!(
+            catalog.by_acronym("VALSOU").unwrap().kind,
+            AttributeType::Float
+        );
+        assert_eq!(catalog.get(402).unwrap().class, AttributeClass::Spatial);
+    }
+}
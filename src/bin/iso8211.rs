@@ -0,0 +1,126 @@
+//! Command line tool for inspecting ISO 8211 files.
+use rust_s57::catalog::{Catalog, FieldTree, Schema};
+use rust_s57::raw::{raw_records, RawRecord};
+use std::collections::HashSet;
+use std::fs::File;
+use std::process;
+
+type Result<T> = std::result::Result<T, failure::Error>;
+
+const USAGE: &str = "usage: iso8211 <command> <file> [args]
+
+commands:
+    tree                print the field hierarchy declared by the DDR
+    hexdump [record]    dump a record with its structure annotated, record 0 (the
+                        default) is the DDR";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.as_slice() {
+        [cmd, file] if cmd == "tree" => tree(file),
+        [cmd, file] if cmd == "hexdump" => hexdump(file, 0),
+        [cmd, file, record] if cmd == "hexdump" => match record.parse() {
+            Ok(record) => hexdump(file, record),
+            Err(_) => Err(failure::format_err!("Bad record number '{}'", record)),
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = result {
+        for c in err.iter_chain() {
+            eprintln!("{}", c);
+        }
+        process::exit(1);
+    }
+}
+
+fn tree(file: &str) -> Result<()> {
+    let schema = Catalog::read_schema_only(File::open(file)?)?;
+    if let Some(title) = schema.title().filter(|t| !t.is_empty()) {
+        println!("{}", title);
+    }
+    let tree = schema.field_tree();
+    let mut printed = HashSet::new();
+    for root in tree.roots() {
+        print_field(&schema, &tree, root, 0, &mut printed);
+    }
+    Ok(())
+}
+
+fn print_field<'a>(
+    schema: &Schema,
+    tree: &FieldTree<'a>,
+    tag: &'a str,
+    depth: usize,
+    printed: &mut HashSet<&'a str>,
+) {
+    let indent = "  ".repeat(depth);
+    // A malformed file control field may contain cycles
+    if !printed.insert(tag) {
+        println!("{}{} (cycle)", indent, tag);
+        return;
+    }
+    match schema.field(tag) {
+        Some(field) => {
+            println!("{}{}  {}", indent, field.tag, field.name);
+            for (label, format) in field.labels.iter().zip(field.formats.iter()) {
+                println!("{}  - {}: {}", indent, label, format);
+            }
+        }
+        None => println!("{}{}  (not described)", indent, tag),
+    }
+    for child in tree.children(tag) {
+        print_field(schema, tree, child, depth + 1, printed);
+    }
+}
+
+fn hexdump(file: &str, index: usize) -> Result<()> {
+    let record = match raw_records(File::open(file)?).nth(index) {
+        Some(record) => record?,
+        None => return Err(failure::format_err!("There is no record {}", index)),
+    };
+    let notes = annotations(&record);
+    for (line, chunk) in record.bytes.chunks(16).enumerate() {
+        let offset = line * 16;
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if (0x20..0x7f).contains(&b) {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let line_notes: Vec<String> = notes
+            .iter()
+            .filter(|(at, _)| (offset..offset + chunk.len()).contains(at))
+            .map(|(at, note)| format!("{:04x} {}", at, note))
+            .collect();
+        let line = format!("{:08x}  {:<47}  |{:<16}|", offset, hex.join(" "), ascii);
+        if line_notes.is_empty() {
+            println!("{}", line);
+        } else {
+            println!("{}  {}", line, line_notes.join(", "));
+        }
+    }
+    Ok(())
+}
+
+// Offsets in the record paired with a description of what starts or ends there.
+fn annotations(record: &RawRecord) -> Vec<(usize, String)> {
+    let mut notes = vec![(0, "leader".to_string())];
+    for field in record.fields.iter() {
+        notes.push((field.entry.start, format!("dir {}", field.tag)));
+    }
+    notes.push((record.field_area - 1, "dir FT".to_string()));
+    for field in record.fields.iter() {
+        notes.push((field.data.start, format!("field {}", field.tag)));
+        notes.push((field.data.end - 1, format!("{} FT", field.tag)));
+    }
+    notes.sort_by_key(|(at, _)| *at);
+    notes
+}
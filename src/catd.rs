@@ -0,0 +1,116 @@
+//! Types for the subfields of the Catalogue Directory field (CATD) of the entries of a
+//! CATALOG.031.
+use crate::catalog::{Record, Result};
+use crate::coverage::BoundingBox;
+use crate::error::ErrorKind;
+use crate::volume::{resolve_file, Volume};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+/// IMPL, how the file of a catalogue entry is implemented.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Implementation {
+    Ascii,         // "ASC", e.g. the catalogue itself
+    Binary,        // "BIN", an ISO 8211 file such as a cell
+    Text,          // "TXT"
+    Picture,       // "TIF"
+    Other(String), // Any other code, as it is written
+}
+
+impl Implementation {
+    pub fn from_code(code: &str) -> Implementation {
+        match code {
+            "ASC" => Implementation::Ascii,
+            "BIN" => Implementation::Binary,
+            "TXT" => Implementation::Text,
+            "TIF" => Implementation::Picture,
+            _ => Implementation::Other(code.to_string()),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            Implementation::Ascii => "ASC",
+            Implementation::Binary => "BIN",
+            Implementation::Text => "TXT",
+            Implementation::Picture => "TIF",
+            Implementation::Other(code) => code,
+        }
+    }
+}
+
+/// A catalogue entry, the CATD field of a record of a CATALOG.031. Subfields that are empty are
+/// `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogRecord {
+    pub rcnm: String,
+    pub rcid: i64,
+    pub file: PathBuf, // Relative to the exchange set root
+    pub lfil: Option<String>,
+    pub volm: Option<Volume>,
+    pub impl_: Option<Implementation>,
+    pub bounds: Option<BoundingBox>, // SLAT, WLON, NLAT and ELON, only cells have them
+    pub crcs: Option<u32>,
+    pub comt: Option<String>,
+}
+
+impl TryFrom<&Record> for CatalogRecord {
+    type Error = crate::error::Error;
+    fn try_from(record: &Record) -> Result<CatalogRecord> {
+        let missing = |label: &str| ErrorKind::MissingSubfield("CATD".into(), label.into());
+        let file = record.file().ok_or_else(|| missing("FILE"))?;
+        let crcs = match record.catd_str("CRCS") {
+            Some(crc) => Some(
+                u32::from_str_radix(crc.trim(), 16)
+                    .map_err(|_| ErrorKind::BadSubfieldValue("CRCS".into(), crc.into()))?,
+            ),
+            None => None,
+        };
+        Ok(CatalogRecord {
+            rcnm: record.get_str("CATD", "RCNM")?.to_string(),
+            rcid: record
+                .get_i64("CATD", "RCID")?
+                .ok_or_else(|| missing("RCID"))?,
+            file: resolve_file(Path::new(""), file),
+            lfil: record.long_file_name().map(String::from),
+            volm: record.volume().transpose()?,
+            impl_: record.implementation().map(Implementation::from_code),
+            bounds: record.bounds(),
+            crcs,
+            comt: record.catd_str("COMT").map(String::from),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::test_support::sample_catalog;
+
+    #[test]
+    fn catalog_records() {
+        let bytes = sample_catalog();
+        let entries: Vec<CatalogRecord> = Catalog::new(&bytes[..])
+            .unwrap()
+            .map(|r| CatalogRecord::try_from(&r.unwrap()).unwrap())
+            .collect();
+        assert_eq!(entries[0].rcnm, "CD");
+        assert_eq!(entries[0].file, Path::new("CATALOG.031"));
+        assert_eq!(entries[0].impl_, Some(Implementation::Ascii));
+        assert_eq!(entries[0].bounds, None);
+        assert_eq!(entries[1].rcid, 2);
+        assert_eq!(entries[1].volm.unwrap().to_string(), "V01X01");
+        assert_eq!(entries[1].impl_, Some(Implementation::Binary));
+        assert!(entries[1].bounds.unwrap().contains(51.5, 0.5));
+        assert_eq!(entries[1].lfil, None);
+        assert_eq!(entries[1].crcs, None);
+    }
+
+    #[test]
+    fn implementation_codes() {
+        assert_eq!(Implementation::from_code("TIF"), Implementation::Picture);
+        assert_eq!(Implementation::from_code("XYZ").code(), "XYZ");
+        assert_eq!(Implementation::Text.code(), "TXT");
+    }
+}
@@ -0,0 +1,108 @@
+//! Parsing of S-57 ENC cell file names. A cell name has the form "CCPXXXXX.EEE" where CC is the
+//! producer code, P the navigational purpose (1-6), XXXXX the individual cell code and EEE the
+//! extension, "000" for a base cell and the update number for update files.
+use crate::catalog::Result;
+use crate::dsid::IntendedUsage;
+use crate::error::ErrorKind;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CellName {
+    pub producer: String, // Producer code, e.g. "GB"
+    pub purpose: u8,      // Navigational purpose, 1 (overview) to 6 (berthing)
+    pub cell_id: String,  // Individual cell code
+    pub update: u32,      // Update number from the extension, 0 for a base cell
+}
+
+impl FromStr for CellName {
+    type Err = crate::error::Error;
+    fn from_str(value: &str) -> Result<CellName> {
+        let bad = || ErrorKind::BadCellName(value.to_string());
+        let (base, ext) = match value.split_once('.') {
+            Some(parts) => parts,
+            None => return Err(bad().into()),
+        };
+        if base.len() != 8
+            || ext.len() != 3
+            || !base.is_ascii()
+            || !base.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(bad().into());
+        }
+        let purpose = match base[2..3].parse::<u8>() {
+            Ok(p @ 1..=6) => p,
+            _ => return Err(bad().into()),
+        };
+        if !ext.chars().all(|c| c.is_ascii_digit()) {
+            return Err(bad().into());
+        }
+        Ok(CellName {
+            producer: base[..2].to_string(),
+            purpose,
+            cell_id: base[3..].to_string(),
+            update: ext.parse().map_err(|_| bad())?,
+        })
+    }
+}
+
+impl Display for CellName {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}.{:03}", self.base_name(), self.update)
+    }
+}
+
+impl CellName {
+    /// The file name without extension, which is what the DSNM subfield of DSID holds.
+    pub fn base_name(&self) -> String {
+        format!("{}{}{}", self.producer, self.purpose, self.cell_id)
+    }
+
+    pub fn intended_usage(&self) -> IntendedUsage {
+        // The purpose digit is validated to 1-6 when parsing
+        IntendedUsage::from_code(i64::from(self.purpose)).unwrap()
+    }
+
+    pub fn is_base_cell(&self) -> bool {
+        self.update == 0
+    }
+
+    /// Whether the name agrees with the DSNM (data set name) and UPDN (update number) subfields
+    /// of the DSID field in the file. DSNM may be given with or without extension.
+    pub fn matches_dsid(&self, dsnm: &str, updn: u32) -> bool {
+        let dsnm = dsnm.split('.').next().unwrap_or("");
+        dsnm.eq_ignore_ascii_case(&self.base_name()) && updn == self.update
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cell_name() {
+        let name: CellName = "GB4X0000.002".parse().unwrap();
+        assert_eq!(
+            name,
+            CellName {
+                producer: "GB".to_string(),
+                purpose: 4,
+                cell_id: "X0000".to_string(),
+                update: 2,
+            }
+        );
+        assert_eq!(name.to_string(), "GB4X0000.002");
+        assert!(!name.is_base_cell());
+        assert_eq!(name.intended_usage(), IntendedUsage::Approach);
+        assert!(name.matches_dsid("GB4X0000.000", 2));
+        assert!(!name.matches_dsid("GB4X0000", 1));
+    }
+
+    #[test]
+    fn bad_cell_names() {
+        assert!("GB4X0000".parse::<CellName>().is_err());
+        assert!("GB7X0000.000".parse::<CellName>().is_err());
+        assert!("GB4X000.000".parse::<CellName>().is_err());
+        assert!("GB4X0000.TXT".parse::<CellName>().is_err());
+    }
+}
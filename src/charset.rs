@@ -0,0 +1,128 @@
+//! Decoding of character data that uses ISO 2022 escape sequences to switch character set in the
+//! middle of a subfield, which the standard allows when the leader's In Line Code Extension
+//! Indicator is 'E'. The escape sequences recognised are the ones S-57 uses for its lexical
+//! levels plus the ones to get back to ASCII.
+use crate::catalog::Result;
+use crate::error::ErrorKind;
+use failure::ResultExt;
+
+const ESCAPE: u8 = 0x1b;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Charset {
+    Utf8,   // ASCII, and UTF-8 for producers that ignore the lexical levels
+    Latin1, // ISO 8859-1, lexical level 1
+    Ucs2,   // ISO 10646 UCS-2 little endian, lexical level 2
+}
+
+impl Charset {
+    fn from_escape(seq: &[u8]) -> Result<Charset> {
+        match seq {
+            b"(B" | b"%@" | b"%G" => Ok(Charset::Utf8),
+            b"-A" => Ok(Charset::Latin1),
+            b"%/A" | b"%/@" => Ok(Charset::Ucs2),
+            _ => Err(ErrorKind::UnknownEscapeSequence(String::from_utf8_lossy(seq).into()).into()),
+        }
+    }
+}
+
+// An escape sequence is ESC, any number of intermediate bytes in 0x20..=0x2f and a final byte
+// in 0x30..=0x7e. Returns the sequence without the ESC.
+fn escape_sequence(units: &[u16]) -> Result<Vec<u8>> {
+    let mut seq = Vec::new();
+    for &unit in units {
+        match unit {
+            0x20..=0x2f => seq.push(unit as u8),
+            0x30..=0x7e => {
+                seq.push(unit as u8);
+                return Ok(seq);
+            }
+            _ => break,
+        }
+    }
+    Err(ErrorKind::UnknownEscapeSequence(String::from_utf8_lossy(&seq).into()).into())
+}
+
+fn decode_run(bytes: &[u8], charset: Charset, out: &mut String) -> Result<()> {
+    match charset {
+        Charset::Utf8 => {
+            out.push_str(std::str::from_utf8(bytes).with_context(|&err| ErrorKind::UtfError(err))?)
+        }
+        Charset::Latin1 => out.extend(bytes.iter().map(|&b| b as char)),
+        Charset::Ucs2 => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Decodes `bytes`, starting out as ASCII/UTF-8 and switching character set whenever an escape
+/// sequence is encountered.
+pub(crate) fn decode_extended(bytes: &[u8]) -> Result<String> {
+    let mut out = String::new();
+    let mut charset = Charset::Utf8;
+    let mut i = 0;
+    while i < bytes.len() {
+        if charset == Charset::Ucs2 {
+            let units = bytes[i..]
+                .chunks(2)
+                .map(|c| u16::from(c[0]) | c.get(1).map_or(0, |&b| u16::from(b) << 8))
+                .collect::<Vec<u16>>();
+            match units.iter().position(|&u| u == u16::from(ESCAPE)) {
+                Some(pos) => {
+                    out.push_str(&String::from_utf16_lossy(&units[..pos]));
+                    let seq = escape_sequence(&units[pos + 1..])?;
+                    charset = Charset::from_escape(&seq)?;
+                    i += 2 * (pos + 1 + seq.len());
+                }
+                None => {
+                    out.push_str(&String::from_utf16_lossy(&units));
+                    i = bytes.len();
+                }
+            }
+        } else {
+            match bytes[i..].iter().position(|&b| b == ESCAPE) {
+                Some(pos) => {
+                    decode_run(&bytes[i..i + pos], charset, &mut out)?;
+                    let units = bytes[i + pos + 1..]
+                        .iter()
+                        .map(|&b| u16::from(b))
+                        .collect::<Vec<u16>>();
+                    let seq = escape_sequence(&units)?;
+                    charset = Charset::from_escape(&seq)?;
+                    i += pos + 1 + seq.len();
+                }
+                None => {
+                    decode_run(&bytes[i..], charset, &mut out)?;
+                    i = bytes.len();
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_ascii() {
+        assert_eq!(decode_extended(b"Hejsa").unwrap(), "Hejsa");
+    }
+
+    #[test]
+    fn switch_to_latin1_and_back() {
+        let bytes = b"Sm\x1b-A\xf6g\x1b(Ben";
+        assert_eq!(decode_extended(bytes).unwrap(), "Sm\u{f6}gen");
+    }
+
+    #[test]
+    fn switch_to_ucs2_and_back() {
+        let bytes = b"A\x1b%/AB\x00\xe5\x00\x1b\x00(\x00B\x00C";
+        assert_eq!(decode_extended(bytes).unwrap(), "AB\u{e5}C");
+    }
+
+    #[test]
+    fn unknown_escape_sequence() {
+        assert!(decode_extended(b"A\x1b$B").is_err());
+    }
+}
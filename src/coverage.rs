@@ -0,0 +1,311 @@
+//! The area covered by an exchange set, as the union of the bounding boxes of its catalogue
+//! entries and, when the cells have been read, of the polygons of their M_COVR features. Meant
+//! for answering "is this position covered" without opening any cell.
+use crate::catalog::Record;
+use crate::data_parser::Data;
+
+/// An area bounded by two parallels and two meridians, in degrees. A box crossing the
+/// antimeridian has `west` east of `east`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// The smallest box around a ring of (lat, lon) vertices, e.g. of an M_COVR feature. A ring
+    /// spanning more than 180 degrees of longitude is taken to cross the antimeridian.
+    pub fn from_ring(ring: &[(f64, f64)]) -> Option<BoundingBox> {
+        let (&(lat, lon), rest) = ring.split_first()?;
+        let mut b = BoundingBox {
+            south: lat,
+            west: lon,
+            north: lat,
+            east: lon,
+        };
+        for &(lat, lon) in rest {
+            b.south = b.south.min(lat);
+            b.north = b.north.max(lat);
+            b.west = b.west.min(lon);
+            b.east = b.east.max(lon);
+        }
+        if b.east - b.west > 180.0 {
+            let lons = ring.iter().map(|&(_, lon)| lon);
+            b.west = lons.clone().filter(|&lon| lon >= 0.0).fold(180.0, f64::min);
+            b.east = lons.filter(|&lon| lon < 0.0).fold(-180.0, f64::max);
+        }
+        Some(b)
+    }
+
+    fn crosses_antimeridian(&self) -> bool {
+        self.west > self.east
+    }
+
+    // The degrees of longitude spanned, going east from west.
+    fn width(&self) -> f64 {
+        if self.crosses_antimeridian() {
+            self.east - self.west + 360.0
+        } else {
+            self.east - self.west
+        }
+    }
+
+    // How far east of the western edge of this box the western edge of other is, 0 to 360.
+    fn offset(&self, other: &BoundingBox) -> f64 {
+        (other.west - self.west).rem_euclid(360.0)
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let within_lon = if self.crosses_antimeridian() {
+            self.west <= lon || lon <= self.east
+        } else {
+            self.west <= lon && lon <= self.east
+        };
+        self.south <= lat && lat <= self.north && within_lon
+    }
+
+    /// Whether `other` lies entirely within this box.
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.south <= other.south
+            && other.north <= self.north
+            && (self.width() >= 360.0 || self.offset(other) + other.width() <= self.width())
+    }
+
+    /// Whether the boxes have any point in common.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.south <= other.north
+            && other.south <= self.north
+            && (self.offset(other) <= self.width() || other.offset(self) <= other.width())
+    }
+
+    /// The smallest box containing both boxes. Of the two ways around the globe the one leaving
+    /// the smaller gap in longitude is taken.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        // Going east from the western edge of a until the whole of b is covered too
+        let span = |a: &BoundingBox, b: &BoundingBox| a.width().max(a.offset(b) + b.width());
+        let (west, width) = if span(self, other) <= span(other, self) {
+            (self.west, span(self, other))
+        } else {
+            (other.west, span(other, self))
+        };
+        let (west, east) = if width >= 360.0 {
+            (-180.0, 180.0)
+        } else if west + width > 180.0 {
+            (west, west + width - 360.0)
+        } else {
+            (west, west + width)
+        };
+        BoundingBox {
+            south: self.south.min(other.south),
+            west,
+            north: self.north.max(other.north),
+            east,
+        }
+    }
+}
+
+fn float(record: &Record, label: &str) -> Option<f64> {
+    match record.get("CATD")?.get(label)? {
+        Data::Float(f) => *f,
+        Data::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+impl Record {
+    /// The bounding box of a catalogue entry, from the SLAT, WLON, NLAT and ELON subfields of its
+    /// CATD field. Only cells have one.
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        Some(BoundingBox {
+            south: float(self, "SLAT")?,
+            west: float(self, "WLON")?,
+            north: float(self, "NLAT")?,
+            east: float(self, "ELON")?,
+        })
+    }
+}
+
+// Whether the point is inside the ring of (lat, lon) vertices, by counting crossings of a ray
+// going east. A ring crossing the antimeridian, as BoundingBox::from_ring tells, is unwrapped to
+// longitudes from 0 to 360 and so is the point.
+fn ring_contains(ring: &[(f64, f64)], lat: f64, lon: f64) -> bool {
+    let crosses = BoundingBox::from_ring(ring).is_some_and(|b| b.crosses_antimeridian());
+    let unwrap = |lon: f64| {
+        if crosses && lon < 0.0 {
+            lon + 360.0
+        } else {
+            lon
+        }
+    };
+    let lon = unwrap(lon);
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for i in 0..ring.len() {
+        let (lat_i, lon_i) = (ring[i].0, unwrap(ring[i].1));
+        let (lat_j, lon_j) = (ring[j].0, unwrap(ring[j].1));
+        if (lat_i > lat) != (lat_j > lat)
+            && lon < (lon_j - lon_i) * (lat - lat_i) / (lat_j - lat_i) + lon_i
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// The union of a number of boxes and polygons.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageArea {
+    boxes: Vec<BoundingBox>,
+    polygons: Vec<Vec<(f64, f64)>>, // Rings of (lat, lon) vertices
+}
+
+impl CoverageArea {
+    pub fn new() -> CoverageArea {
+        CoverageArea::default()
+    }
+
+    /// The union of the bounding boxes of the catalogue entries in `records`.
+    pub fn from_catalog(records: &[Record]) -> CoverageArea {
+        let mut area = CoverageArea::new();
+        for bounds in records.iter().filter_map(Record::bounds) {
+            area.add_box(bounds);
+        }
+        area
+    }
+
+    /// Adds `bounds` to the area. Boxes that end up within another box are dropped.
+    pub fn add_box(&mut self, bounds: BoundingBox) {
+        if self.boxes.iter().any(|b| b.contains_box(&bounds)) {
+            return;
+        }
+        self.boxes.retain(|b| !bounds.contains_box(b));
+        self.boxes.push(bounds);
+    }
+
+    /// Adds the polygon with the outer ring `ring` of (lat, lon) vertices, e.g. an M_COVR feature.
+    pub fn add_polygon(&mut self, ring: Vec<(f64, f64)>) {
+        self.polygons.push(ring);
+    }
+
+    pub fn boxes(&self) -> &[BoundingBox] {
+        &self.boxes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty() && self.polygons.is_empty()
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        self.boxes.iter().any(|b| b.contains(lat, lon))
+            || self
+                .polygons
+                .iter()
+                .any(|ring| ring_contains(ring, lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(south: f64, west: f64, north: f64, east: f64) -> BoundingBox {
+        BoundingBox {
+            south,
+            west,
+            north,
+            east,
+        }
+    }
+
+    #[test]
+    fn union() {
+        let mut area = CoverageArea::new();
+        assert!(area.is_empty());
+        area.add_box(bbox(51.4, 0.4, 51.6, 0.6));
+        area.add_box(bbox(51.0, 0.0, 52.0, 1.0));
+        area.add_box(bbox(51.5, 0.5, 51.6, 0.6));
+        area.add_box(bbox(55.0, 0.0, 56.0, 1.0));
+        assert_eq!(area.boxes().len(), 2);
+        assert!(area.contains(51.5, 0.9));
+        assert!(area.contains(55.5, 0.5));
+        assert!(!area.contains(53.0, 0.5));
+        area.add_polygon(vec![(53.0, 0.0), (54.0, 0.0), (53.0, 1.0)]);
+        assert!(area.contains(53.2, 0.2));
+        assert!(!area.contains(53.8, 0.8));
+        area.add_polygon(vec![
+            (60.0, 175.0),
+            (62.0, 175.0),
+            (62.0, -175.0),
+            (60.0, -175.0),
+        ]);
+        assert!(area.contains(61.0, 179.0));
+        assert!(area.contains(61.0, -178.0));
+        assert!(!area.contains(61.0, 0.0));
+        assert!(!area.contains(61.0, 170.0));
+    }
+
+    #[test]
+    fn antimeridian() {
+        let b = bbox(50.0, 170.0, 60.0, -170.0);
+        assert!(b.contains(55.0, 179.0));
+        assert!(b.contains(55.0, -175.0));
+        assert!(!b.contains(55.0, 0.0));
+        assert!(b.contains_box(&bbox(51.0, 171.0, 52.0, 172.0)));
+        assert!(b.contains_box(&bbox(51.0, 175.0, 52.0, -175.0)));
+        assert!(!b.contains_box(&bbox(51.0, 160.0, 52.0, 172.0)));
+        assert!(!bbox(50.0, -10.0, 60.0, 10.0).contains_box(&b));
+        assert!(bbox(-90.0, -180.0, 90.0, 180.0).contains_box(&b));
+    }
+
+    #[test]
+    fn intersection() {
+        let b = bbox(50.0, 0.0, 52.0, 2.0);
+        assert!(b.intersects(&bbox(51.0, 1.0, 53.0, 3.0)));
+        assert!(b.intersects(&bbox(51.0, -1.0, 51.5, 3.0)));
+        assert!(!b.intersects(&bbox(53.0, 0.0, 54.0, 2.0)));
+        assert!(!b.intersects(&bbox(50.0, 3.0, 52.0, 4.0)));
+        let pacific = bbox(50.0, 170.0, 60.0, -170.0);
+        assert!(pacific.intersects(&bbox(55.0, -175.0, 56.0, -160.0)));
+        assert!(bbox(55.0, 160.0, 56.0, 175.0).intersects(&pacific));
+        assert!(!pacific.intersects(&b));
+    }
+
+    #[test]
+    fn union_of_boxes() {
+        let b = bbox(50.0, 0.0, 52.0, 2.0);
+        assert_eq!(
+            b.union(&bbox(51.0, 3.0, 53.0, 4.0)),
+            bbox(50.0, 0.0, 53.0, 4.0)
+        );
+        assert_eq!(
+            bbox(50.0, 170.0, 52.0, 175.0).union(&bbox(50.0, -175.0, 52.0, -170.0)),
+            bbox(50.0, 170.0, 52.0, -170.0)
+        );
+        assert_eq!(
+            bbox(0.0, -100.0, 1.0, 0.0).union(&bbox(0.0, 0.0, 1.0, 100.0)),
+            bbox(0.0, -100.0, 1.0, 100.0)
+        );
+        assert_eq!(
+            bbox(0.0, -180.0, 1.0, 0.0).union(&bbox(0.0, 0.0, 1.0, 180.0)),
+            bbox(0.0, -180.0, 1.0, 180.0)
+        );
+    }
+
+    #[test]
+    fn ring_bounds() {
+        let ring = [(53.0, 0.0), (54.0, 0.5), (53.0, 1.0)];
+        assert_eq!(
+            BoundingBox::from_ring(&ring),
+            Some(bbox(53.0, 0.0, 54.0, 1.0))
+        );
+        let ring = [(50.0, 175.0), (52.0, -175.0), (51.0, 178.0)];
+        assert_eq!(
+            BoundingBox::from_ring(&ring),
+            Some(bbox(50.0, 175.0, 52.0, -175.0))
+        );
+        assert_eq!(BoundingBox::from_ring(&[]), None);
+    }
+}
@@ -1,21 +1,44 @@
-use crate::catalog::{Result, UNIT_SEPARATOR};
+use crate::charset::decode_extended;
 use crate::error::ErrorKind;
+use crate::iso8211::{Result, RECORD_SEPARATOR, UNIT_SEPARATOR};
 use failure::ResultExt;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
 use std::io::prelude::*;
 
 lazy_static! {
-    // Maybe a number folowed by A,I,R followed by maybe a parenthesied number
+    // Maybe a number folowed by A,I,R,B,C,X followed by maybe a parenthesied number or
+    // delimiter character
     // See tests
-    static ref FIELD_REGEX: Regex = Regex::new(r"^(\d+)?([AIR])(\(\d*\))?").unwrap();
+    static ref FIELD_REGEX: Regex =
+        Regex::new(r"^(\d+)?([AIRBCX])(\((\d*|[^\d)])\))?").unwrap();
+    // Maybe a number followed by b, 1 (unsigned) or 2 (signed) and the width in bytes
+    static ref BINARY_REGEX: Regex = Regex::new(r"^(\d+)?b([12])([124])$").unwrap();
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum ParseData {
     Fixed(ParseType, usize),
     Variable(ParseType),
+    // A variable length subfield ending with a delimiter of the user's choosing instead of the
+    // unit terminator, e.g. A(#)
+    Delimited(ParseType, u8),
+    // A character string running to the field terminator, for the fields of level 1 and 2 files
+    // that have no format controls
+    Elementary,
+    // A subfield preceded by X(n), n bytes of padding that are skipped
+    Padded(usize, Box<ParseData>),
+}
+
+// How the bytes of a character subfield are turned into a string.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Encoding {
+    Utf8,     // ASCII or ISO 8859-1 written as UTF-8
+    Extended, // May contain escape sequences switching character set
+    Ucs2,     // UCS-2 little endian, lexical level 2, with two byte unit terminators
+    Latin1,   // ISO 8859-1, one byte per character
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -23,16 +46,116 @@ pub(crate) enum ParseType {
     Integer,
     String,
     Float,
+    // B(n), a bit string whose width n is given in bits rather than bytes. Only whole bytes are
+    // supported. Up to 64 bits it is read as a little endian integer the way S-57 packs e.g. the
+    // NAME (RCNM, RCID) pointers, wider ones are kept as bytes.
+    BitString,
+    // b1w and b2w, unsigned and signed little endian integers w bytes wide.
+    Unsigned,
+    Signed,
+    // C, a bit string written as the characters '0' and '1', kept as a string.
+    CharacterBits,
+    // X, bytes to skip. Never the format of a subfield of its own, see ParseData::Padded.
+    Skip,
 }
 
 // Variable length fields are optional (can be empty), therefore Options
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Data {
     Integer(Option<i64>),
     String(String),
     Float(Option<f64>),
+    Bytes(Vec<u8>), // A bit string too wide for an integer
+    // A subfield left out at the end of its field, as opposed to one that is there but empty
+    Missing,
 }
 
+impl Data {
+    /// The string of a character subfield.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Data::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer subfield, `None` if it is empty or not an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Data::Integer(i) => *i,
+            _ => None,
+        }
+    }
+
+    /// The value of a numeric subfield, integers converted. `None` if it is empty or not
+    /// numeric.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Data::Float(f) => *f,
+            Data::Integer(i) => i.map(|i| i as f64),
+            Data::String(_) | Data::Bytes(_) | Data::Missing => None,
+        }
+    }
+
+    /// The bytes of a binary subfield.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Data::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    // What the data is, for error messages.
+    pub(crate) fn describe(&self) -> &'static str {
+        match self {
+            Data::Integer(Some(_)) => "an integer",
+            Data::Integer(None) => "an empty integer",
+            Data::String(_) => "a string",
+            Data::Float(Some(_)) => "a float",
+            Data::Float(None) => "an empty float",
+            Data::Bytes(_) => "bytes",
+            Data::Missing => "missing",
+        }
+    }
+}
+
+impl TryFrom<Data> for i64 {
+    type Error = crate::error::Error;
+    fn try_from(data: Data) -> Result<i64> {
+        data.as_i64()
+            .ok_or_else(|| ErrorKind::WrongDataType("an integer", data.describe()).into())
+    }
+}
+
+impl TryFrom<Data> for f64 {
+    type Error = crate::error::Error;
+    fn try_from(data: Data) -> Result<f64> {
+        data.as_f64()
+            .ok_or_else(|| ErrorKind::WrongDataType("a float", data.describe()).into())
+    }
+}
+
+impl TryFrom<Data> for String {
+    type Error = crate::error::Error;
+    fn try_from(data: Data) -> Result<String> {
+        match data {
+            Data::String(s) => Ok(s),
+            data => Err(ErrorKind::WrongDataType("a string", data.describe()).into()),
+        }
+    }
+}
+
+impl TryFrom<Data> for Vec<u8> {
+    type Error = crate::error::Error;
+    fn try_from(data: Data) -> Result<Vec<u8>> {
+        match data {
+            Data::Bytes(b) => Ok(b),
+            data => Err(ErrorKind::WrongDataType("bytes", data.describe()).into()),
+        }
+    }
+}
+
+// Bytes are displayed in hexadecimal.
 impl Display for Data {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match &self {
@@ -45,12 +168,52 @@ impl Display for Data {
                 Some(x) => Display::fmt(x, f),
                 None => Display::fmt("", f),
             },
+            Data::Bytes(val) => val.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+            Data::Missing => Ok(()),
+        }
+    }
+}
+
+impl Display for ParseData {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ParseData::Fixed(t @ (ParseType::Unsigned | ParseType::Signed), size) => {
+                write!(f, "{}{}", t, size)
+            }
+            ParseData::Fixed(t, size) => write!(f, "{}({})", t, size),
+            ParseData::Variable(t) => Display::fmt(t, f),
+            ParseData::Delimited(t, delimiter) => write!(f, "{}({})", t, *delimiter as char),
+            ParseData::Elementary => f.write_str("A"),
+            ParseData::Padded(n, pd) => write!(f, "X({}),{}", n, pd),
+        }
+    }
+}
+
+impl Display for ParseType {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ParseType::Integer => f.write_str("I"),
+            ParseType::String => f.write_str("A"),
+            ParseType::Float => f.write_str("R"),
+            ParseType::BitString => f.write_str("B"),
+            ParseType::Unsigned => f.write_str("b1"),
+            ParseType::Signed => f.write_str("b2"),
+            ParseType::CharacterBits => f.write_str("C"),
+            ParseType::Skip => f.write_str("X"),
         }
     }
 }
 
 impl ParseData {
     pub(crate) fn from_str(s: &str) -> Result<(usize, ParseData)> {
+        if let Some(cap) = BINARY_REGEX.captures(s) {
+            let num = cap.get(1).map_or(1, |c| c.as_str().parse().unwrap());
+            let typ = match cap.get(2).unwrap().as_str() {
+                "1" => ParseType::Unsigned,
+                _ => ParseType::Signed,
+            };
+            return Ok((num, ParseData::Fixed(typ, cap[3].parse().unwrap())));
+        }
         match FIELD_REGEX.captures(s) {
             Some(cap) => {
                 let num = cap.get(1).map_or(1, |c| c.as_str().parse().unwrap());
@@ -58,38 +221,150 @@ impl ParseData {
                     "A" => ParseType::String,
                     "I" => ParseType::Integer,
                     "R" => ParseType::Float,
+                    "B" => ParseType::BitString,
+                    "C" => ParseType::CharacterBits,
+                    "X" => ParseType::Skip,
                     _ => unreachable!(),
                 };
-                let pd = cap.get(3).map_or(ParseData::Variable(typ.clone()), |c| {
-                    let c = &c.as_str();
-                    ParseData::Fixed(typ, c[1..c.len() - 1].parse().unwrap())
-                });
-                Ok((num, pd))
+                let pd = match cap.get(4).map(|c| c.as_str()) {
+                    None => ParseData::Variable(typ),
+                    Some(width) => match width.parse() {
+                        Ok(width) => ParseData::Fixed(typ, width),
+                        Err(_) if width.len() == 1 => {
+                            ParseData::Delimited(typ, width.as_bytes()[0])
+                        }
+                        Err(_) => {
+                            return Err(ErrorKind::UnParsableFormatControl(String::from(s)).into())
+                        }
+                    },
+                };
+                match pd {
+                    ParseData::Fixed(ParseType::BitString, bits)
+                        if bits > 0 && bits.is_multiple_of(8) =>
+                    {
+                        Ok((num, pd))
+                    }
+                    ParseData::Fixed(ParseType::BitString, _)
+                    | ParseData::Variable(ParseType::BitString)
+                    | ParseData::Delimited(ParseType::BitString | ParseType::Skip, _) => {
+                        Err(ErrorKind::UnParsableFormatControl(String::from(s)).into())
+                    }
+                    // A lone X skips a single byte
+                    ParseData::Variable(ParseType::Skip) => {
+                        Ok((num, ParseData::Fixed(ParseType::Skip, 1)))
+                    }
+                    _ => Ok((num, pd)),
+                }
             }
             None => Err(ErrorKind::UnParsableFormatControl(String::from(s)).into()),
         }
     }
 
-    pub(crate) fn parse<R: BufRead>(&self, mut rdr: R) -> Result<Data> {
+    // Folds the X(n) formats of an expanded list of format controls into the format that
+    // follows, as they have no array descriptor label of their own. Trailing padding is dropped,
+    // the next field being found from the directory anyway.
+    pub(crate) fn fold_padding(parsers: Vec<ParseData>) -> Vec<ParseData> {
+        let mut folded = Vec::with_capacity(parsers.len());
+        let mut padding = 0;
+        for pd in parsers {
+            match pd {
+                ParseData::Fixed(ParseType::Skip, n) => padding += n,
+                pd if padding > 0 => {
+                    folded.push(ParseData::Padded(padding, Box::new(pd)));
+                    padding = 0;
+                }
+                pd => folded.push(pd),
+            }
+        }
+        folded
+    }
+
+    // The number of padding bytes before the subfield itself.
+    pub(crate) fn padding(&self) -> usize {
+        match self {
+            ParseData::Padded(n, _) => *n,
+            _ => 0,
+        }
+    }
+
+    // Whether the subfield ends with a terminator rather than having a fixed width.
+    pub(crate) fn is_delimited(&self) -> bool {
+        match self {
+            ParseData::Fixed(..) => false,
+            ParseData::Variable(_) | ParseData::Delimited(..) | ParseData::Elementary => true,
+            ParseData::Padded(_, pd) => pd.is_delimited(),
+        }
+    }
+
+    // The encoding only applies to character subfields.
+    pub(crate) fn parse<R: BufRead>(&self, mut rdr: R, encoding: Encoding) -> Result<Data> {
         let (d, t) = match &self {
+            ParseData::Padded(n, pd) => {
+                let mut padding = vec![0; *n];
+                rdr.read_exact(&mut padding)
+                    .with_context(|err| ErrorKind::IOError(err.kind()))?;
+                return pd.parse(rdr, encoding);
+            }
+            ParseData::Fixed(ParseType::BitString, bits) if *bits > 64 => {
+                let mut data = vec![0; bits / 8];
+                rdr.read_exact(&mut data)
+                    .with_context(|err| ErrorKind::IOError(err.kind()))?;
+                return Ok(Data::Bytes(data));
+            }
+            ParseData::Fixed(ParseType::BitString, bits) => {
+                let mut data = [0; 8];
+                rdr.read_exact(&mut data[..bits / 8])
+                    .with_context(|err| ErrorKind::IOError(err.kind()))?;
+                return Ok(Data::Integer(Some(u64::from_le_bytes(data) as i64)));
+            }
+            ParseData::Fixed(t @ (ParseType::Unsigned | ParseType::Signed), size) => {
+                let mut data = [0; 8];
+                rdr.read_exact(&mut data[..*size])
+                    .with_context(|err| ErrorKind::IOError(err.kind()))?;
+                let mut value = i64::from_le_bytes(data);
+                // Sign extend from the width of the subfield
+                if *t == ParseType::Signed && *size < 8 {
+                    let shift = 64 - 8 * size;
+                    value = (value << shift) >> shift;
+                }
+                return Ok(Data::Integer(Some(value)));
+            }
             ParseData::Fixed(t, size) => {
                 let mut data = vec![0; *size];
                 rdr.read_exact(&mut data)
                     .with_context(|err| ErrorKind::IOError(err.kind()))?;
                 (data, t)
             }
-            ParseData::Variable(t) => {
+            ParseData::Variable(ParseType::String) if encoding == Encoding::Ucs2 => {
+                (read_ucs2_unit(&mut rdr)?, &ParseType::String)
+            }
+            ParseData::Variable(t) => (read_delimited(&mut rdr, UNIT_SEPARATOR)?, t),
+            ParseData::Delimited(t, delimiter) => (read_delimited(&mut rdr, *delimiter)?, t),
+            ParseData::Elementary => {
                 let mut data = Vec::new();
-                rdr.read_until(UNIT_SEPARATOR, &mut data)
+                rdr.read_until(RECORD_SEPARATOR, &mut data)
                     .with_context(|err| ErrorKind::IOError(err.kind()))?;
-                (Vec::from(&data[..data.len() - 1]), t)
+                if data.last() == Some(&RECORD_SEPARATOR) {
+                    data.pop();
+                }
+                (data, &ParseType::String)
             }
         };
+        if *t == ParseType::String {
+            match encoding {
+                Encoding::Extended => return Ok(Data::String(decode_extended(&d)?)),
+                Encoding::Ucs2 => return Ok(Data::String(decode_ucs2(&d))),
+                Encoding::Latin1 => {
+                    return Ok(Data::String(d.iter().map(|&b| b as char).collect()))
+                }
+                Encoding::Utf8 => (),
+            }
+        }
         let d = std::str::from_utf8(&d).with_context(|&err| ErrorKind::UtfError(err))?;
         match t {
-            ParseType::String => Ok(Data::String(d.to_string())),
+            ParseType::String | ParseType::CharacterBits => Ok(Data::String(d.to_string())),
             ParseType::Integer => {
-                if d.is_empty() {
+                if d.trim().is_empty() {
                     Ok(Data::Integer(None))
                 } else {
                     Ok(Data::Integer(Some(d.parse().with_context(
@@ -100,7 +375,7 @@ impl ParseData {
                 }
             }
             ParseType::Float => {
-                if d.is_empty() {
+                if d.trim().is_empty() {
                     Ok(Data::Float(None))
                 } else {
                     Ok(Data::Float(Some(d.parse().with_context(
@@ -110,8 +385,184 @@ impl ParseData {
                     )?)))
                 }
             }
+            ParseType::BitString | ParseType::Unsigned | ParseType::Signed | ParseType::Skip => {
+                unreachable!()
+            }
         }
     }
+
+    // The inverse of parse: the bytes of data, None for an empty value, as a subfield in this
+    // format. Variable length subfields get their unit terminator, fixed length ones are padded,
+    // numbers with leading zeroes and strings with trailing spaces.
+    pub(crate) fn format(
+        &self,
+        label: &str,
+        data: Option<&Data>,
+        encoding: Encoding,
+    ) -> Result<Vec<u8>> {
+        // A missing subfield is written as an empty one
+        let data = data.filter(|data| **data != Data::Missing);
+        let (t, size) = match self {
+            ParseData::Padded(n, pd) => {
+                let mut bytes = vec![b' '; *n];
+                bytes.extend(pd.format(label, data, encoding)?);
+                return Ok(bytes);
+            }
+            ParseData::Fixed(ParseType::BitString, bits) => {
+                let value = match data {
+                    Some(Data::Bytes(b)) if b.len() == bits / 8 => return Ok(b.clone()),
+                    Some(Data::Integer(Some(i))) => *i as u64,
+                    Some(Data::Integer(None)) | None => 0,
+                    Some(data) => {
+                        return Err(ErrorKind::BadSubfieldValue(
+                            label.to_string(),
+                            data.to_string(),
+                        )
+                        .into())
+                    }
+                };
+                if *bits < 64 && value >> bits != 0 {
+                    return Err(
+                        ErrorKind::BadSubfieldValue(label.to_string(), value.to_string()).into(),
+                    );
+                }
+                let mut bytes = value.to_le_bytes().to_vec();
+                bytes.resize(bits / 8, 0);
+                return Ok(bytes);
+            }
+            ParseData::Fixed(t @ (ParseType::Unsigned | ParseType::Signed), size) => {
+                let value = match data {
+                    Some(Data::Integer(Some(i))) => *i,
+                    Some(Data::Integer(None)) | None => 0,
+                    Some(data) => {
+                        return Err(ErrorKind::BadSubfieldValue(
+                            label.to_string(),
+                            data.to_string(),
+                        )
+                        .into())
+                    }
+                };
+                let bits = 8 * *size as u32;
+                let fits = match t {
+                    ParseType::Unsigned => value >= 0 && (bits == 64 || value >> bits == 0),
+                    _ => {
+                        let shift = 64 - bits;
+                        (value << shift) >> shift == value
+                    }
+                };
+                if !fits {
+                    return Err(
+                        ErrorKind::BadSubfieldValue(label.to_string(), value.to_string()).into(),
+                    );
+                }
+                return Ok(value.to_le_bytes()[..*size].to_vec());
+            }
+            ParseData::Fixed(t, size) => (t, Some(*size)),
+            ParseData::Variable(t) | ParseData::Delimited(t, _) => (t, None),
+            ParseData::Elementary => {
+                return Ok(data.map_or_else(String::new, Data::to_string).into_bytes())
+            }
+        };
+        let text = match (data, size) {
+            (Some(Data::Integer(Some(i))), Some(size)) if *t != ParseType::String => {
+                format!("{:01$}", i, size)
+            }
+            (Some(Data::Float(Some(f))), Some(size)) if *t != ParseType::String => {
+                format!("{:01$}", f, size)
+            }
+            (Some(data), _) => data.to_string(),
+            (None, _) => String::new(),
+        };
+        let ucs2 = encoding == Encoding::Ucs2 && *t == ParseType::String;
+        let latin1 = encoding == Encoding::Latin1 && *t == ParseType::String;
+        if latin1 && text.chars().any(|c| c as u32 > 0xff) {
+            return Err(ErrorKind::BadSubfieldValue(label.to_string(), text).into());
+        }
+        let encode = |s: &str| -> Vec<u8> {
+            if ucs2 {
+                s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+            } else if latin1 {
+                s.chars().map(|c| c as u8).collect()
+            } else {
+                s.as_bytes().to_vec()
+            }
+        };
+        let delimiter = match self {
+            ParseData::Delimited(_, delimiter) => *delimiter,
+            _ => UNIT_SEPARATOR,
+        };
+        let mut bytes = encode(&text);
+        if size.is_none() && delimiter != UNIT_SEPARATOR && bytes.contains(&delimiter) {
+            return Err(ErrorKind::BadSubfieldValue(label.to_string(), text).into());
+        }
+        match size {
+            Some(size) if bytes.len() > size => {
+                return Err(ErrorKind::BadSubfieldValue(label.to_string(), text).into())
+            }
+            Some(size) => {
+                let pad = encode(" ");
+                while bytes.len() < size {
+                    bytes.extend_from_slice(&pad[..pad.len().min(size - bytes.len())]);
+                }
+            }
+            None if ucs2 => bytes.extend_from_slice(&[UNIT_SEPARATOR, 0]),
+            None => bytes.push(delimiter),
+        }
+        Ok(bytes)
+    }
+}
+
+// Reads a variable length subfield up to and including its delimiter, or the field terminator
+// when the last subfield of a field leaves out its unit terminator. The terminator is left out
+// of the data.
+fn read_delimited<R: BufRead>(mut rdr: R, delimiter: u8) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    loop {
+        let buf = rdr
+            .fill_buf()
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        if buf.is_empty() {
+            return Ok(data);
+        }
+        match buf
+            .iter()
+            .position(|&b| b == delimiter || b == RECORD_SEPARATOR)
+        {
+            Some(pos) => {
+                data.extend_from_slice(&buf[..pos]);
+                rdr.consume(pos + 1);
+                return Ok(data);
+            }
+            None => {
+                let len = buf.len();
+                data.extend_from_slice(buf);
+                rdr.consume(len);
+            }
+        }
+    }
+}
+
+// Reads two byte units up to and including the unit terminator 0x001f, returning the bytes
+// before it.
+fn read_ucs2_unit<R: BufRead>(mut rdr: R) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut unit = [0; 2];
+    loop {
+        rdr.read_exact(&mut unit)
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        if unit == [UNIT_SEPARATOR, 0] || unit == [RECORD_SEPARATOR, 0] {
+            return Ok(data);
+        }
+        data.extend_from_slice(&unit);
+    }
+}
+
+fn decode_ucs2(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks(2)
+        .map(|c| u16::from(c[0]) | c.get(1).map_or(0, |&b| u16::from(b) << 8))
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
 }
 
 #[cfg(test)]
@@ -119,6 +570,36 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    #[test]
+    fn data_conversions() {
+        assert_eq!(Data::Integer(Some(7)).to_string(), "7");
+        assert_eq!(Data::Float(None).to_string(), "");
+        assert_eq!(
+            Data::String("Sm\u{f6}gen".into()).as_str(),
+            Some("Sm\u{f6}gen")
+        );
+        assert_eq!(Data::Integer(Some(7)).as_str(), None);
+        assert_eq!(Data::Integer(Some(7)).as_i64(), Some(7));
+        assert_eq!(Data::Integer(Some(7)).as_f64(), Some(7.0));
+        assert_eq!(i64::try_from(Data::Integer(Some(-3))).unwrap(), -3);
+        assert_eq!(f64::try_from(Data::Float(Some(0.5))).unwrap(), 0.5);
+        assert_eq!(String::try_from(Data::String("A".into())).unwrap(), "A");
+        let err = i64::try_from(Data::Integer(None)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Data is an empty integer where an integer was expected"
+        );
+        assert!(String::try_from(Data::Float(Some(1.0))).is_err());
+        assert!(f64::try_from(Data::String("1.0".into())).is_err());
+        assert_eq!(Data::Missing.to_string(), "");
+        assert_eq!(
+            ParseData::Fixed(ParseType::BitString, 16)
+                .format("X", Some(&Data::Missing), Encoding::Utf8)
+                .unwrap(),
+            [0, 0]
+        );
+    }
+
     #[test]
     fn parsedata() {
         assert_eq!(
@@ -157,66 +638,307 @@ mod tests {
             ParseData::from_str("1R(5)").unwrap(),
             (1, ParseData::Fixed(ParseType::Float, 5))
         );
+        assert_eq!(
+            ParseData::from_str("B(40)").unwrap(),
+            (1, ParseData::Fixed(ParseType::BitString, 40))
+        );
+        assert!(ParseData::from_str("B(12)").is_err());
+        assert_eq!(
+            ParseData::from_str("B(72)").unwrap(),
+            (1, ParseData::Fixed(ParseType::BitString, 72))
+        );
+        assert!(ParseData::from_str("B").is_err());
+    }
+
+    #[test]
+    fn user_delimiters() {
+        let string = ParseData::from_str("A(#)").unwrap().1;
+        assert_eq!(string, ParseData::Delimited(ParseType::String, b'#'));
+        assert_eq!(string.to_string(), "A(#)");
+        let integer = ParseData::from_str("I(;)").unwrap().1;
+        assert_eq!(integer, ParseData::Delimited(ParseType::Integer, b';'));
+        assert!(ParseData::from_str("B(#)").is_err());
+
+        let mut rdr = Cursor::new(&b"Ada#42\x1e"[..]);
+        assert_eq!(
+            string.parse(&mut rdr, Encoding::Utf8).unwrap(),
+            Data::String("Ada".into())
+        );
+        assert_eq!(
+            integer.parse(&mut rdr, Encoding::Utf8).unwrap(),
+            Data::Integer(Some(42))
+        );
+        let data = Data::String("Ada".into());
+        assert_eq!(
+            string.format("NAME", Some(&data), Encoding::Utf8).unwrap(),
+            b"Ada#"
+        );
+        let data = Data::String("A#a".into());
+        assert!(string.format("NAME", Some(&data), Encoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn last_subfield_without_unit_terminator() {
+        let string = ParseData::Variable(ParseType::String);
+        let integer = ParseData::Variable(ParseType::Integer);
+        let mut rdr = Cursor::new(&b"Ada\x1f12\x1eNext\x1f"[..]);
+        assert_eq!(
+            string.parse(&mut rdr, Encoding::Utf8).unwrap(),
+            Data::String("Ada".into())
+        );
+        assert_eq!(
+            integer.parse(&mut rdr, Encoding::Utf8).unwrap(),
+            Data::Integer(Some(12))
+        );
+        assert_eq!(rdr.position(), 7);
+        let mut rdr = Cursor::new(&b"A\x00\x1e\x00N\x00"[..]);
+        assert_eq!(
+            string.parse(&mut rdr, Encoding::Ucs2).unwrap(),
+            Data::String("A".into())
+        );
+        assert_eq!(rdr.position(), 4);
+        let mut rdr = Cursor::new(&b""[..]);
+        assert_eq!(
+            string.parse(&mut rdr, Encoding::Utf8).unwrap(),
+            Data::String("".into())
+        );
+    }
+
+    #[test]
+    fn padding_and_character_bits() {
+        let parsers = ["A(2)", "X(3)", "C(4)", "X", "X", "I(1)", "X(9)"]
+            .iter()
+            .map(|s| ParseData::from_str(s).unwrap().1)
+            .collect();
+        let parsers = ParseData::fold_padding(parsers);
+        assert_eq!(parsers.len(), 3);
+        assert_eq!(parsers[1].to_string(), "X(3),C(4)");
+        assert_eq!(parsers[2].padding(), 2);
+        let mut cur = Cursor::new(b"AB   0110  7".to_vec());
+        let data: Vec<Data> = parsers
+            .iter()
+            .map(|pd| pd.parse(&mut cur, Encoding::Utf8).unwrap())
+            .collect();
+        assert_eq!(
+            data,
+            [
+                Data::String("AB".to_string()),
+                Data::String("0110".to_string()),
+                Data::Integer(Some(7))
+            ]
+        );
+        assert_eq!(
+            parsers[2]
+                .format("X", Some(&Data::Integer(Some(7))), Encoding::Utf8)
+                .unwrap(),
+            b"  7"
+        );
+    }
+
+    #[test]
+    fn binary_integers() {
+        assert_eq!(
+            ParseData::from_str("b11").unwrap(),
+            (1, ParseData::Fixed(ParseType::Unsigned, 1))
+        );
+        assert_eq!(
+            ParseData::from_str("2b24").unwrap(),
+            (2, ParseData::Fixed(ParseType::Signed, 4))
+        );
+        assert!(ParseData::from_str("b13").is_err());
+        let parse = |pd: &ParseData, bytes: &[u8]| pd.parse(Cursor::new(bytes), Encoding::Utf8);
+        let b14 = ParseData::Fixed(ParseType::Unsigned, 4);
+        let b22 = ParseData::Fixed(ParseType::Signed, 2);
+        assert_eq!(
+            parse(&b14, &[0xff, 0xff, 0xff, 0xff]).unwrap(),
+            Data::Integer(Some(0xffff_ffff))
+        );
+        assert_eq!(parse(&b22, &[0xfe, 0xff]).unwrap(), Data::Integer(Some(-2)));
+        assert_eq!(b14.to_string(), "b14");
+        assert_eq!(
+            b22.format("X", Some(&Data::Integer(Some(-2))), Encoding::Utf8)
+                .unwrap(),
+            [0xfe, 0xff]
+        );
+        assert!(b22
+            .format("X", Some(&Data::Integer(Some(40000))), Encoding::Utf8)
+            .is_err());
+        assert!(b14
+            .format("X", Some(&Data::Integer(Some(-1))), Encoding::Utf8)
+            .is_err());
+    }
+
+    #[test]
+    fn bit_strings() {
+        let name = ParseData::Fixed(ParseType::BitString, 40);
+        let bytes = [0x82, 0x2a, 0x00, 0x00, 0x00];
+        let data = name.parse(Cursor::new(&bytes[..]), Encoding::Utf8).unwrap();
+        assert_eq!(data, Data::Integer(Some(0x2a82)));
+        assert_eq!(name.to_string(), "B(40)");
+        assert_eq!(
+            name.format("NAME", Some(&data), Encoding::Utf8).unwrap(),
+            bytes
+        );
+        assert!(ParseData::Fixed(ParseType::BitString, 8)
+            .format("X", Some(&Data::Integer(Some(256))), Encoding::Utf8)
+            .is_err());
+
+        let digest = ParseData::Fixed(ParseType::BitString, 96);
+        let bytes = *b"\x00\x1f\x1e\xff\x01\x02\x03\x04\x05\x06\x07\x08";
+        let data = digest
+            .parse(Cursor::new(&bytes[..]), Encoding::Utf8)
+            .unwrap();
+        assert_eq!(data, Data::Bytes(bytes.to_vec()));
+        assert_eq!(data.to_string(), "001f1eff0102030405060708");
+        assert_eq!(data.as_bytes(), Some(&bytes[..]));
+        assert_eq!(
+            digest.format("HASH", Some(&data), Encoding::Utf8).unwrap(),
+            bytes
+        );
+        assert_eq!(
+            digest.format("HASH", None, Encoding::Utf8).unwrap(),
+            [0; 12]
+        );
+        assert!(digest
+            .format("HASH", Some(&Data::Bytes(vec![0; 11])), Encoding::Utf8)
+            .is_err());
+        assert_eq!(Vec::<u8>::try_from(data).unwrap(), bytes);
     }
 
     #[test]
     fn read_data() {
         assert_eq!(
             ParseData::Fixed(ParseType::Integer, 5)
-                .parse(Cursor::new("00001".as_bytes()))
+                .parse(Cursor::new("00001".as_bytes()), Encoding::Utf8)
                 .unwrap(),
             Data::Integer(Some(1))
         );
+        assert_eq!(
+            ParseData::Fixed(ParseType::Integer, 3)
+                .parse(Cursor::new("   ".as_bytes()), Encoding::Utf8)
+                .unwrap(),
+            Data::Integer(None)
+        );
         assert_eq!(
             ParseData::Fixed(ParseType::String, 5)
-                .parse(Cursor::new("Hejsa".as_bytes()))
+                .parse(Cursor::new("Hejsa".as_bytes()), Encoding::Utf8)
                 .unwrap(),
             Data::String(String::from("Hejsa"))
         );
         assert_eq!(
             ParseData::Fixed(ParseType::Float, 5)
-                .parse(Cursor::new("0.005".as_bytes()))
+                .parse(Cursor::new("0.005".as_bytes()), Encoding::Utf8)
                 .unwrap(),
             Data::Float(Some(0.005))
         );
         assert_eq!(
             ParseData::Variable(ParseType::Integer)
-                .parse(Cursor::new(&[
-                    '0' as u8,
-                    '0' as u8,
-                    '0' as u8,
-                    '0' as u8,
-                    '1' as u8,
-                    UNIT_SEPARATOR,
-                ]))
+                .parse(
+                    Cursor::new(&[b'0', b'0', b'0', b'0', b'1', UNIT_SEPARATOR]),
+                    Encoding::Utf8
+                )
                 .unwrap(),
             Data::Integer(Some(1))
         );
         assert_eq!(
             ParseData::Variable(ParseType::String)
-                .parse(Cursor::new(&[
-                    'H' as u8,
-                    'e' as u8,
-                    'j' as u8,
-                    's' as u8,
-                    'a' as u8,
-                    UNIT_SEPARATOR,
-                ]))
+                .parse(
+                    Cursor::new(&[b'H', b'e', b'j', b's', b'a', UNIT_SEPARATOR]),
+                    Encoding::Utf8
+                )
                 .unwrap(),
             Data::String(String::from("Hejsa"))
         );
         assert_eq!(
             ParseData::Variable(ParseType::Float)
-                .parse(Cursor::new(&[
-                    '0' as u8,
-                    '.' as u8,
-                    '0' as u8,
-                    '0' as u8,
-                    '5' as u8,
-                    UNIT_SEPARATOR,
-                ]))
+                .parse(
+                    Cursor::new(&[b'0', b'.', b'0', b'0', b'5', UNIT_SEPARATOR]),
+                    Encoding::Utf8
+                )
                 .unwrap(),
             Data::Float(Some(0.005))
         );
     }
+
+    #[test]
+    fn format_data() {
+        let format = |pd: ParseData, data: Data| pd.format("X", Some(&data), Encoding::Utf8);
+        assert_eq!(
+            format(
+                ParseData::Fixed(ParseType::Integer, 5),
+                Data::Integer(Some(1))
+            )
+            .unwrap(),
+            b"00001"
+        );
+        assert_eq!(
+            format(
+                ParseData::Fixed(ParseType::String, 4),
+                Data::String("CD".to_string())
+            )
+            .unwrap(),
+            b"CD  "
+        );
+        assert!(format(
+            ParseData::Fixed(ParseType::String, 1),
+            Data::String("CD".to_string())
+        )
+        .is_err());
+        assert_eq!(
+            format(
+                ParseData::Variable(ParseType::Float),
+                Data::Float(Some(0.005))
+            )
+            .unwrap(),
+            b"0.005\x1f"
+        );
+        assert_eq!(
+            ParseData::Variable(ParseType::String)
+                .format(
+                    "X",
+                    Some(&Data::String("\u{e5}".to_string())),
+                    Encoding::Ucs2
+                )
+                .unwrap(),
+            b"\xe5\x00\x1f\x00"
+        );
+        assert_eq!(
+            ParseData::Variable(ParseType::Integer)
+                .format("X", None, Encoding::Utf8)
+                .unwrap(),
+            b"\x1f"
+        );
+    }
+
+    #[test]
+    fn latin1() {
+        let parser = ParseData::Variable(ParseType::String);
+        assert_eq!(
+            parser
+                .parse(Cursor::new(b"G\xe4vle\x1f".to_vec()), Encoding::Latin1)
+                .unwrap(),
+            Data::String("G\u{e4}vle".to_string())
+        );
+        let data = Data::String("G\u{e4}vle".to_string());
+        assert_eq!(
+            parser.format("X", Some(&data), Encoding::Latin1).unwrap(),
+            b"G\xe4vle\x1f"
+        );
+        let data = Data::String("\u{2713}".to_string());
+        assert!(parser.format("X", Some(&data), Encoding::Latin1).is_err());
+    }
+
+    #[test]
+    fn read_ucs2() {
+        let mut cur = Cursor::new(b"O\x00s\x00l\x00o\x00\x1f\x00\xe5\x00\x1f\x00".to_vec());
+        let parser = ParseData::Variable(ParseType::String);
+        assert_eq!(
+            parser.parse(&mut cur, Encoding::Ucs2).unwrap(),
+            Data::String("Oslo".to_string())
+        );
+        assert_eq!(
+            parser.parse(&mut cur, Encoding::Ucs2).unwrap(),
+            Data::String("\u{e5}".to_string())
+        );
+    }
 }
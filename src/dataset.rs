@@ -0,0 +1,545 @@
+//! An ENC cell (.000) read into memory. A cell holds the data set descriptive records (DSID and
+//! DSPM), followed by vector records and feature records that refer to each other by record
+//! name, so those are kept indexed by it.
+use crate::catalog::{Catalog, ParserConfig, Record, Result, Schema};
+use crate::data_parser::Data;
+use crate::diagnostics::Diagnostic;
+use crate::dsid::{DataSetIdentification, DataSetStructure};
+use crate::dspm::DataSetParameters;
+use crate::error::ErrorKind;
+use crate::feature::FeatureRecord;
+use crate::history::FeatureHistory;
+use crate::iso8211::read_record;
+use crate::validation::AttributeRules;
+use crate::vector::VectorRecord;
+use failure::ResultExt;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::io::Read;
+use std::path::Path;
+
+/// A record name, RCNM and RCID, which is unique within a cell.
+pub type RecordName = (u32, u32);
+
+/// RCNM, the kind of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RecordType {
+    DataSetGeneral = 10,          // DS
+    DataSetParameter = 20,        // DP
+    DataSetHistory = 30,          // DH
+    DataSetAccuracy = 40,         // DA
+    CatalogueCrossReference = 60, // CR
+    Feature = 100,                // FE
+    IsolatedNode = 110,           // VI
+    ConnectedNode = 120,          // VC
+    Edge = 130,                   // VE
+    Face = 140,                   // VF
+}
+
+impl RecordType {
+    pub fn from_code(rcnm: u32) -> Option<RecordType> {
+        use RecordType::*;
+        [
+            DataSetGeneral,
+            DataSetParameter,
+            DataSetHistory,
+            DataSetAccuracy,
+            CatalogueCrossReference,
+            Feature,
+            IsolatedNode,
+            ConnectedNode,
+            Edge,
+            Face,
+        ]
+        .iter()
+        .copied()
+        .find(|kind| *kind as u32 == rcnm)
+    }
+
+    pub(crate) fn from_data(data: &Data) -> Result<RecordType> {
+        RecordType::from_code(number("RCNM", data)?).ok_or_else(|| bad_value("RCNM", data))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DataSet {
+    ddr: Vec<u8>,                        // The Data Descriptive Record as read
+    indices: HashMap<RecordName, usize>, // Index of the Data Record of every feature read
+    pub general: Record,                 // The data set general information record (DSID)
+    pub parameters: Option<Record>,      // The data set geographic reference record (DSPM)
+    pub vectors: BTreeMap<RecordName, Record>,
+    pub features: BTreeMap<RecordName, Record>,
+    pub other: Vec<Record>, // Records of any other kind, e.g. data set history
+    pub(crate) history: Option<FeatureHistory>, // Changes made by updates, if kept
+}
+
+// The record name from the RCNM and RCID subfields of the field `tag`.
+pub(crate) fn record_name(record: &Record, tag: &str) -> Result<RecordName> {
+    let field = record
+        .field(tag)
+        .ok_or_else(|| ErrorKind::MissingField(tag.to_string()))?;
+    let subfield = |label: &str| -> Result<u32> {
+        let value = field
+            .get(label)
+            .ok_or_else(|| ErrorKind::MissingSubfield(tag.to_string(), label.to_string()))?;
+        number(label, value)
+    };
+    Ok((subfield("RCNM")?, subfield("RCID")?))
+}
+
+// Inserts `record` under `name`, failing if a record with the same name was already read.
+fn insert_unique(
+    records: &mut BTreeMap<RecordName, Record>,
+    name: RecordName,
+    record: Record,
+) -> Result<()> {
+    match records.entry(name) {
+        Entry::Occupied(_) => Err(ErrorKind::DuplicateRecord(name.0, name.1).into()),
+        Entry::Vacant(entry) => {
+            entry.insert(record);
+            Ok(())
+        }
+    }
+}
+
+/// The record name in the NAME subfield of a pointer field such as FSPT or VRPT. It is a B(40)
+/// with RCNM in the first byte and RCID in the following four.
+pub fn pointer_name(data: &Data) -> Option<RecordName> {
+    match data {
+        Data::Integer(Some(n)) => Some(((n & 0xff) as u32, (n >> 8) as u32)),
+        _ => None,
+    }
+}
+
+// The subfields `labels` of every row of the fields `tag` of `record`. A field without a
+// repeating group is a single row.
+pub(crate) fn rows<'a>(
+    record: &'a Record,
+    tag: &str,
+    labels: &[&str],
+) -> Result<Vec<Vec<&'a Data>>> {
+    let missing = |label: &str| ErrorKind::MissingSubfield(tag.to_string(), label.to_string());
+    let mut out = Vec::new();
+    for field in record.fields_with_tag(tag) {
+        match field.rows() {
+            Some(rows) => {
+                // The repeating group is the last labels of the field
+                let all = field.labels();
+                let group = &all[all.len().saturating_sub(rows.first().map_or(0, Vec::len))..];
+                let index = labels
+                    .iter()
+                    .map(|label| {
+                        group
+                            .iter()
+                            .position(|l| l == label)
+                            .ok_or_else(|| missing(label))
+                    })
+                    .collect::<std::result::Result<Vec<usize>, ErrorKind>>()?;
+                out.extend(
+                    rows.iter()
+                        .map(|row| index.iter().map(|&i| &row[i]).collect()),
+                );
+            }
+            None => out.push(
+                labels
+                    .iter()
+                    .map(|label| field.get(label).ok_or_else(|| missing(label)))
+                    .collect::<std::result::Result<Vec<&Data>, ErrorKind>>()?,
+            ),
+        }
+    }
+    Ok(out)
+}
+
+pub(crate) fn bad_value(label: &str, data: &Data) -> crate::error::Error {
+    ErrorKind::BadSubfieldValue(label.to_string(), data.to_string()).into()
+}
+
+// The value of an integer subfield, which is a string of digits in the ASCII implementation.
+pub(crate) fn integer(label: &str, data: &Data) -> Result<i64> {
+    match data {
+        Data::Integer(Some(i)) => Ok(*i),
+        Data::String(s) => s.trim().parse().map_err(|_| bad_value(label, data)),
+        data => Err(bad_value(label, data)),
+    }
+}
+
+// The value of an integer subfield as a T, failing if it is out of the range of T.
+pub(crate) fn number<T: TryFrom<i64>>(label: &str, data: &Data) -> Result<T> {
+    T::try_from(integer(label, data)?).map_err(|_| bad_value(label, data))
+}
+
+// The integer subfield `label` of the first field `tag` of `record` as a T, see `number`.
+pub(crate) fn subfield_number<T: TryFrom<i64>>(
+    record: &Record,
+    tag: &str,
+    label: &str,
+) -> Result<T> {
+    let field = record
+        .field(tag)
+        .ok_or_else(|| ErrorKind::MissingField(tag.to_string()))?;
+    match field.get(label) {
+        None | Some(Data::Missing) | Some(Data::Integer(None)) => {
+            Err(ErrorKind::MissingSubfield(tag.to_string(), label.to_string()).into())
+        }
+        Some(data) => number(label, data),
+    }
+}
+
+impl DataSet {
+    /// Reads every record of the cell read from `rdr`. Fails if there is no DSID record or if
+    /// two feature or vector records have the same name.
+    pub fn read<R: Read>(rdr: R) -> Result<DataSet> {
+        DataSet::read_with_config(rdr, &ParserConfig::default())
+    }
+
+    /// Like [`DataSet::read`], with the options of `config`. A memory budget covers the DDR and
+    /// the Data Records together.
+    pub fn read_with_config<R: Read>(mut rdr: R, config: &ParserConfig) -> Result<DataSet> {
+        let ddr = read_record(&mut rdr, config.memory_budget)?;
+        let config = ParserConfig {
+            memory_budget: config.memory_budget.map(|budget| budget - ddr.len() as u64),
+            ..config.clone()
+        };
+        let mut general = None;
+        let mut parameters = None;
+        let mut vectors = BTreeMap::new();
+        let mut features = BTreeMap::new();
+        let mut other = Vec::new();
+        let mut indices = HashMap::new();
+        let records = Catalog::with_config((&ddr[..]).chain(rdr), &config)?;
+        for (index, record) in records.enumerate() {
+            let record = record?;
+            if record.get("FRID").is_some() {
+                let name = record_name(&record, "FRID")?;
+                insert_unique(&mut features, name, record)?;
+                indices.insert(name, index);
+            } else if record.get("VRID").is_some() {
+                insert_unique(&mut vectors, record_name(&record, "VRID")?, record)?;
+            } else if record.get("DSID").is_some() && general.is_none() {
+                general = Some(record);
+            } else if record.get("DSPM").is_some() && parameters.is_none() {
+                parameters = Some(record);
+            } else {
+                other.push(record);
+            }
+        }
+        Ok(DataSet {
+            ddr,
+            indices,
+            general: general.ok_or_else(|| ErrorKind::MissingField("DSID".to_string()))?,
+            parameters,
+            vectors,
+            features,
+            other,
+            history: None,
+        })
+    }
+
+    /// Reads the cell in the file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<DataSet> {
+        let file = std::fs::File::open(path).with_context(|err| ErrorKind::IOError(err.kind()))?;
+        DataSet::read(std::io::BufReader::new(file))
+    }
+
+    /// The schema declared by the DDR.
+    pub fn schema(&self) -> Result<Schema> {
+        Catalog::read_schema_only(&self.ddr[..])
+    }
+
+    /// The DSID field of the cell.
+    pub fn identification(&self) -> Result<DataSetIdentification> {
+        DataSetIdentification::try_from(&self.general)
+    }
+
+    /// The DSSI field of the cell.
+    pub fn structure(&self) -> Result<DataSetStructure> {
+        DataSetStructure::try_from(&self.general)
+    }
+
+    /// The DSPM field of the cell.
+    pub fn dspm(&self) -> Result<DataSetParameters> {
+        let record = self
+            .parameters
+            .as_ref()
+            .ok_or_else(|| ErrorKind::MissingField("DSPM".to_string()))?;
+        DataSetParameters::try_from(record)
+    }
+
+    /// The coordinates of the vector record `vector` in degrees, see
+    /// [`DataSetParameters::coordinates`].
+    pub fn coordinates(&self, vector: &Record) -> Result<Vec<(f64, f64)>> {
+        self.dspm()?.coordinates(vector)
+    }
+
+    /// The soundings of the vector record `vector`, see [`DataSetParameters::soundings`].
+    pub fn soundings(&self, vector: &Record) -> Result<Vec<(f64, f64, f64)>> {
+        self.dspm()?.soundings(vector)
+    }
+
+    /// The compilation scale of the cell, from the CSCL subfield of DSPM.
+    pub fn compilation_scale(&self) -> Option<u32> {
+        self.parameters.as_ref()?.compilation_scale()
+    }
+
+    pub fn feature(&self, name: RecordName) -> Option<&Record> {
+        self.features.get(&name)
+    }
+
+    /// Checks the attributes (ATTF) of every feature against the rules of its object class, in
+    /// record name order. The diagnostics point at the Data Record the feature was read from, or
+    /// at none for a feature an update inserted.
+    pub fn validate(&self, rules: &AttributeRules) -> Result<Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        for (name, feature) in &self.features {
+            let objl = subfield_number(feature, "FRID", "OBJL")?;
+            let attls = rows(feature, "ATTF", &["ATTL"])?
+                .into_iter()
+                .map(|row| number("ATTL", row[0]))
+                .collect::<Result<Vec<u16>>>()?;
+            let index = self.indices.get(name).copied();
+            diagnostics.extend(rules.validate(index, objl, &attls));
+        }
+        Ok(diagnostics)
+    }
+
+    /// Every feature record as a [`FeatureRecord`], in record name order.
+    pub fn feature_records(&self) -> impl Iterator<Item = Result<FeatureRecord>> + '_ {
+        self.features.values().map(FeatureRecord::try_from)
+    }
+
+    pub fn vector(&self, name: RecordName) -> Option<&Record> {
+        self.vectors.get(&name)
+    }
+
+    /// Every vector record as a [`VectorRecord`], in record name order, with its coordinates
+    /// scaled by the DSPM of the cell.
+    pub fn vector_records(&self) -> Result<impl Iterator<Item = Result<VectorRecord>> + '_> {
+        let dspm = self.dspm()?;
+        Ok(self
+            .vectors
+            .values()
+            .map(move |record| VectorRecord::new(record, &dspm)))
+    }
+
+    /// The vector records the FSPT field of `feature` points to, in order.
+    pub fn spatial_records(&self, feature: &Record) -> Result<Vec<&Record>> {
+        self.pointed_to(feature, "FSPT")
+    }
+
+    /// The vector records the VRPT field of `vector` points to, e.g. the end nodes of an edge.
+    pub fn topology(&self, vector: &Record) -> Result<Vec<&Record>> {
+        self.pointed_to(vector, "VRPT")
+    }
+
+    // Fails with ErrorKind::MissingRecord for a pointer to a record the cell doesn't have.
+    fn pointed_to(&self, record: &Record, tag: &str) -> Result<Vec<&Record>> {
+        record
+            .all_subfields()
+            .filter(|(t, label, _)| *t == tag && *label == "NAME")
+            .map(|(_, _, data)| {
+                let name = pointer_name(data).ok_or_else(|| {
+                    ErrorKind::BadSubfieldValue("NAME".to_string(), data.to_string())
+                })?;
+                self.vectors
+                    .get(&name)
+                    .ok_or_else(|| ErrorKind::MissingRecord(name.0, name.1).into())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::DiagnosticKind;
+    use crate::test_support::FileBuilder;
+    use crate::validation::ClassRules;
+    use std::collections::HashSet;
+
+    fn name(rcnm: i64, rcid: i64) -> Data {
+        Data::Integer(Some(rcnm | rcid << 8))
+    }
+
+    fn cell(pointers: &[Data]) -> Vec<u8> {
+        let int = |i| Data::Integer(Some(i));
+        let mut feature = Record::new();
+        feature.set_id(5);
+        for (label, value) in &[("RCNM", 100), ("RCID", 1), ("OBJL", 42)] {
+            feature.set("FRID", label, int(*value));
+        }
+        let rows = pointers.iter().map(|p| vec![p.clone(), int(1)]).collect();
+        feature.insert_rows("FSPT", &["NAME", "ORNT"], rows);
+        FileBuilder::new()
+            .field(
+                "DSID",
+                "Data Set Identification Field",
+                "RCNM!RCID!DSNM",
+                "(b11,b14,A)",
+            )
+            .field(
+                "DSPM",
+                "Data Set Parameter Field",
+                "RCNM!RCID!COMF",
+                "(b11,b14,b14)",
+            )
+            .field(
+                "VRID",
+                "Vector Record Identifier Field",
+                "RCNM!RCID",
+                "(b11,b14)",
+            )
+            .field(
+                "VRPT",
+                "Vector Record Pointer Field",
+                "*NAME!ORNT",
+                "(B(40),b11)",
+            )
+            .field(
+                "FRID",
+                "Feature Record Identifier Field",
+                "RCNM!RCID!OBJL",
+                "(b11,b14,b12)",
+            )
+            .field(
+                "FSPT",
+                "Feature Record to Spatial Record Pointer Field",
+                "*NAME!ORNT",
+                "(B(40),b11)",
+            )
+            .record(&[(
+                "DSID",
+                &[
+                    ("RCNM", int(10)),
+                    ("RCID", int(1)),
+                    ("DSNM", Data::String("GB4X0000.000".into())),
+                ],
+            )])
+            .record(&[(
+                "DSPM",
+                &[
+                    ("RCNM", int(20)),
+                    ("RCID", int(1)),
+                    ("COMF", int(10_000_000)),
+                ],
+            )])
+            .record(&[("VRID", &[("RCNM", int(120)), ("RCID", int(1))])])
+            .record(&[("VRID", &[("RCNM", int(120)), ("RCID", int(2))])])
+            .raw_record(feature)
+            .build()
+    }
+
+    #[test]
+    fn read_cell() {
+        let bytes = cell(&[name(120, 2), name(120, 1)]);
+        let data_set = DataSet::read(&bytes[..]).unwrap();
+        assert_eq!(
+            data_set.general.get_str("DSID", "DSNM").unwrap(),
+            "GB4X0000.000"
+        );
+        assert!(data_set.parameters.is_some());
+        assert_eq!(data_set.vectors.len(), 2);
+        assert!(data_set.other.is_empty());
+        assert!(data_set.schema().unwrap().field("FSPT").is_some());
+        let feature = data_set.feature((100, 1)).unwrap();
+        let ids: Vec<_> = data_set
+            .spatial_records(feature)
+            .unwrap()
+            .iter()
+            .map(|vector| record_name(vector, "VRID").unwrap())
+            .collect();
+        assert_eq!(ids, vec![(120, 2), (120, 1)]);
+        assert!(data_set
+            .topology(data_set.vector((120, 1)).unwrap())
+            .unwrap()
+            .is_empty());
+
+        let bytes = cell(&[name(120, 3)]);
+        let data_set = DataSet::read(&bytes[..]).unwrap();
+        let err = data_set
+            .spatial_records(data_set.feature((100, 1)).unwrap())
+            .unwrap_err();
+        match err.kind() {
+            ErrorKind::MissingRecord(120, 3) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+
+    #[test]
+    fn duplicate_records() {
+        let mut bytes = cell(&[name(120, 1)]);
+        // Repeat the last record, the feature (100, 1)
+        let mut start = 0;
+        loop {
+            let len: usize = std::str::from_utf8(&bytes[start..start + 5])
+                .unwrap()
+                .parse()
+                .unwrap();
+            if start + len == bytes.len() {
+                break;
+            }
+            start += len;
+        }
+        let feature = bytes[start..].to_vec();
+        bytes.extend(feature);
+        let err = DataSet::read(&bytes[..]).unwrap_err();
+        match err.kind() {
+            ErrorKind::DuplicateRecord(100, 1) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+
+    #[test]
+    fn validate_features() {
+        let bytes = cell(&[name(120, 1)]);
+        let data_set = DataSet::read(&bytes[..]).unwrap();
+        let mut rules = AttributeRules::new();
+        let diagnostics = data_set.validate(&rules).unwrap();
+        assert_eq!(
+            diagnostics[0].kind,
+            DiagnosticKind::UnknownObjectClass { objl: 42 }
+        );
+        rules.insert(
+            42,
+            ClassRules {
+                allowed: HashSet::new(),
+                mandatory: [87].iter().copied().collect(),
+            },
+        );
+        let diagnostics = data_set.validate(&rules).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].record, Some(4));
+        assert_eq!(
+            diagnostics[0].kind,
+            DiagnosticKind::MissingMandatoryAttribute { objl: 42, attl: 87 }
+        );
+    }
+
+    #[cfg(all(feature = "objects", feature = "attributes"))]
+    #[test]
+    fn validate_builtin() {
+        let bytes = crate::test_support::sample_cell();
+        let data_set = DataSet::read(&bytes[..]).unwrap();
+        let diagnostics = data_set.validate(&AttributeRules::builtin()).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn memory_budget() {
+        let bytes = cell(&[name(120, 2), name(120, 1)]);
+        let config = |budget| ParserConfig {
+            memory_budget: Some(budget),
+            ..ParserConfig::default()
+        };
+        let whole = bytes.len() as u64;
+        assert!(DataSet::read_with_config(&bytes[..], &config(whole)).is_ok());
+        for budget in &[whole - 1, 10] {
+            let err = DataSet::read_with_config(&bytes[..], &config(*budget)).unwrap_err();
+            match err.kind() {
+                ErrorKind::MemoryBudgetExceeded(_) => (),
+                kind => panic!("unexpected error: {}", kind),
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Non-fatal problems found while parsing. Unlike an [`Error`] a
+//! [`Diagnostic`] does not stop the parse, it is collected on the
+//! [`Catalog`](crate::catalog::Catalog) so that producer bugs can be reported after the fact.
+use crate::error::Error;
+use crate::iso8211::Record;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub record: Option<usize>, // Index of the Data Record, None for the DDR
+    pub tag: Option<String>,   // Tag of the field the diagnostic concerns
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    /// A character subfield contains characters outside of the lexical level declared by the
+    /// truncated escape sequence of its field.
+    LexicalLevelMismatch { label: String, declared: u8 },
+    /// A feature carries an attribute its object class does not allow.
+    AttributeNotAllowed { objl: u16, attl: u16 },
+    /// A feature lacks an attribute that is mandatory for its object class.
+    MissingMandatoryAttribute { objl: u16, attl: u16 },
+    /// A feature has an object class there are no rules for.
+    UnknownObjectClass { objl: u16 },
+    /// The base address of field area in the leader is not where the field area starts, i.e.
+    /// just after the directory. The field area is read from where it actually starts.
+    BaseAddressMismatch { declared: usize, actual: usize },
+    /// A record identifier already used by an earlier record.
+    DuplicateRecordId { id: i64 },
+    /// A record identifier that does not follow the previous one.
+    RecordIdGap { expected: i64, found: i64 },
+    /// The file ends before the record does. The fields that are all there are still decoded.
+    TruncatedRecord { missing: usize },
+    /// The directory entries are not in the order of the fields in the field area.
+    UnsortedDirectory,
+    /// A directory entry runs into the next field or past the field area. The field is read up
+    /// to its first field terminator instead.
+    OverlappingField { declared: usize, actual: usize },
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            DiagnosticKind::LexicalLevelMismatch { label, declared } => write!(
+                f,
+                "Subfield '{}' contains characters outside of lexical level {}",
+                label, declared
+            ),
+            DiagnosticKind::AttributeNotAllowed { objl, attl } => write!(
+                f,
+                "Attribute {} is not allowed for object class {}",
+                attl, objl
+            ),
+            DiagnosticKind::MissingMandatoryAttribute { objl, attl } => write!(
+                f,
+                "Attribute {} is mandatory for object class {}",
+                attl, objl
+            ),
+            DiagnosticKind::UnknownObjectClass { objl } => {
+                write!(f, "Unknown object class {}", objl)
+            }
+            DiagnosticKind::BaseAddressMismatch { declared, actual } => write!(
+                f,
+                "Base address of field area is {} but the field area starts at {}",
+                declared, actual
+            ),
+            DiagnosticKind::DuplicateRecordId { id } => {
+                write!(f, "Record identifier {} is used more than once", id)
+            }
+            DiagnosticKind::RecordIdGap { expected, found } => write!(
+                f,
+                "Record identifier {} where {} was expected",
+                found, expected
+            ),
+            DiagnosticKind::TruncatedRecord { missing } => {
+                write!(f, "Record is truncated, {} bytes are missing", missing)
+            }
+            DiagnosticKind::UnsortedDirectory => {
+                write!(f, "Directory entries are not in the order of the fields")
+            }
+            DiagnosticKind::OverlappingField { declared, actual } => write!(
+                f,
+                "Field of {} bytes overlaps the next field, only {} bytes are read",
+                declared, actual
+            ),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if let Some(record) = self.record {
+            write!(f, "Record {}: ", record)?;
+        }
+        if let Some(tag) = &self.tag {
+            write!(f, "{}: ", tag)?;
+        }
+        Display::fmt(&self.kind, f)
+    }
+}
+
+/// What [`Catalog::read_all`](crate::catalog::Catalog::read_all) got out of a file with Data
+/// Records that could not be read: the ones that could, why the others could not and the
+/// diagnostics collected on the way.
+#[derive(Debug)]
+pub struct DiagnosticsReport {
+    pub records: Vec<Record>,
+    pub errors: Vec<(usize, Error)>, // Index of the Data Record and why it could not be read
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} records could not be read, {} could",
+            self.errors.len(),
+            self.records.len()
+        )?;
+        for (record, err) in &self.errors {
+            write!(f, "\nRecord {}: {}", record, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl failure::Fail for DiagnosticsReport {}
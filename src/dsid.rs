@@ -0,0 +1,316 @@
+//! Types for the Data Set Identification (DSID) and Data Set Structure Information (DSSI) fields
+//! of an ENC cell, both found in its first record.
+use crate::agency;
+use crate::catalog::{FieldView, Record, Result};
+use crate::data_parser::Data;
+use crate::dataset::{bad_value, subfield_number};
+use crate::error::ErrorKind;
+use crate::updates::Version;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+/// EXPP, the purpose of the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExchangePurpose {
+    New,      // 1, "N"
+    Revision, // 2, "R"
+}
+
+/// INTU, the intended usage (navigational purpose) of the data set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntendedUsage {
+    Overview = 1,
+    General = 2,
+    Coastal = 3,
+    Approach = 4,
+    Harbour = 5,
+    Berthing = 6,
+}
+
+/// AALL and NALL, the lexical level of the ATTF and NATF fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LexicalLevel {
+    Ascii = 0,
+    Latin1 = 1,
+    Ucs2 = 2,
+}
+
+/// The subfields of the DSID field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSetIdentification {
+    pub purpose: ExchangePurpose, // EXPP
+    pub usage: IntendedUsage,     // INTU
+    pub name: String,             // DSNM, the file name of the base cell
+    pub edition: u32,             // EDTN
+    pub update: u32,              // UPDN
+    pub update_date: String,      // UADT, YYYYMMDD
+    pub issue_date: String,       // ISDT, YYYYMMDD
+    pub s57_edition: String,      // STED, e.g. "03.1"
+    pub agency: u16,              // AGEN, the producing agency
+    pub comment: String,          // COMT
+}
+
+/// The subfields of the DSSI field, the structure of the data set and how many records of each
+/// kind it has. DSTR is 1 for cartographic spaghetti, 2 chain-node, 3 planar graph and 4 full
+/// topology.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSetStructure {
+    pub data_structure: u8,        // DSTR, the topology level
+    pub attf_level: LexicalLevel,  // AALL
+    pub natf_level: LexicalLevel,  // NALL
+    pub meta_records: u32,         // NOMR
+    pub cartographic_records: u32, // NOCR
+    pub geo_records: u32,          // NOGR
+    pub collection_records: u32,   // NOLR
+    pub isolated_nodes: u32,       // NOIN
+    pub connected_nodes: u32,      // NOCN
+    pub edges: u32,                // NOED
+    pub faces: u32,                // NOFA
+}
+
+impl TryFrom<&Data> for ExchangePurpose {
+    type Error = crate::error::Error;
+    fn try_from(data: &Data) -> Result<ExchangePurpose> {
+        match data {
+            Data::Integer(Some(1)) => Ok(ExchangePurpose::New),
+            Data::Integer(Some(2)) => Ok(ExchangePurpose::Revision),
+            Data::String(s) if s == "N" => Ok(ExchangePurpose::New),
+            Data::String(s) if s == "R" => Ok(ExchangePurpose::Revision),
+            _ => Err(bad_value("EXPP", data)),
+        }
+    }
+}
+
+impl TryFrom<&Data> for IntendedUsage {
+    type Error = crate::error::Error;
+    fn try_from(data: &Data) -> Result<IntendedUsage> {
+        match data {
+            Data::Integer(Some(i)) => IntendedUsage::from_code(*i),
+            Data::String(s) => s.parse().ok().and_then(IntendedUsage::from_code),
+            _ => None,
+        }
+        .ok_or_else(|| bad_value("INTU", data))
+    }
+}
+
+impl TryFrom<&Data> for LexicalLevel {
+    type Error = crate::error::Error;
+    fn try_from(data: &Data) -> Result<LexicalLevel> {
+        match data {
+            Data::Integer(Some(0)) => Ok(LexicalLevel::Ascii),
+            Data::Integer(Some(1)) => Ok(LexicalLevel::Latin1),
+            Data::Integer(Some(2)) => Ok(LexicalLevel::Ucs2),
+            _ => Err(bad_value("AALL/NALL", data)),
+        }
+    }
+}
+
+fn field<'a>(record: &'a Record, tag: &str) -> Result<FieldView<'a>> {
+    record
+        .field(tag)
+        .ok_or_else(|| ErrorKind::MissingField(tag.to_string()).into())
+}
+
+fn subfield<'a>(field: &FieldView<'a>, label: &str) -> Result<&'a Data> {
+    field.get(label).ok_or_else(|| {
+        ErrorKind::MissingSubfield(field.tag().to_string(), label.to_string()).into()
+    })
+}
+
+// A character subfield, empty if it is left out.
+fn text(field: &FieldView, label: &str) -> String {
+    match field.get(label) {
+        Some(Data::String(s)) => s.trim().to_string(),
+        _ => String::new(),
+    }
+}
+
+impl TryFrom<&Record> for DataSetIdentification {
+    type Error = crate::error::Error;
+    fn try_from(record: &Record) -> Result<DataSetIdentification> {
+        let dsid = field(record, "DSID")?;
+        Ok(DataSetIdentification {
+            purpose: ExchangePurpose::try_from(subfield(&dsid, "EXPP")?)?,
+            usage: IntendedUsage::try_from(subfield(&dsid, "INTU")?)?,
+            name: text(&dsid, "DSNM"),
+            edition: subfield_number(record, "DSID", "EDTN")?,
+            update: subfield_number(record, "DSID", "UPDN")?,
+            update_date: text(&dsid, "UADT"),
+            issue_date: text(&dsid, "ISDT"),
+            s57_edition: text(&dsid, "STED"),
+            agency: subfield_number(record, "DSID", "AGEN")?,
+            comment: text(&dsid, "COMT"),
+        })
+    }
+}
+
+impl DataSetIdentification {
+    pub fn version(&self) -> Version {
+        Version {
+            edition: self.edition,
+            update: self.update,
+        }
+    }
+}
+
+/// The name, version and update date, and the producer (see [`agency::producer`]), e.g.
+/// "GB4X0000.000 edition 2 update 1 of 20200201 by GB".
+impl Display for DataSetIdentification {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} edition {} update {} of {} by {}",
+            self.name,
+            self.edition,
+            self.update,
+            self.update_date,
+            agency::producer(self.agency)
+        )
+    }
+}
+
+impl TryFrom<&Record> for DataSetStructure {
+    type Error = crate::error::Error;
+    fn try_from(record: &Record) -> Result<DataSetStructure> {
+        let dssi = field(record, "DSSI")?;
+        Ok(DataSetStructure {
+            data_structure: subfield_number(record, "DSSI", "DSTR")?,
+            attf_level: LexicalLevel::try_from(subfield(&dssi, "AALL")?)?,
+            natf_level: LexicalLevel::try_from(subfield(&dssi, "NALL")?)?,
+            meta_records: subfield_number(record, "DSSI", "NOMR")?,
+            cartographic_records: subfield_number(record, "DSSI", "NOCR")?,
+            geo_records: subfield_number(record, "DSSI", "NOGR")?,
+            collection_records: subfield_number(record, "DSSI", "NOLR")?,
+            isolated_nodes: subfield_number(record, "DSSI", "NOIN")?,
+            connected_nodes: subfield_number(record, "DSSI", "NOCN")?,
+            edges: subfield_number(record, "DSSI", "NOED")?,
+            faces: subfield_number(record, "DSSI", "NOFA")?,
+        })
+    }
+}
+
+impl DataSetStructure {
+    /// The number of feature records, NOMR + NOCR + NOGR + NOLR.
+    pub fn feature_records(&self) -> u32 {
+        self.meta_records + self.cartographic_records + self.geo_records + self.collection_records
+    }
+
+    /// The number of vector records, NOIN + NOCN + NOED + NOFA.
+    pub fn vector_records(&self) -> u32 {
+        self.isolated_nodes + self.connected_nodes + self.edges + self.faces
+    }
+}
+
+impl IntendedUsage {
+    pub fn from_code(code: i64) -> Option<IntendedUsage> {
+        match code {
+            1 => Some(IntendedUsage::Overview),
+            2 => Some(IntendedUsage::General),
+            3 => Some(IntendedUsage::Coastal),
+            4 => Some(IntendedUsage::Approach),
+            5 => Some(IntendedUsage::Harbour),
+            6 => Some(IntendedUsage::Berthing),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exchange_purpose() {
+        assert_eq!(
+            ExchangePurpose::try_from(&Data::Integer(Some(2))).unwrap(),
+            ExchangePurpose::Revision
+        );
+        assert_eq!(
+            ExchangePurpose::try_from(&Data::String("N".to_string())).unwrap(),
+            ExchangePurpose::New
+        );
+        assert!(ExchangePurpose::try_from(&Data::Integer(Some(3))).is_err());
+    }
+
+    #[test]
+    fn intended_usage() {
+        assert_eq!(
+            IntendedUsage::try_from(&Data::Integer(Some(5))).unwrap(),
+            IntendedUsage::Harbour
+        );
+        assert_eq!(
+            IntendedUsage::try_from(&Data::String("1".to_string())).unwrap(),
+            IntendedUsage::Overview
+        );
+        assert!(IntendedUsage::try_from(&Data::Integer(None)).is_err());
+    }
+
+    #[test]
+    fn identification_and_structure() {
+        let mut record = Record::new();
+        let int = |i| Data::Integer(Some(i));
+        let string = |s: &str| Data::String(s.to_string());
+        for (label, value) in vec![
+            ("EXPP", int(1)),
+            ("INTU", int(4)),
+            ("DSNM", string("GB4X0000.000")),
+            ("EDTN", string("3")),
+            ("UPDN", string("0")),
+            ("UADT", string("20200101")),
+            ("ISDT", string("20200102")),
+            ("STED", string("03.1")),
+            ("AGEN", int(540)),
+        ] {
+            record.set("DSID", label, value);
+        }
+        let dsid = DataSetIdentification::try_from(&record).unwrap();
+        assert_eq!(dsid.usage, IntendedUsage::Approach);
+        assert_eq!(
+            dsid.version(),
+            Version {
+                edition: 3,
+                update: 0
+            }
+        );
+        assert_eq!(dsid.issue_date, "20200102");
+        assert_eq!(dsid.comment, "");
+        let producer = if cfg!(feature = "agencies") {
+            "GB"
+        } else {
+            "540"
+        };
+        assert_eq!(
+            dsid.to_string(),
+            format!(
+                "GB4X0000.000 edition 3 update 0 of 20200101 by {}",
+                producer
+            )
+        );
+        assert!(DataSetStructure::try_from(&record).is_err());
+        let mut agency = record.clone();
+        agency.set("DSID", "AGEN", int(65_536 + 540));
+        assert!(DataSetIdentification::try_from(&agency).is_err());
+
+        for (label, value) in [
+            ("DSTR", 2),
+            ("AALL", 1),
+            ("NALL", 2),
+            ("NOMR", 5),
+            ("NOCR", 0),
+            ("NOGR", 40),
+            ("NOLR", 1),
+            ("NOIN", 12),
+            ("NOCN", 30),
+            ("NOED", 35),
+            ("NOFA", 0),
+        ] {
+            record.set("DSSI", label, int(value));
+        }
+        let dssi = DataSetStructure::try_from(&record).unwrap();
+        assert_eq!(dssi.attf_level, LexicalLevel::Latin1);
+        assert_eq!(dssi.natf_level, LexicalLevel::Ucs2);
+        assert_eq!((dssi.feature_records(), dssi.vector_records()), (46, 77));
+        record.set("DSSI", "AALL", int(3));
+        assert!(DataSetStructure::try_from(&record).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! The Data Set Parameter field (DSPM) of an ENC cell, and the coordinates of its vector records
+//! scaled by it. Coordinates are stored as integers, latitudes and longitudes multiplied by the
+//! coordinate multiplication factor (COMF) and soundings by the sounding multiplication factor
+//! (SOMF).
+use crate::catalog::{Record, Result};
+use crate::dataset::{integer, rows, subfield_number};
+use crate::error::ErrorKind;
+use crate::units::Units;
+use std::convert::TryFrom;
+
+/// The subfields of the DSPM field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSetParameters {
+    pub horizontal_datum: u8,   // HDAT, 2 for WGS 84
+    pub vertical_datum: u8,     // VDAT
+    pub sounding_datum: u8,     // SDAT
+    pub compilation_scale: u32, // CSCL
+    pub units: Units,           // DUNI, HUNI and PUNI
+    pub coordinate_units: u8,   // COUN, 1 for latitude and longitude
+    pub coordinate_factor: u32, // COMF
+    pub sounding_factor: u32,   // SOMF
+}
+
+fn number<T: TryFrom<i64>>(record: &Record, label: &str) -> Result<T> {
+    subfield_number(record, "DSPM", label)
+}
+
+impl TryFrom<&Record> for DataSetParameters {
+    type Error = crate::error::Error;
+    fn try_from(record: &Record) -> Result<DataSetParameters> {
+        let factor = |label| -> Result<u32> {
+            match number::<u32>(record, label)? {
+                0 => Err(ErrorKind::BadSubfieldValue(label.to_string(), "0".to_string()).into()),
+                factor => Ok(factor),
+            }
+        };
+        Ok(DataSetParameters {
+            horizontal_datum: number(record, "HDAT")?,
+            vertical_datum: number(record, "VDAT")?,
+            sounding_datum: number(record, "SDAT")?,
+            compilation_scale: number(record, "CSCL")?,
+            units: Units::from_dspm(record)?,
+            coordinate_units: number(record, "COUN")?,
+            coordinate_factor: factor("COMF")?,
+            sounding_factor: factor("SOMF")?,
+        })
+    }
+}
+
+// The columns `labels` of every row of the fields `tag` of `record`, as integers.
+fn columns(record: &Record, tag: &str, labels: &[&str]) -> Result<Vec<Vec<i64>>> {
+    rows(record, tag, labels)?
+        .into_iter()
+        .map(|row| {
+            labels
+                .iter()
+                .zip(row)
+                .map(|(label, data)| integer(label, data))
+                .collect()
+        })
+        .collect()
+}
+
+impl DataSetParameters {
+    /// The 2-D coordinates of the SG2D fields of `record`, as (latitude, longitude) in degrees.
+    pub fn coordinates(&self, record: &Record) -> Result<Vec<(f64, f64)>> {
+        let comf = f64::from(self.coordinate_factor);
+        Ok(columns(record, "SG2D", &["YCOO", "XCOO"])?
+            .into_iter()
+            .map(|c| (c[0] as f64 / comf, c[1] as f64 / comf))
+            .collect())
+    }
+
+    /// The soundings of the SG3D fields of `record`, as (latitude, longitude, depth) with the
+    /// depth in the depth units of the data set.
+    pub fn soundings(&self, record: &Record) -> Result<Vec<(f64, f64, f64)>> {
+        let comf = f64::from(self.coordinate_factor);
+        let somf = f64::from(self.sounding_factor);
+        Ok(columns(record, "SG3D", &["YCOO", "XCOO", "VE3D"])?
+            .into_iter()
+            .map(|c| (c[0] as f64 / comf, c[1] as f64 / comf, c[2] as f64 / somf))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_parser::Data;
+
+    fn parameters() -> Record {
+        let mut record = Record::new();
+        for (label, value) in [
+            ("HDAT", 2),
+            ("VDAT", 3),
+            ("SDAT", 23),
+            ("CSCL", 22000),
+            ("DUNI", 1),
+            ("HUNI", 1),
+            ("PUNI", 1),
+            ("COUN", 1),
+            ("COMF", 10_000_000),
+            ("SOMF", 10),
+        ] {
+            record.set("DSPM", label, Data::Integer(Some(value)));
+        }
+        record
+    }
+
+    #[test]
+    fn scaled_coordinates() {
+        let dspm = DataSetParameters::try_from(&parameters()).unwrap();
+        assert_eq!(dspm.compilation_scale, 22000);
+        let int = |i| Data::Integer(Some(i));
+        let mut vector = Record::new();
+        vector.insert_rows(
+            "SG2D",
+            &["YCOO", "XCOO"],
+            vec![
+                vec![int(515_000_000), int(-12_500_000)],
+                vec![int(520_000_000), int(0)],
+            ],
+        );
+        assert_eq!(
+            dspm.coordinates(&vector).unwrap(),
+            vec![(51.5, -1.25), (52.0, 0.0)]
+        );
+        assert!(dspm.soundings(&vector).unwrap().is_empty());
+        vector.insert_rows(
+            "SG3D",
+            &["YCOO", "XCOO", "VE3D"],
+            vec![vec![int(515_000_000), int(5_000_000), int(123)]],
+        );
+        assert_eq!(dspm.soundings(&vector).unwrap(), vec![(51.5, 0.5, 12.3)]);
+
+        let mut record = parameters();
+        record.set("DSPM", "COMF", Data::Integer(Some(0)));
+        assert!(DataSetParameters::try_from(&record).is_err());
+        // Out of the range of the field rather than truncated
+        let mut record = parameters();
+        record.set("DSPM", "HDAT", Data::Integer(Some(258)));
+        match DataSetParameters::try_from(&record).unwrap_err().kind() {
+            ErrorKind::BadSubfieldValue(label, value) => {
+                assert_eq!((&label[..], &value[..]), ("HDAT", "258"))
+            }
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+}
@@ -1,3 +1,6 @@
+// The `Fail` derive from failure generates its impls inside an anonymous const.
+#![allow(non_local_definitions)]
+
 use failure::{Backtrace, Context, Fail};
 use std::fmt;
 use std::io::ErrorKind as IoError;
@@ -20,7 +23,7 @@ impl fmt::Display for Error {
 }
 
 impl Fail for Error {
-    fn cause(&self) -> Option<&Fail> {
+    fn cause(&self) -> Option<&dyn Fail> {
         self.inner.cause()
     }
 
@@ -43,22 +46,65 @@ impl From<Context<ErrorKind>> for Error {
     }
 }
 
+#[allow(clippy::manual_non_exhaustive)]
 #[derive(Fail, Debug)]
 pub enum ErrorKind {
+    #[fail(display = "Bad Agency Table at line {}", _0)]
+    BadAgencyTable(usize),
+    #[fail(display = "Bad Attribute Catalogue at line {}", _0)]
+    BadAttributeCatalog(usize),
+    #[fail(display = "Bad Cell Name: '{}'", _0)]
+    BadCellName(String),
     #[fail(display = "Bad Data Structure Code: {}", _0)]
     BadDataStructureCode(String),
     #[fail(display = "Bad Data Type Code: {}", _0)]
     BadDataTypeCode(String),
+    #[fail(
+        display = "Base address of field area is {} but the directory ends at {}",
+        _0, _1
+    )]
+    BadBaseAddress(usize, usize),
     #[fail(display = "Bad Directory Data")]
     BadDirectoryData,
+    #[fail(display = "Bad Object Catalogue at line {}", _0)]
+    BadObjectCatalog(usize),
+    #[fail(display = "Bad State Store at line {}", _0)]
+    BadStateStore(usize),
+    #[fail(display = "Bad value '{}' for subfield {}", _1, _0)]
+    BadSubfieldValue(String, String),
+    #[fail(
+        display = "Subfield {} ({}) of field {} can not be decoded from '{}'",
+        _1, _2, _0, _3
+    )]
+    BadSubfield(String, usize, String, String),
     #[fail(display = "Bad Truncated Escape Sequence: '{}'", _0)]
     BadTruncEscSeq(String),
+    #[fail(
+        display = "Update control field {} points outside of the field it controls: index {}, {} entries",
+        _0, _1, _2
+    )]
+    BadUpdateControl(String, i64, i64),
+    #[fail(display = "Bad Volume: '{}'", _0)]
+    BadVolume(String),
+    #[fail(display = "Bad File Control Field")]
+    BadFileControlField,
     #[fail(display = "Bad Field Control")]
     BadFieldControl,
+    #[fail(display = "Field controls are not {} bytes as the leader declares", _0)]
+    BadFieldControlLength(usize),
+    #[fail(display = "'{}' is cancelled by update {}", _0, _1)]
+    CellCancelled(String, u32),
     #[fail(display = "Could Not Parse The Catalog File")]
     CouldNotParseCatalog,
     #[fail(display = "Could Not Parse Name")]
     CouldNotParseName,
+    #[fail(display = "Record with RCNM {} and RCID {} already exists", _0, _1)]
+    DuplicateRecord(u32, u32),
+    #[fail(
+        display = "Edition of '{}' is {} but the base cell's is {}",
+        _0, _2, _1
+    )]
+    EditionMismatch(String, String, String),
     #[fail(display = "Empty Format Controls")]
     EmptyFormatControls,
     #[fail(display = "The Data Descriptive Record is not correct.")]
@@ -77,14 +123,53 @@ pub enum ErrorKind {
     EOF,
     #[fail(display = "IOError: {:?}", _0)]
     IOError(IoError),
+    #[fail(display = "Memory budget exceeded, only {} bytes left.", _0)]
+    MemoryBudgetExceeded(u64),
+    #[fail(display = "No base cell found for '{}'", _0)]
+    MissingBaseCell(String),
+    #[fail(display = "Missing field {}", _0)]
+    MissingField(String),
+    #[fail(display = "No record with RCNM {} and RCID {}", _0, _1)]
+    MissingRecord(u32, u32),
+    #[fail(display = "Missing subfield {} in field {}", _1, _0)]
+    MissingSubfield(String, String),
+    #[fail(display = "Update {} of '{}' is missing", _1, _0)]
+    MissingUpdate(String, u32),
+    #[fail(display = "The edges of feature {} do not make closed rings", _0)]
+    OpenRing(u32),
     #[fail(display = "Could not parse '{}' as integer.", _1)]
     ParseIntError(#[cause] std::num::ParseIntError, String),
     #[fail(display = "Could not parse '{}' as float.", _1)]
     ParseFloatError(#[cause] std::num::ParseFloatError, String),
+    #[fail(
+        display = "Record with RCNM {} and RCID {} is at version {}, the update is version {}",
+        _0, _1, _2, _3
+    )]
+    RecordVersionMismatch(u32, u32, i64, i64),
     #[fail(display = "Can not parse Format Control '{}'", _0)]
     UnParsableFormatControl(String),
+    #[fail(
+        display = "Field {} has {} subfields where {} are described: '{}'",
+        _0, _2, _1, _3
+    )]
+    SubfieldCountMismatch(String, usize, usize, String),
+    #[fail(display = "Data Record {} is truncated, {} bytes are missing", _0, _1)]
+    TruncatedRecord(usize, usize),
+    #[fail(display = "Unknown Escape Sequence: 'ESC {}'", _0)]
+    UnknownEscapeSequence(String),
+    #[fail(
+        display = "'{}' is dated {}, before the data set's update date {}",
+        _0, _1, _2
+    )]
+    UpdateDateMismatch(String, String, String),
+    #[fail(display = "'{}' is update {} where update {} was expected", _0, _1, _2)]
+    UpdateOutOfOrder(String, u32, u32),
     #[fail(display = "UtfError")]
     UtfError(#[cause] std::str::Utf8Error),
+    #[fail(display = "Data is {} where {} was expected", _1, _0)]
+    WrongDataType(&'static str, &'static str),
+    #[fail(display = "Subfield {} is {} where {} was expected", _0, _2, _1)]
+    WrongSubfieldType(String, &'static str, &'static str),
     #[doc(hidden)]
     #[fail(display = "")]
     __Nonexhaustive,
@@ -0,0 +1,19 @@
+//! A pull-style alternative to iterating a [`Catalog`](crate::catalog::Catalog), see
+//! [`Catalog::events`](crate::catalog::Catalog::events). Like a
+//! [`Visitor`](crate::visitor::Visitor) it is handed the subfields one at a time as they are
+//! decoded, but asks for each one instead of being called back.
+use crate::data_parser::Data;
+
+/// What [`Iso8211Events`](crate::iso8211::Iso8211Events) comes across next in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The start of a Data Record, with its index counting from zero.
+    StartRecord(usize),
+    /// The start of a field of the current record, with its tag.
+    Field(String),
+    /// A decoded subfield of the current field, with its label. The subfields of a repeating
+    /// group follow each other once for every repetition.
+    Subfield(String, Data),
+    /// The end of the current record.
+    EndRecord,
+}
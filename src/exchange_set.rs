@@ -0,0 +1,366 @@
+//! An exchange set on disk: the directory holding a CATALOG.031 (ENC_ROOT) together with the
+//! files its catalogue lists.
+use crate::catalog::{Catalog, Record, Result};
+use crate::catd::{CatalogRecord, Implementation};
+use crate::cell_name::CellName;
+use crate::error::ErrorKind;
+use crate::updates::{check_sequence, open, read_version, Version};
+use crate::volume::resolve_file;
+use failure::ResultExt;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct ExchangeSet {
+    root: PathBuf,
+    records: Vec<Record>, // The catalogue entries
+}
+
+/// The result of [`ExchangeSet::check_completeness`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completeness {
+    pub missing: Vec<String>, // FILE of the entries whose file is not present
+    pub unexpected: Vec<PathBuf>, // Files present that no entry lists
+}
+
+/// What kind of file a catalogue entry is, from its IMPL subfield and file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemberKind {
+    Catalog,
+    BaseCell,
+    Update,
+    Text,
+    Picture,
+    Other,
+}
+
+/// A file of an exchange set as its catalogue lists it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub kind: MemberKind,
+    pub path: PathBuf,          // The file on disk, which need not be there
+    pub cell: Option<CellName>, // For base cells and updates
+    pub entry: CatalogRecord,
+}
+
+impl Member {
+    fn new(root: &Path, record: &Record) -> Result<Member> {
+        let entry = CatalogRecord::try_from(record)?;
+        let name = entry
+            .file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let cell = match entry.impl_ {
+            Some(Implementation::Binary) => name.parse::<CellName>().ok(),
+            _ => None,
+        };
+        let kind = match (&entry.impl_, &cell) {
+            (_, Some(cell)) if cell.is_base_cell() => MemberKind::BaseCell,
+            (_, Some(_)) => MemberKind::Update,
+            (Some(Implementation::Text), _) => MemberKind::Text,
+            (Some(Implementation::Picture), _) => MemberKind::Picture,
+            _ if name.eq_ignore_ascii_case("CATALOG.031") => MemberKind::Catalog,
+            _ => MemberKind::Other,
+        };
+        Ok(Member {
+            kind,
+            path: root.join(&entry.file),
+            cell,
+            entry,
+        })
+    }
+}
+
+// The version of the base cell of a cell, if listed, and of its updates.
+type CellVersions = (Option<Version>, Vec<(PathBuf, Version)>);
+
+impl Completeness {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+// The file name of the catalogue in a directory, matched case insensitively.
+fn find_catalog(root: &Path) -> Result<PathBuf> {
+    let entries = std::fs::read_dir(root).with_context(|err| ErrorKind::IOError(err.kind()))?;
+    for entry in entries {
+        let path = entry
+            .with_context(|err| ErrorKind::IOError(err.kind()))?
+            .path();
+        let is_catalog = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.eq_ignore_ascii_case("CATALOG.031"));
+        if is_catalog && path.is_file() {
+            return Ok(path);
+        }
+    }
+    Err(ErrorKind::IOError(std::io::ErrorKind::NotFound).into())
+}
+
+// Every file below dir, as paths relative to root.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|err| ErrorKind::IOError(err.kind()))?;
+    for entry in entries {
+        let path = entry
+            .with_context(|err| ErrorKind::IOError(err.kind()))?
+            .path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+// A relative path the way a catalogue writes it, '\' separated and upper case.
+fn catalog_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_uppercase())
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+impl ExchangeSet {
+    /// Reads the catalogue of the exchange set rooted at `root`.
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<ExchangeSet> {
+        let root = root.as_ref().to_path_buf();
+        let file = std::fs::File::open(find_catalog(&root)?)
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        let records = Catalog::new(file)?.collect::<Result<Vec<Record>>>()?;
+        Ok(ExchangeSet { root, records })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// The files the catalogue lists, in its order, resolved against the root.
+    pub fn members(&self) -> impl Iterator<Item = Result<Member>> + '_ {
+        self.records
+            .iter()
+            .map(move |record| Member::new(&self.root, record))
+    }
+
+    fn members_of(&self, kind: MemberKind) -> impl Iterator<Item = Result<Member>> + '_ {
+        self.members()
+            .filter(move |member| member.as_ref().map_or(true, |m| m.kind == kind))
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Result<Member>> + '_ {
+        self.members_of(MemberKind::BaseCell)
+    }
+
+    pub fn updates(&self) -> impl Iterator<Item = Result<Member>> + '_ {
+        self.members_of(MemberKind::Update)
+    }
+
+    pub fn text_files(&self) -> impl Iterator<Item = Result<Member>> + '_ {
+        self.members_of(MemberKind::Text)
+    }
+
+    /// Reads the version of every base cell and update the catalogue lists and checks that the
+    /// update number of each update matches its file name and that the updates of each cell
+    /// follow on from its base cell with [`check_sequence`]. When the exchange set holds only
+    /// updates of a cell they have to follow on from each other. Updates already included in a
+    /// reissued base cell are left out.
+    pub fn check_updates(&self) -> Result<()> {
+        let mut cells: BTreeMap<String, CellVersions> = BTreeMap::new();
+        for member in self.members() {
+            let member = member?;
+            let cell = match &member.cell {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let version = read_version(open(&member.path)?)?;
+            let (base, updates) = cells.entry(cell.base_name()).or_default();
+            if cell.is_base_cell() {
+                *base = (*base).max(Some(version));
+            } else if version.update != cell.update {
+                return Err(ErrorKind::UpdateOutOfOrder(
+                    member.path.display().to_string(),
+                    version.update,
+                    cell.update,
+                )
+                .into());
+            } else {
+                updates.push((member.path, version));
+            }
+        }
+        for (cell, (base, mut updates)) in cells {
+            updates.sort_by_key(|(_, version)| version.update);
+            let base = match (base, updates.first()) {
+                (Some(base), _) => base,
+                (None, Some((_, first))) => Version {
+                    edition: first.edition,
+                    update: first.update - 1,
+                },
+                (None, None) => continue,
+            };
+            updates.retain(|(_, version)| version.update > base.update);
+            check_sequence(&cell, base, &updates)?;
+        }
+        Ok(())
+    }
+
+    /// Compares the catalogue with the files on disk. File names are compared case
+    /// insensitively, as catalogues are usually written in upper case.
+    pub fn check_completeness(&self) -> Result<Completeness> {
+        let mut present = Vec::new();
+        collect_files(&self.root, &self.root, &mut present)?;
+        let present_names: HashSet<String> = present.iter().map(|p| catalog_name(p)).collect();
+        let listed: HashSet<String> = self
+            .records
+            .iter()
+            .filter_map(Record::file)
+            .map(str::to_uppercase)
+            .collect();
+        let missing = self
+            .records
+            .iter()
+            .filter_map(Record::file)
+            .filter(|file| {
+                !present_names.contains(&file.to_uppercase())
+                    && !resolve_file(&self.root, file).exists()
+            })
+            .map(String::from)
+            .collect();
+        let mut unexpected: Vec<PathBuf> = present
+            .into_iter()
+            .filter(|path| !listed.contains(&catalog_name(path)))
+            .collect();
+        unexpected.sort();
+        Ok(Completeness {
+            missing,
+            unexpected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_parser::Data;
+
+    #[test]
+    fn member_kinds() {
+        let record = |file: &str, implementation: &str| {
+            let mut record = Record::new();
+            record.set("CATD", "RCNM", Data::String("CD".into()));
+            record.set("CATD", "RCID", Data::Integer(Some(1)));
+            record.set("CATD", "FILE", Data::String(file.into()));
+            record.set("CATD", "IMPL", Data::String(implementation.into()));
+            record
+        };
+        let set = ExchangeSet {
+            root: PathBuf::from("ENC_ROOT"),
+            records: vec![
+                record("CATALOG.031", "ASC"),
+                record("GB\\GB4X0000.000", "BIN"),
+                record("GB\\GB4X0000.001", "BIN"),
+                record("GB\\NOTES.TXT", "TXT"),
+                record("GB\\PLAN.TIF", "TIF"),
+            ],
+        };
+        let kinds: Vec<MemberKind> = set.members().map(|m| m.unwrap().kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                MemberKind::Catalog,
+                MemberKind::BaseCell,
+                MemberKind::Update,
+                MemberKind::Text,
+                MemberKind::Picture,
+            ]
+        );
+        let update = set.updates().next().unwrap().unwrap();
+        assert_eq!(
+            update.path,
+            Path::new("ENC_ROOT").join("GB").join("GB4X0000.001")
+        );
+        assert_eq!(update.cell.unwrap().update, 1);
+        assert_eq!(set.cells().count(), 1);
+        assert_eq!(set.text_files().count(), 1);
+    }
+
+    #[test]
+    fn catalog_names() {
+        assert_eq!(
+            catalog_name(&Path::new("gb").join("GB4X0000.000")),
+            "GB\\GB4X0000.000"
+        );
+    }
+
+    #[test]
+    fn update_versions() {
+        use crate::test_support::FileBuilder;
+        let root = std::env::temp_dir().join(format!("rust-s57-exchange-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let write = |file: &str, edition: &str, update: &str| {
+            let bytes = FileBuilder::new()
+                .field("DSID", "Data Set Identification Field", "EDTN!UPDN", "(2A)")
+                .record(&[(
+                    "DSID",
+                    &[
+                        ("EDTN", Data::String(edition.into())),
+                        ("UPDN", Data::String(update.into())),
+                    ],
+                )])
+                .build();
+            std::fs::write(root.join(file), bytes).unwrap();
+        };
+        let record = |file: &str| {
+            let mut record = Record::new();
+            record.set("CATD", "RCNM", Data::String("CD".into()));
+            record.set("CATD", "RCID", Data::Integer(Some(1)));
+            record.set("CATD", "FILE", Data::String(file.into()));
+            record.set("CATD", "IMPL", Data::String("BIN".into()));
+            record
+        };
+        let set = |files: &[&str]| ExchangeSet {
+            root: root.clone(),
+            records: files.iter().map(|file| record(file)).collect(),
+        };
+        write("GB4X0000.000", "2", "0");
+        write("GB4X0000.001", "2", "1");
+        write("GB4X0000.002", "2", "2");
+        write("GB4X0000.003", "2", "2");
+        write("GB5X0000.004", "1", "4");
+        write("GB5X0000.005", "1", "5");
+        let files = [
+            "GB4X0000.002",
+            "GB4X0000.000",
+            "GB4X0000.001",
+            "GB5X0000.004",
+        ];
+        assert!(set(&files).check_updates().is_ok());
+        assert!(set(&files[1..]).check_updates().is_ok());
+        assert!(set(&["GB5X0000.004", "GB5X0000.005"])
+            .check_updates()
+            .is_ok());
+        match set(&files[..2]).check_updates().unwrap_err().kind() {
+            ErrorKind::MissingUpdate(cell, 1) => assert_eq!(cell, "GB4X0000"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        match set(&["GB4X0000.003"]).check_updates().unwrap_err().kind() {
+            ErrorKind::UpdateOutOfOrder(_, 2, 3) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        write("GB4X0000.001", "1", "1");
+        match set(&files).check_updates().unwrap_err().kind() {
+            ErrorKind::EditionMismatch(file, base, edition) => {
+                assert!(file.ends_with("GB4X0000.001"));
+                assert_eq!((&base[..], &edition[..]), ("2", "1"));
+            }
+            kind => panic!("unexpected error: {}", kind),
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+//! Feature records of an ENC cell as one typed struct: the feature record identifier (FRID), the
+//! feature object identifier (FOID), the attributes (ATTF and NATF) and the pointers to other
+//! features (FFPT) and to spatial records (FSPT).
+use crate::agency;
+use crate::attributes::{AttributeCatalog, AttributeValue};
+use crate::catalog::{Record, Result};
+use crate::data_parser::Data;
+use crate::dataset::{integer, number, pointer_name, rows, RecordType};
+use crate::error::ErrorKind;
+use crate::history::UpdateInstruction;
+use crate::meta::SpatialPointer;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+/// PRIM, the geometric primitive of a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Point = 1,
+    Line = 2,
+    Area = 3,
+    None = 255, // Meta and collection features
+}
+
+/// GRUP, the group of a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Group {
+    SkinOfTheEarth = 1, // Group 1, features that together cover the cell without overlap
+    Other = 2,          // Group 2, every other feature
+    None = 255,         // Meta and collection features
+}
+
+impl Primitive {
+    pub fn from_code(code: i64) -> Option<Primitive> {
+        match code {
+            1 => Some(Primitive::Point),
+            2 => Some(Primitive::Line),
+            3 => Some(Primitive::Area),
+            255 => Some(Primitive::None),
+            _ => None,
+        }
+    }
+}
+
+impl Group {
+    pub fn from_code(code: i64) -> Option<Group> {
+        match code {
+            1 => Some(Group::SkinOfTheEarth),
+            2 => Some(Group::Other),
+            255 => Some(Group::None),
+            _ => None,
+        }
+    }
+}
+
+/// The world wide unique name of a feature, from FOID or the LNAM of an FFPT pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LongName {
+    pub agency: u16,      // AGEN
+    pub id: u32,          // FIDN
+    pub subdivision: u16, // FIDS
+}
+
+impl LongName {
+    /// The long name packed into a B(64) LNAM: AGEN, FIDN and FIDS little endian.
+    pub fn from_lnam(lnam: i64) -> LongName {
+        let lnam = lnam as u64;
+        LongName {
+            agency: lnam as u16,
+            id: (lnam >> 16) as u32,
+            subdivision: (lnam >> 48) as u16,
+        }
+    }
+}
+
+/// The producer (see [`agency::producer`]), FIDN and FIDS, e.g. "GB 123456 1".
+impl Display for LongName {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            agency::producer(self.agency),
+            self.id,
+            self.subdivision
+        )
+    }
+}
+
+/// A pointer from a feature to another feature, one entry of its FFPT field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeaturePointer {
+    pub name: LongName,   // LNAM of the feature pointed to
+    pub relationship: u8, // RIND, 1 master, 2 slave, 3 peer
+    pub comment: String,  // COMT
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureRecord {
+    pub rcnm: RecordType,
+    pub rcid: u32,
+    pub primitive: Primitive,
+    pub group: Group,
+    pub objl: u16,                               // The object class
+    pub version: u32,                            // RVER
+    pub instruction: UpdateInstruction,          // RUIN, always insert in a base cell
+    pub long_name: Option<LongName>,             // FOID
+    pub attributes: Vec<(u16, String)>,          // ATTL and ATVL of every ATTF entry
+    pub national_attributes: Vec<(u16, String)>, // ATTL and ATVL of every NATF entry
+    pub features: Vec<FeaturePointer>,           // FFPT
+    pub spatial: Vec<SpatialPointer>,            // FSPT
+}
+
+impl FeatureRecord {
+    pub fn attribute(&self, attl: u16) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(code, _)| *code == attl)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The attribute `attl` decoded with `catalog`.
+    pub fn attribute_value(&self, attl: u16, catalog: &AttributeCatalog) -> Option<AttributeValue> {
+        self.attribute(attl).map(|atvl| catalog.decode(attl, atvl))
+    }
+}
+
+fn text(data: &Data) -> String {
+    match data {
+        Data::String(s) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+// A coded subfield, e.g. PRIM or RUIN, decoded by `from_code`.
+pub(crate) fn code<T>(label: &str, data: &Data, from_code: fn(i64) -> Option<T>) -> Result<T> {
+    let code = integer(label, data)?;
+    from_code(code)
+        .ok_or_else(|| ErrorKind::BadSubfieldValue(label.to_string(), code.to_string()).into())
+}
+
+// The ATTL and ATVL of every entry of the attribute fields `tag`, e.g. ATTF or ATTV.
+pub(crate) fn attributes(record: &Record, tag: &str) -> Result<Vec<(u16, String)>> {
+    rows(record, tag, &["ATTL", "ATVL"])?
+        .into_iter()
+        .map(|row| Ok((number("ATTL", row[0])?, text(row[1]))))
+        .collect()
+}
+
+impl TryFrom<&Record> for FeatureRecord {
+    type Error = crate::error::Error;
+    fn try_from(record: &Record) -> Result<FeatureRecord> {
+        let frid = rows(
+            record,
+            "FRID",
+            &["RCNM", "RCID", "PRIM", "GRUP", "OBJL", "RVER", "RUIN"],
+        )?;
+        let frid = frid
+            .first()
+            .ok_or_else(|| ErrorKind::MissingField("FRID".to_string()))?;
+        let long_name = match rows(record, "FOID", &["AGEN", "FIDN", "FIDS"])?.first() {
+            Some(foid) => Some(LongName {
+                agency: number("AGEN", foid[0])?,
+                id: number("FIDN", foid[1])?,
+                subdivision: number("FIDS", foid[2])?,
+            }),
+            None => None,
+        };
+        let features = rows(record, "FFPT", &["LNAM", "RIND", "COMT"])?
+            .into_iter()
+            .map(|row| {
+                Ok(FeaturePointer {
+                    name: LongName::from_lnam(integer("LNAM", row[0])?),
+                    relationship: number("RIND", row[1])?,
+                    comment: text(row[2]),
+                })
+            })
+            .collect::<Result<Vec<FeaturePointer>>>()?;
+        let spatial = rows(record, "FSPT", &["NAME", "ORNT", "USAG", "MASK"])?
+            .into_iter()
+            .map(|row| {
+                let (rcnm, rcid) = pointer_name(row[0]).ok_or_else(|| {
+                    ErrorKind::BadSubfieldValue("NAME".to_string(), row[0].to_string())
+                })?;
+                Ok(SpatialPointer {
+                    rcnm: rcnm as u8,
+                    rcid,
+                    orientation: number("ORNT", row[1])?,
+                    usage: number("USAG", row[2])?,
+                    mask: number("MASK", row[3])?,
+                })
+            })
+            .collect::<Result<Vec<SpatialPointer>>>()?;
+        Ok(FeatureRecord {
+            rcnm: RecordType::from_data(frid[0])?,
+            rcid: number("RCID", frid[1])?,
+            primitive: code("PRIM", frid[2], Primitive::from_code)?,
+            group: code("GRUP", frid[3], Group::from_code)?,
+            objl: number("OBJL", frid[4])?,
+            version: number("RVER", frid[5])?,
+            instruction: code("RUIN", frid[6], UpdateInstruction::from_code)?,
+            long_name,
+            attributes: attributes(record, "ATTF")?,
+            national_attributes: attributes(record, "NATF")?,
+            features,
+            spatial,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(i: i64) -> Data {
+        Data::Integer(Some(i))
+    }
+
+    #[test]
+    fn feature_record() {
+        let mut record = Record::new();
+        for (label, value) in [
+            ("RCNM", 100),
+            ("RCID", 7),
+            ("PRIM", 3),
+            ("GRUP", 1),
+            ("OBJL", 42),
+            ("RVER", 1),
+            ("RUIN", 1),
+        ] {
+            record.set("FRID", label, int(value));
+        }
+        for (label, value) in [("AGEN", 540), ("FIDN", 123_456), ("FIDS", 1)] {
+            record.set("FOID", label, int(value));
+        }
+        let string = |s: &str| Data::String(s.to_string());
+        record.insert_rows(
+            "ATTF",
+            &["ATTL", "ATVL"],
+            vec![vec![int(87), string("0")], vec![int(88), string("5")]],
+        );
+        let lnam = 540 | 99 << 16 | 2 << 48;
+        record.insert_rows(
+            "FFPT",
+            &["LNAM", "RIND", "COMT"],
+            vec![vec![int(lnam), int(2), string("")]],
+        );
+        record.insert_rows(
+            "FSPT",
+            &["NAME", "ORNT", "USAG", "MASK"],
+            vec![vec![int(130 | 12 << 8), int(1), int(1), int(255)]],
+        );
+        let feature = FeatureRecord::try_from(&record).unwrap();
+        assert_eq!(feature.rcnm, RecordType::Feature);
+        assert_eq!(
+            (feature.primitive, feature.group),
+            (Primitive::Area, Group::SkinOfTheEarth)
+        );
+        assert_eq!(feature.instruction, UpdateInstruction::Insert);
+        assert_eq!(feature.long_name.unwrap().id, 123_456);
+        assert_eq!(feature.attribute(88), Some("5"));
+        assert!(feature.national_attributes.is_empty());
+        assert_eq!(
+            feature.features[0].name,
+            LongName {
+                agency: 540,
+                id: 99,
+                subdivision: 2
+            }
+        );
+        let producer = if cfg!(feature = "agencies") {
+            "GB"
+        } else {
+            "540"
+        };
+        assert_eq!(
+            feature.features[0].name.to_string(),
+            format!("{} 99 2", producer)
+        );
+        assert_eq!(
+            (feature.spatial[0].rcnm, feature.spatial[0].rcid),
+            (130, 12)
+        );
+
+        record.set("FRID", "PRIM", int(4));
+        assert!(FeatureRecord::try_from(&record).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! The polygons of area features. The FSPT field of an area feature points to the edges of its
+//! boundary in order, ring after ring, and every edge runs from its beginning node through its
+//! own coordinates to its end node.
+use crate::catalog::{Record, Result};
+use crate::dataset::{DataSet, RecordType};
+use crate::dspm::DataSetParameters;
+use crate::error::ErrorKind;
+use crate::feature::FeatureRecord;
+use crate::meta::SpatialPointer;
+use crate::vector::VectorRecord;
+
+/// A closed ring of the boundary of an area feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ring {
+    pub exterior: bool,               // USAG 1 or 3 rather than 2, interior
+    pub coordinates: Vec<(f64, f64)>, // (latitude, longitude), the first repeated at the end
+}
+
+impl Ring {
+    fn is_closed(&self) -> bool {
+        self.coordinates.len() > 2 && self.coordinates.first() == self.coordinates.last()
+    }
+}
+
+impl DataSet {
+    /// The rings of the edges the FSPT field of `feature` points to, empty for a feature that
+    /// is not an area. Fails with `ErrorKind::OpenRing` if the edges do not join up into
+    /// closed rings.
+    pub fn rings(&self, feature: &FeatureRecord) -> Result<Vec<Ring>> {
+        let dspm = self.dspm()?;
+        let mut rings = Vec::new();
+        let mut open: Option<Ring> = None;
+        let edges = feature
+            .spatial
+            .iter()
+            .filter(|pointer| pointer.rcnm == RecordType::Edge as u8);
+        for pointer in edges {
+            let coordinates = self.edge_coordinates(pointer, &dspm)?;
+            let ring = match open.take() {
+                None => Ring {
+                    exterior: pointer.usage != 2,
+                    coordinates,
+                },
+                Some(mut ring) if ring.coordinates.last() == coordinates.first() => {
+                    ring.coordinates.extend(coordinates.into_iter().skip(1));
+                    ring
+                }
+                Some(_) => return Err(ErrorKind::OpenRing(feature.rcid).into()),
+            };
+            if ring.is_closed() {
+                rings.push(ring);
+            } else {
+                open = Some(ring);
+            }
+        }
+        match open {
+            Some(_) => Err(ErrorKind::OpenRing(feature.rcid).into()),
+            None => Ok(rings),
+        }
+    }
+
+    // The coordinates of the edge `pointer` points to, from its beginning node to its end node
+    // or the other way around if the pointer is reversed.
+    fn edge_coordinates(
+        &self,
+        pointer: &SpatialPointer,
+        dspm: &DataSetParameters,
+    ) -> Result<Vec<(f64, f64)>> {
+        let edge = VectorRecord::new(self.vector_or_err(pointer.rcnm, pointer.rcid)?, dspm)?;
+        let node = |topology: u8| -> Result<(f64, f64)> {
+            let pointer = edge
+                .pointers
+                .iter()
+                .find(|p| p.topology == topology)
+                .ok_or_else(|| ErrorKind::MissingField("VRPT".to_string()))?;
+            let node = self.vector_or_err(pointer.rcnm, pointer.rcid)?;
+            dspm.coordinates(node)?
+                .first()
+                .copied()
+                .ok_or_else(|| ErrorKind::MissingField("SG2D".to_string()).into())
+        };
+        let (begin, end) = (node(1)?, node(2)?);
+        let mut coordinates = vec![begin];
+        coordinates.extend(edge.coordinates);
+        coordinates.push(end);
+        if pointer.orientation == 2 {
+            coordinates.reverse();
+        }
+        Ok(coordinates)
+    }
+
+    fn vector_or_err(&self, rcnm: u8, rcid: u32) -> Result<&Record> {
+        let name = (u32::from(rcnm), rcid);
+        self.vector(name)
+            .ok_or_else(|| ErrorKind::MissingRecord(name.0, name.1).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_cell;
+    use std::convert::TryFrom;
+
+    fn feature(data_set: &DataSet, rcid: u32) -> FeatureRecord {
+        FeatureRecord::try_from(data_set.feature((100, rcid)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rings() {
+        let data_set = DataSet::read(&sample_cell()[..]).unwrap();
+        // DEPARE, ending with the coast from node 5 back to node 2
+        let rings = data_set.rings(&feature(&data_set, 2)).unwrap();
+        assert_eq!(
+            rings,
+            vec![Ring {
+                exterior: true,
+                coordinates: vec![
+                    (51.0, 0.5),
+                    (51.0, 1.0),
+                    (52.0, 1.0),
+                    (52.0, 0.5),
+                    (51.5, 0.6),
+                    (51.0, 0.5),
+                ],
+            }]
+        );
+        // UWTROC is a point
+        assert!(data_set.rings(&feature(&data_set, 3)).unwrap().is_empty());
+
+        // LNDARE without its last edge
+        let mut lndare = feature(&data_set, 1);
+        lndare.spatial.pop();
+        match data_set.rings(&lndare).unwrap_err().kind() {
+            ErrorKind::OpenRing(1) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        // DEPARE with a hole around the rock, the edge looping from node 7 back to it
+        let mut depare = feature(&data_set, 2);
+        depare.spatial.push(SpatialPointer {
+            rcid: 8,
+            usage: 2,
+            ..depare.spatial[0]
+        });
+        let rings = data_set.rings(&depare).unwrap();
+        assert_eq!(rings.len(), 2);
+        assert!(rings[0].exterior);
+        assert!(!rings[1].exterior);
+        assert_eq!(
+            rings[1].coordinates,
+            vec![
+                (51.4, 0.7),
+                (51.4, 0.9),
+                (51.6, 0.9),
+                (51.6, 0.7),
+                (51.4, 0.7)
+            ]
+        );
+        depare.spatial.push(SpatialPointer {
+            rcid: 9,
+            ..depare.spatial[0]
+        });
+        match data_set.rings(&depare).unwrap_err().kind() {
+            ErrorKind::MissingRecord(130, 9) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! The history of individual features across the updates of a cell. Every feature record in an
+//! update file carries its record version (RVER of FRID) and an update instruction (RUIN of
+//! FRID), so by feeding the update records to a [`FeatureHistory`] as they are applied it is
+//! possible to show, per feature, what each update did. A [`DataSet`] does so itself once
+//! [`DataSet::keep_history`] is called.
+use crate::apply::{CONTROLS, DELETE};
+use crate::catalog::{Catalog, Record, Result};
+use crate::data_parser::Data;
+use crate::dataset::{number, record_name, rows, subfield_number, DataSet, RecordName};
+use crate::error::ErrorKind;
+use crate::feature::code;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// RUIN, the record update instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpdateInstruction {
+    Insert, // 1, "I"
+    Delete, // 2, "D"
+    Modify, // 3, "M"
+}
+
+impl UpdateInstruction {
+    pub fn from_code(code: i64) -> Option<UpdateInstruction> {
+        match code {
+            1 => Some(UpdateInstruction::Insert),
+            2 => Some(UpdateInstruction::Delete),
+            3 => Some(UpdateInstruction::Modify),
+            _ => None,
+        }
+    }
+}
+
+/// A change to the pointers of a feature, from an FSPC or FFPC control field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointerChange {
+    pub tag: String, // The pointer field changed, FSPT or FFPT
+    pub instruction: UpdateInstruction,
+    pub index: u32, // Of the first pointer changed, counting from 1
+    pub count: u32, // Number of pointers changed
+}
+
+/// What a single update did to a feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub update: u32,  // Update number of the file the change came from
+    pub version: u32, // RVER, the version of the feature after the change
+    pub instruction: UpdateInstruction,
+    pub fields: Vec<String>, // Tags of the fields the update record carries besides FRID
+    // ATTL and new ATVL of every ATTF and NATF entry of the update, None for a deleted attribute
+    pub attributes: Vec<(u16, Option<String>)>,
+    pub pointers: Vec<PointerChange>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FeatureHistory {
+    features: BTreeMap<RecordName, Vec<Change>>,
+}
+
+// The ATTL and ATVL of the attribute fields of `record`, None for the delete character.
+fn attribute_changes(record: &Record) -> Result<Vec<(u16, Option<String>)>> {
+    let mut changes = Vec::new();
+    for tag in &["ATTF", "NATF"] {
+        for row in rows(record, tag, &["ATTL", "ATVL"])? {
+            let value = match row[1] {
+                Data::String(s) if s == DELETE => None,
+                Data::String(s) => Some(s.clone()),
+                _ => Some(String::new()),
+            };
+            changes.push((number("ATTL", row[0])?, value));
+        }
+    }
+    Ok(changes)
+}
+
+// The changes the FSPC and FFPC control fields of `record` make to its pointers.
+fn pointer_changes(record: &Record) -> Result<Vec<PointerChange>> {
+    let mut changes = Vec::new();
+    for (control, tag, labels) in CONTROLS.iter().filter(|(_, tag, _)| *tag != "VRPT") {
+        let field = match record.get(control) {
+            Some(field) => field,
+            None => continue,
+        };
+        let instruction = field.get(labels[0]).unwrap_or(&Data::Missing);
+        changes.push(PointerChange {
+            tag: tag.to_string(),
+            instruction: code(labels[0], instruction, UpdateInstruction::from_code)?,
+            index: subfield_number(record, control, labels[1])?,
+            count: subfield_number(record, control, labels[2])?,
+        });
+    }
+    Ok(changes)
+}
+
+impl FeatureHistory {
+    pub fn new() -> FeatureHistory {
+        FeatureHistory::default()
+    }
+
+    /// Adds the change made by `record` in the update `update`. Records other than feature
+    /// records are ignored.
+    pub fn ingest(&mut self, update: u32, record: &Record) -> Result<()> {
+        if record.get("FRID").is_none() {
+            return Ok(());
+        }
+        let name = record_name(record, "FRID")?;
+        let ruin = subfield_number(record, "FRID", "RUIN")?;
+        let instruction = UpdateInstruction::from_code(ruin)
+            .ok_or_else(|| ErrorKind::BadSubfieldValue("RUIN".to_string(), ruin.to_string()))?;
+        let mut fields: Vec<String> = record
+            .tags()
+            .filter(|&tag| tag != "FRID" && tag != "0001")
+            .map(String::from)
+            .collect();
+        fields.sort();
+        self.features.entry(name).or_default().push(Change {
+            update,
+            version: subfield_number(record, "FRID", "RVER")?,
+            instruction,
+            fields,
+            attributes: attribute_changes(record)?,
+            pointers: pointer_changes(record)?,
+        });
+        Ok(())
+    }
+
+    /// Adds every feature record of the update file `update` read from `rdr`.
+    pub fn ingest_file<R: Read>(&mut self, update: u32, rdr: R) -> Result<()> {
+        for record in Catalog::new(rdr)? {
+            self.ingest(update, &record?)?;
+        }
+        Ok(())
+    }
+
+    /// The changes made to the feature `name`, oldest first.
+    pub fn feature(&self, name: RecordName) -> &[Change] {
+        self.features.get(&name).map_or(&[], |changes| changes)
+    }
+
+    /// The features changed by the update `update`, with what was done to them.
+    pub fn changes_in(&self, update: u32) -> impl Iterator<Item = (RecordName, &Change)> {
+        self.features.iter().flat_map(move |(name, changes)| {
+            changes
+                .iter()
+                .filter(move |change| change.update == update)
+                .map(move |change| (*name, change))
+        })
+    }
+}
+
+impl DataSet {
+    /// Starts recording the changes the updates applied from now on make to features, see
+    /// [`DataSet::history`].
+    pub fn keep_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(FeatureHistory::new());
+        }
+    }
+
+    /// The changes made to features by the updates applied since [`DataSet::keep_history`] was
+    /// called, None if it was not.
+    pub fn history(&self) -> Option<&FeatureHistory> {
+        self.history.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn feature_record(rcid: i64, rver: i64, ruin: i64, tags: &[&str]) -> Record {
+        let frid = [
+            ("RCNM", 100),
+            ("RCID", rcid),
+            ("RVER", rver),
+            ("RUIN", ruin),
+        ]
+        .iter()
+        .map(|(label, value)| (label.to_string(), Data::Integer(Some(*value))))
+        .collect();
+        let mut fields = HashMap::new();
+        fields.insert("FRID".to_string(), frid);
+        for tag in tags {
+            fields.insert(tag.to_string(), HashMap::new());
+        }
+        Record::from_fields(fields)
+    }
+
+    fn with_attributes(mut record: Record, attributes: &[(i64, &str)]) -> Record {
+        let rows = attributes
+            .iter()
+            .map(|(attl, atvl)| vec![Data::Integer(Some(*attl)), Data::String(atvl.to_string())])
+            .collect();
+        record.insert_rows("ATTF", &["ATTL", "ATVL"], rows);
+        record
+    }
+
+    #[test]
+    fn history() {
+        let mut history = FeatureHistory::new();
+        let mut modify = with_attributes(feature_record(7, 2, 3, &[]), &[(88, "10"), (87, DELETE)]);
+        for (label, value) in [("FSUI", 2), ("FSIX", 3), ("NSPT", 1)] {
+            modify.set("FSPC", label, Data::Integer(Some(value)));
+        }
+        history.ingest(1, &modify).unwrap();
+        history
+            .ingest(
+                1,
+                &with_attributes(feature_record(8, 1, 1, &["FOID"]), &[(116, "Name")]),
+            )
+            .unwrap();
+        history.ingest(2, &feature_record(7, 3, 2, &[])).unwrap();
+        assert!(history.ingest(2, &feature_record(9, 1, 4, &[])).is_err());
+        let changes = history.feature((100, 7));
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].fields, vec!["ATTF", "FSPC"]);
+        assert_eq!(
+            changes[0].attributes,
+            vec![(88, Some("10".to_string())), (87, None)]
+        );
+        assert_eq!(
+            changes[0].pointers,
+            vec![PointerChange {
+                tag: "FSPT".to_string(),
+                instruction: UpdateInstruction::Delete,
+                index: 3,
+                count: 1,
+            }]
+        );
+        assert_eq!(changes[1].instruction, UpdateInstruction::Delete);
+        assert_eq!(changes[1].version, 3);
+        assert!(changes[1].attributes.is_empty());
+        let first: Vec<_> = history.changes_in(1).map(|(name, _)| name).collect();
+        assert_eq!(first, vec![(100, 7), (100, 8)]);
+        assert_eq!(
+            history.feature((100, 8))[0].attributes,
+            vec![(116, Some("Name".to_string()))]
+        );
+        assert!(history.feature((100, 1)).is_empty());
+    }
+}
@@ -0,0 +1,3661 @@
+//! Reading of ISO/IEC 8211 files of any product: the leader, directory and data descriptive
+//! fields of the Data Descriptive Record, and the Data Records it describes. Nothing here is
+//! particular to S-57, which builds on this in [`crate::catalog`]. The names of the leader and
+//! field control parts follow the standard.
+use crate::data_parser::{Data, Encoding, ParseData, ParseType};
+use crate::diagnostics::{Diagnostic, DiagnosticKind, DiagnosticsReport};
+use crate::error::{Error, ErrorKind};
+use crate::events::Event;
+use crate::visitor::{Flow, Visitor};
+use failure::ResultExt;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Seek, SeekFrom};
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::str::{from_utf8, FromStr};
+
+const DRID: &str = "DRID";
+const TOPLVL: &str = "0001";
+
+pub(crate) const RECORD_SEPARATOR: u8 = 0x1e;
+pub(crate) const UNIT_SEPARATOR: u8 = 0x1f;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Leader {
+    rl: usize,          // Record Length
+    drl: Option<usize>, // Record Length as declared, if it is a number
+    il: char,           // Interchange Level
+    li: char,           // Leader Identifier
+    cei: char,          // In Line Code Extension Indicator
+    vn: char,           // Verison number
+    ai: char,           // Application Indicator
+    fcl: [char; 2],     // Field Control Length
+    ba: u32,            // Base Address Of Field Area
+    csi: [char; 3],     // Extended Character Set Indicator
+    // Values of Entry Map
+    flf: usize, // Size Of Field Length Field
+    fpf: usize, // Size Of Field Position Field
+    rsv: char,  // Reserved
+    ftf: usize, // Size Of Field Tag Field
+}
+
+/// The values of a record leader, for applications that keep their own flags in the
+/// application indicator or reserved byte, or cross-check the lengths. Records that reuse the
+/// leader of one with leader identifier 'R' have its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderInfo {
+    pub record_length: Option<usize>, // As declared, None if it is not a number
+    pub interchange_level: char,
+    pub leader_identifier: char,
+    pub code_extension_indicator: char,
+    pub version: char,
+    pub application_indicator: char,
+    pub base_address: usize, // Of the field area, as declared
+    pub reserved: char,      // Reserved byte of the entry map
+}
+
+impl Leader {
+    pub(crate) fn info(&self) -> LeaderInfo {
+        LeaderInfo {
+            record_length: self.drl,
+            interchange_level: self.il,
+            leader_identifier: self.li,
+            code_extension_indicator: self.cei,
+            version: self.vn,
+            application_indicator: self.ai,
+            base_address: self.ba as usize,
+            reserved: self.rsv,
+        }
+    }
+
+    // The size in bytes of one directory entry
+    pub(crate) fn entry_size(&self) -> usize {
+        self.ftf + self.flf + self.fpf
+    }
+
+    // The size in bytes of the field controls of each data descriptive field. Level 1 files
+    // have none and leave it blank.
+    fn field_control_length(&self) -> Result<usize> {
+        match self.fcl {
+            [' ', ' '] => Ok(0),
+            [a, b] => match format!("{}{}", a, b).parse() {
+                Ok(fcl @ (0 | 6 | 9)) => Ok(fcl),
+                Ok(fcl) => Err(ErrorKind::BadFieldControlLength(fcl).into()),
+                Err(_) => Err(ErrorKind::InvalidLeader.into()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DirectoryEntry {
+    pub(crate) id: String,    // The Id of the field
+    pub(crate) length: usize, // The length of the field in bytes
+    pub(crate) offset: usize, // The offset in bytes form the start of the field area
+}
+
+impl Display for DirectoryEntry {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        Display::fmt(&self.id, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DataStructureCode {
+    SDI, // Single Data Item
+    LS,  // Linear Structure
+    MDS, // Multi-Dimensional structure
+}
+
+impl FromStr for DataStructureCode {
+    type Err = crate::error::Error;
+    fn from_str(value: &str) -> Result<DataStructureCode> {
+        match value {
+            "0" => Ok(DataStructureCode::SDI),
+            "1" => Ok(DataStructureCode::LS),
+            "2" => Ok(DataStructureCode::MDS),
+            _ => Err(ErrorKind::BadDataStructureCode(value.to_string()).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DataTypeCode {
+    CS,  // Character String
+    IP,  // Implicit Point
+    EP,  // Explicit Point (Real)
+    BF,  // Binary Form
+    MDT, // Mixed Data Types
+}
+impl FromStr for DataTypeCode {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<DataTypeCode> {
+        match value {
+            "0" => Ok(DataTypeCode::CS),
+            "1" => Ok(DataTypeCode::IP),
+            "2" => Ok(DataTypeCode::EP),
+            "5" => Ok(DataTypeCode::BF),
+            "6" => Ok(DataTypeCode::MDT),
+            _ => Err(ErrorKind::BadDataTypeCode(value.to_string()).into()),
+        }
+    }
+}
+
+// Truncated Escape Sequence
+#[derive(Debug, Clone, PartialEq)]
+enum TruncEscSeq {
+    LE0, //Lexical Level 0
+    LE1, //Lexical Level 1
+    LE2, //Lexical Level 2
+}
+impl FromStr for TruncEscSeq {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<TruncEscSeq> {
+        match value {
+            "   " => Ok(TruncEscSeq::LE0),
+            "-A " => Ok(TruncEscSeq::LE1),
+            "%/A" => Ok(TruncEscSeq::LE2),
+            _ => Err(ErrorKind::BadTruncEscSeq(value.to_string()).into()),
+        }
+    }
+}
+
+impl TruncEscSeq {
+    fn level(&self) -> u8 {
+        match self {
+            TruncEscSeq::LE0 => 0,
+            TruncEscSeq::LE1 => 1,
+            TruncEscSeq::LE2 => 2,
+        }
+    }
+
+    // Whether all characters in s can be represented in this lexical level, i.e. ASCII for level
+    // 0 and ISO 8859-1 for level 1.
+    fn admits(&self, s: &str) -> bool {
+        match self {
+            TruncEscSeq::LE0 => s.is_ascii(),
+            TruncEscSeq::LE1 => s.chars().all(|c| (c as u32) <= 0xff),
+            TruncEscSeq::LE2 => true,
+        }
+    }
+}
+
+fn check_lexical_level(tes: &TruncEscSeq, label: &str, data: &Data) -> Option<DiagnosticKind> {
+    match data {
+        Data::String(s) if !tes.admits(s) => Some(DiagnosticKind::LexicalLevelMismatch {
+            label: label.to_string(),
+            declared: tes.level(),
+        }),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FileControlField {
+    fic: FieldControls,
+    title: String,
+    pairs: Vec<(String, String)>, // Field tag pairs, (parent, child)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FieldControls {
+    dsc: DataStructureCode,
+    dtc: DataTypeCode,
+    aux: u8,                // Auxilliary controls
+    prt: PrintableGraphics, // Printable graphics
+    tes: TruncEscSeq,
+}
+
+/// The printable graphics the field controls of a field declare for the terminators, ";&" in
+/// S-57. Some producers write them in place of the unit terminator in the DDR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintableGraphics {
+    pub field_terminator: char,
+    pub unit_terminator: char,
+}
+
+impl PrintableGraphics {
+    // Bytes 5 and 6 of the field controls, or ";&" for the implied controls of level 1.
+    fn from_field_controls(fic_bytes: &[u8]) -> PrintableGraphics {
+        match fic_bytes.get(4..6) {
+            Some(&[ft, ut]) => PrintableGraphics {
+                field_terminator: ft as char,
+                unit_terminator: ut as char,
+            },
+            _ => PrintableGraphics {
+                field_terminator: ';',
+                unit_terminator: '&',
+            },
+        }
+    }
+}
+
+// Data Descriptive Field Entry
+#[derive(Debug, Clone, PartialEq)]
+struct DDFEntry {
+    fic: FieldControls,
+    name: String,
+    foc: Vec<(String, ParseData)>,
+    repeat: Option<usize>, // Index of the subfield marked with '*' in the array descriptor
+    rows: Vec<String>,     // Row labels of a Cartesian array descriptor
+}
+
+impl DDFEntry {
+    // Index of the first subfield of the group that repeats until the field terminator. Without
+    // a '*' the whole subfield set of a multi-dimensional or Cartesian field repeats.
+    fn repeating_group(&self) -> Option<usize> {
+        match self.repeat {
+            Some(index) => Some(index),
+            None if self.fic.dsc == DataStructureCode::MDS || !self.rows.is_empty() => Some(0),
+            None => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// A record split into its leader, directory and field area
+type RecordParts = (Leader, Vec<DirectoryEntry>, Vec<u8>);
+
+pub(crate) fn parse_to_usize(bytes: &[u8]) -> Result<usize> {
+    let s = from_utf8(bytes).with_context(|&err| ErrorKind::UtfError(err))?;
+    Ok(s.parse().with_context(|err: &std::num::ParseIntError| {
+        ErrorKind::ParseIntError(err.clone(), s.to_string())
+    })?)
+}
+
+pub(crate) fn parse_to_string(bytes: &[u8]) -> Result<String> {
+    Ok(from_utf8(bytes)
+        .with_context(|&err| ErrorKind::UtfError(err))?
+        .to_string())
+}
+
+fn parse_leader(byte: &[u8], len: usize) -> Result<Leader> {
+    let rl = len;
+    let il = byte[0] as char;
+    let li = byte[1] as char;
+    let cei = byte[2] as char;
+    let vn = byte[3] as char;
+    let ai = byte[4] as char;
+    let fcl = [byte[5] as char, byte[6] as char];
+    let ba = parse_to_usize(&byte[7..12]).context(ErrorKind::InvalidLeader)? as u32;
+    let csi = [byte[12] as char, byte[13] as char, byte[14] as char];
+    let flf = parse_to_usize(&byte[15..16]).context(ErrorKind::InvalidLeader)?;
+    let fpf = parse_to_usize(&byte[16..17]).context(ErrorKind::InvalidLeader)?;
+    let rsv = byte[17] as char;
+    let ftf = parse_to_usize(&byte[18..19]).context(ErrorKind::InvalidLeader)?;
+    Ok(Leader {
+        rl,
+        drl: None,
+        il,
+        li,
+        cei,
+        vn,
+        ai,
+        fcl,
+        ba,
+        csi,
+        flf,
+        fpf,
+        rsv,
+        ftf,
+    })
+}
+
+// TODO: Change this function to use exact_chunk when it is stable
+fn parse_directory(byte: &[u8], leader: &Leader) -> Result<Vec<DirectoryEntry>> {
+    let chunksize = leader.entry_size();
+    let dir_iter = byte.chunks(chunksize);
+    let mut directories: Vec<DirectoryEntry> = Vec::new();
+    for d in dir_iter {
+        if d.len() != chunksize {
+            return Err(ErrorKind::BadDirectoryData.into());
+        }
+        let id = parse_to_string(&d[..leader.ftf])?;
+        let length = parse_to_usize(&d[leader.ftf..leader.ftf + leader.flf])?;
+        let offset = parse_to_usize(&d[leader.ftf + leader.flf..])?;
+
+        directories.push(DirectoryEntry { id, length, offset });
+    }
+
+    Ok(directories)
+}
+
+impl FieldControls {
+    // The number of decimals of the implicit point fields, given by their auxiliary controls.
+    fn implied_decimals(&self) -> Option<i32> {
+        match self.dtc {
+            DataTypeCode::IP if self.aux > 0 => Some(i32::from(self.aux)),
+            _ => None,
+        }
+    }
+
+    // Integers of implicit point fields with decimals are read as the numbers they stand for.
+    fn scale(&self, data: Data) -> Data {
+        match (self.implied_decimals(), data) {
+            (Some(decimals), Data::Integer(i)) => {
+                Data::Float(i.map(|i| i as f64 / 10f64.powi(decimals)))
+            }
+            (_, data) => data,
+        }
+    }
+
+    // The inverse of scale, the integer to write for a number in an implicit point field.
+    fn unscale(&self, data: &Data) -> Data {
+        match (self.implied_decimals(), data) {
+            (Some(decimals), Data::Float(f)) => {
+                Data::Integer(f.map(|f| (f * 10f64.powi(decimals)).round() as i64))
+            }
+            (_, data) => data.clone(),
+        }
+    }
+
+    // The implied field controls of level 1 files, whose fields are single character strings.
+    fn elementary() -> FieldControls {
+        FieldControls {
+            dsc: DataStructureCode::SDI,
+            dtc: DataTypeCode::CS,
+            aux: 0,
+            prt: PrintableGraphics::from_field_controls(&[]),
+            tes: TruncEscSeq::LE0,
+        }
+    }
+}
+
+// Field controls are 9 bytes at level 3 and 6 at level 2, which has no truncated escape
+// sequence.
+fn parse_field_controls(byte: &[u8]) -> Result<FieldControls> {
+    if byte.len() < 6 {
+        return Err(ErrorKind::BadFieldControl.into());
+    }
+    let dsc = from_utf8(&byte[0..1])
+        .with_context(|&err| ErrorKind::UtfError(err))?
+        .parse::<DataStructureCode>()
+        .context(ErrorKind::BadFieldControl)?;
+    let dtc = from_utf8(&byte[1..2])
+        .with_context(|&err| ErrorKind::UtfError(err))?
+        .parse::<DataTypeCode>()
+        .context(ErrorKind::BadFieldControl)?;
+    let aux = parse_auxiliary_controls(&byte[2..4]).context(ErrorKind::BadFieldControl)?;
+    let tes = if byte.len() == 6 {
+        TruncEscSeq::LE0
+    } else {
+        from_utf8(&byte[6..])
+            .with_context(|&err| ErrorKind::UtfError(err))?
+            .parse::<TruncEscSeq>()
+            .context(ErrorKind::BadFieldControl)?
+    };
+
+    Ok(FieldControls {
+        dsc,
+        dtc,
+        aux,
+        prt: PrintableGraphics::from_field_controls(byte),
+        tes,
+    })
+}
+
+fn parse_auxiliary_controls(byte: &[u8]) -> Result<u8> {
+    // Blank auxiliary controls are treated as the default "00"
+    if byte.iter().all(|&b| b == b' ') {
+        Ok(0)
+    } else {
+        Ok(parse_to_usize(byte)? as u8)
+    }
+}
+
+// The labels of an array descriptor. Cartesian descriptors, e.g. "DEPTH!TEMP\\JAN!FEB", label
+// the rows of a table before the '\\' and its columns, the subfields, after it.
+#[derive(Debug, PartialEq)]
+struct ArrayDescriptor {
+    labels: Vec<String>,
+    repeat: Option<usize>, // Index of the label a '*' marks as the start of the repeating group
+    rows: Vec<String>,     // Row labels of a Cartesian descriptor
+}
+
+// Labels are separated by '!', the vector label delimiter of ISO 8211, which unlike the
+// terminators has no printable graphic declared for it in the field controls.
+fn parse_array_descriptors(byte: &[u8]) -> Result<ArrayDescriptor> {
+    if byte.is_empty() {
+        // The Record Identifier is an unnamed descriptor and therefore the byte
+        // array is empty. Since this is a key in a HashMap I use the name DRID
+        // (Data Record ID) to identify this field.
+        Ok(ArrayDescriptor {
+            labels: vec![String::from(DRID)],
+            repeat: None,
+            rows: Vec::new(),
+        })
+    } else {
+        let descriptor = parse_to_string(byte)?;
+        let (rows, columns) = match descriptor.split_once("\\\\") {
+            Some((rows, columns)) => (
+                rows.split('!').map(String::from).collect(),
+                columns.trim_start_matches('*'),
+            ),
+            None => (Vec::new(), descriptor.as_str()),
+        };
+        let mut repeat = None;
+        let labels = columns
+            .split('!')
+            .enumerate()
+            .map(|(i, label)| match label.strip_prefix('*') {
+                Some(label) => {
+                    repeat.get_or_insert(i);
+                    String::from(label)
+                }
+                None => String::from(label),
+            })
+            .collect::<Vec<String>>();
+        Ok(ArrayDescriptor {
+            labels,
+            repeat,
+            rows,
+        })
+    }
+}
+
+fn parse_format_controls(byte: &[u8]) -> Result<Vec<ParseData>> {
+    if byte.len() < 2 {
+        Err(ErrorKind::EmptyFormatControls.into())
+    } else {
+        // Remove surrounding parenthesies and create ParseDatas
+        let parsers = expand_format_controls(&parse_to_string(&byte[1..byte.len() - 1])?)?;
+        Ok(ParseData::fold_padding(parsers))
+    }
+}
+
+// Expands a comma separated list of formats, where a repeat factor may apply to a single format,
+// "2I(10)", or to a parenthesized group of them, "3(I(2),R(4))". Groups may nest.
+fn expand_format_controls(s: &str) -> Result<Vec<ParseData>> {
+    let mut parsers = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut items = Vec::new();
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(ErrorKind::UnParsableFormatControl(s.into()).into()),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 {
+        return Err(ErrorKind::UnParsableFormatControl(s.into()).into());
+    }
+    items.push(&s[start..]);
+    for item in items {
+        let digits = item.len() - item.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let group = &item[digits..];
+        if group.starts_with('(') && group.ends_with(')') {
+            let times = if digits == 0 {
+                1
+            } else {
+                parse_to_usize(&item.as_bytes()[..digits])?
+            };
+            let inner = expand_format_controls(&group[1..group.len() - 1])?;
+            for _ in 0..times {
+                parsers.extend(inner.iter().cloned());
+            }
+        } else {
+            let (times, pd) = ParseData::from_str(item)?;
+            parsers.extend(std::iter::repeat_n(pd, times));
+        }
+    }
+    Ok(parsers)
+}
+
+// The record identifier field has a tag of zeros ending with a one, "0001" in S-57 and
+// others using four character tags.
+fn is_record_identifier(tag: &str) -> bool {
+    tag.len() > 1 && tag.ends_with('1') && tag[..tag.len() - 1].chars().all(|c| c == '0')
+}
+
+// The file control field has a tag of only zeros, "0000" in S-57.
+fn is_file_control_field(dir: &DirectoryEntry) -> bool {
+    dir.id.chars().all(|c| c == '0')
+}
+
+fn field_slice<'a>(byte: &'a [u8], dir: &DirectoryEntry) -> Result<&'a [u8]> {
+    //  take -1 to remove the record separator from the slice
+    (dir.offset + dir.length)
+        .checked_sub(1)
+        .and_then(|end| byte.get(dir.offset..end))
+        .ok_or_else(|| ErrorKind::BadDirectoryData.into())
+}
+
+// Some writers put the directory entries out of order, or let a field run into the next one or
+// past the end of the field area. Entries that do are cut at their first field terminator, or
+// where the next field starts. Returns diagnostics for what had to be repaired.
+fn repair_directory(dirs: &mut [DirectoryEntry], field_area: &[u8]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if dirs.windows(2).any(|w| w[0].offset > w[1].offset) {
+        diagnostics.push(Diagnostic {
+            record: None,
+            tag: None,
+            kind: DiagnosticKind::UnsortedDirectory,
+        });
+    }
+    let mut offsets: Vec<usize> = dirs.iter().map(|dir| dir.offset).collect();
+    offsets.sort_unstable();
+    for dir in dirs.iter_mut() {
+        let next = offsets
+            .iter()
+            .find(|&&offset| offset > dir.offset)
+            .map_or(field_area.len(), |&offset| offset.min(field_area.len()));
+        if dir.offset >= next || dir.offset + dir.length <= next {
+            continue;
+        }
+        let length = field_area[dir.offset..next]
+            .iter()
+            .position(|&b| b == RECORD_SEPARATOR)
+            .map_or(next - dir.offset, |pos| pos + 1);
+        diagnostics.push(Diagnostic {
+            record: None,
+            tag: Some(dir.id.clone()),
+            kind: DiagnosticKind::OverlappingField {
+                declared: dir.length,
+                actual: length,
+            },
+        });
+        dir.length = length;
+    }
+    diagnostics
+}
+
+fn parse_file_control_field(byte: &[u8], ftf: usize, fcl: usize) -> Result<FileControlField> {
+    if byte.len() < fcl {
+        return Err(ErrorKind::InvalidHeader.into());
+    }
+    let (fic_bytes, rest) = byte.split_at(fcl);
+    let fic = match fcl {
+        0 => FieldControls::elementary(),
+        _ => parse_field_controls(fic_bytes)?,
+    };
+    let mut parts = rest.splitn(2, |&b| b == UNIT_SEPARATOR);
+    let title = parse_to_string(parts.next().unwrap_or(&[]))?;
+    let pair_bytes = parts.next().unwrap_or(&[]);
+    if ftf == 0 || pair_bytes.len() % (2 * ftf) != 0 {
+        return Err(ErrorKind::BadFileControlField.into());
+    }
+    let pairs = pair_bytes
+        .chunks(2 * ftf)
+        .map(|pair| {
+            Ok((
+                parse_to_string(&pair[..ftf])?,
+                parse_to_string(&pair[ftf..])?,
+            ))
+        })
+        .collect::<Result<Vec<(String, String)>>>()?;
+    Ok(FileControlField { fic, title, pairs })
+}
+
+fn parse_ddfs(
+    byte: &[u8],
+    dirs: &[DirectoryEntry],
+    fcl: usize,
+) -> Result<HashMap<String, DDFEntry>> {
+    dirs.iter()
+        .filter(|dir| !is_file_control_field(dir))
+        .map(|dir| {
+            let ddf_entry =
+                parse_ddf(field_slice(byte, dir)?, fcl).context(ErrorKind::InvalidDDFS)?;
+            Ok((dir.id.clone(), ddf_entry))
+        })
+        .collect()
+}
+
+// Catches a leader declaring the wrong field control length, which would otherwise shift the
+// truncated escape sequence into or out of the field name: the last three bytes of 9 byte field
+// controls are one, and a name following 6 byte ones doesn't start with one.
+fn check_field_control_length(fic_bytes: &[u8], rest: &[u8]) -> Result<()> {
+    let is_tes = |bytes: &[u8]| from_utf8(bytes).is_ok_and(|s| s.parse::<TruncEscSeq>().is_ok());
+    let mismatch = match fic_bytes.len() {
+        9 => !is_tes(&fic_bytes[6..]),
+        6 => rest.get(..3).is_some_and(is_tes),
+        _ => false,
+    };
+    if mismatch {
+        Err(ErrorKind::BadFieldControlLength(fic_bytes.len()).into())
+    } else {
+        Ok(())
+    }
+}
+
+// A data descriptive field of fcl bytes of field controls. Level 1 fields have only a name, and
+// level 2 fields may leave out the format controls, making every subfield a variable length
+// character string, or also the array descriptor, making the field a single character string.
+fn parse_ddf(byte: &[u8], fcl: usize) -> Result<DDFEntry> {
+    if byte.len() < fcl {
+        return Err(ErrorKind::InvalidHeader.into());
+    }
+    let (fic_bytes, rest) = byte.split_at(fcl);
+    check_field_control_length(fic_bytes, rest)?;
+    // Some producers write the printable graphic the field controls declare for the unit
+    // terminator, '&' in S-57, instead of the unit terminator itself. The field terminator is
+    // always the last byte and has already been cut off.
+    let unit_terminator = if rest.contains(&UNIT_SEPARATOR) || fcl == 0 {
+        UNIT_SEPARATOR
+    } else {
+        PrintableGraphics::from_field_controls(fic_bytes).unit_terminator as u8
+    };
+    let parts = rest
+        .split(|&b| b == unit_terminator)
+        .collect::<Vec<&[u8]>>();
+    let name_bytes = parts[0];
+    let name = parse_to_string(name_bytes).context(ErrorKind::CouldNotParseName)?;
+    let fic = match fcl {
+        0 => FieldControls::elementary(),
+        _ => parse_field_controls(fic_bytes).context(ErrorKind::InvalidDDF(name.clone()))?,
+    };
+    let array_desc = match parts.get(1) {
+        Some(array_desc) => parse_array_descriptors(array_desc),
+        None if fcl == 9 => Err(ErrorKind::InvalidDDF(name.clone()).into()),
+        None => parse_array_descriptors(&[]),
+    }
+    .context(ErrorKind::InvalidDDF(name.clone()))?;
+    let data_parser = match parts.get(2).filter(|foc| !foc.is_empty() || fcl == 9) {
+        Some(foc) => parse_format_controls(foc).context(ErrorKind::InvalidDDF(name.clone()))?,
+        None if fcl == 9 => return Err(ErrorKind::InvalidDDF(name.clone()).into()),
+        None if array_desc.labels == [DRID] => vec![ParseData::Elementary],
+        None => vec![ParseData::Variable(ParseType::String); array_desc.labels.len()],
+    };
+    if array_desc.labels.len() == data_parser.len() {
+        let foc = array_desc.labels.into_iter().zip(data_parser).collect();
+        Ok(DDFEntry {
+            fic,
+            name,
+            foc,
+            repeat: array_desc.repeat,
+            rows: array_desc.rows,
+        })
+    } else {
+        Err(ErrorKind::InvalidDDF(name.clone()).into())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DDR {
+    len: usize, // Length of the DDR in bytes
+    leader: LeaderInfo,
+    diagnostics: Vec<Diagnostic>, // Found in the DDR, handed over to the reader
+    code_extension: bool,         // Whether character data may contain escape sequences
+    charset: CharacterSet,        // Declared by the leader or overridden by a ParserConfig
+    dirs: Vec<DirectoryEntry>,
+    file_control_field: Option<FileControlField>,
+    data_descriptive_fields: HashMap<String, DDFEntry>,
+}
+
+impl DDR {
+    // How the character subfields of a field with the field controls fic are encoded. A character
+    // set declared or configured wins over code extension. Otherwise lexical level 2 means UCS-2
+    // throughout the field, also with code extension as in every S-57 file.
+    fn encoding(&self, fic: &FieldControls) -> Encoding {
+        match self.charset {
+            CharacterSet::Latin1 => Encoding::Latin1,
+            CharacterSet::Ucs2 => Encoding::Ucs2,
+            CharacterSet::Utf8 => Encoding::Utf8,
+            CharacterSet::Default if fic.tes == TruncEscSeq::LE2 => Encoding::Ucs2,
+            CharacterSet::Default if self.code_extension => Encoding::Extended,
+            CharacterSet::Default => Encoding::Utf8,
+        }
+    }
+
+    // The bytes of the field of the directory entry dir_entry, without its field terminator.
+    fn raw_field<'a>(&self, dir_entry: &DirectoryEntry, field_data: &'a [u8]) -> &'a [u8] {
+        let end = (dir_entry.offset + dir_entry.length).min(field_data.len());
+        let bytes = field_data.get(dir_entry.offset..end).unwrap_or_default();
+        let ucs2 = self
+            .data_descriptive_fields
+            .get(&dir_entry.id)
+            .is_some_and(|ddf| self.encoding(&ddf.fic) == Encoding::Ucs2);
+        match bytes {
+            [rest @ .., RECORD_SEPARATOR, 0] if ucs2 => rest,
+            [rest @ .., RECORD_SEPARATOR] => rest,
+            _ => bytes,
+        }
+    }
+
+    // Decodes the field of the directory entry dir_entry from the field area field_data, which
+    // starts at byte base of the record.
+    fn decode_field(
+        &self,
+        dir_entry: &DirectoryEntry,
+        field_data: &[u8],
+        base: usize,
+    ) -> Result<DecodedField> {
+        let ddf_entry = self
+            .data_descriptive_fields
+            .get(&dir_entry.id)
+            .ok_or(ErrorKind::InvalidDR)?;
+        let mut spans = Vec::with_capacity(ddf_entry.foc.len());
+        let encoding = self.encoding(&ddf_entry.fic);
+        let group = ddf_entry.repeating_group();
+        // Where the field terminator of the field starts
+        let field_end = (dir_entry.offset + dir_entry.length).saturating_sub(match encoding {
+            Encoding::Ucs2 => 2,
+            _ => 1,
+        });
+        let mut cur = std::io::Cursor::new(field_data);
+        cur.set_position(dir_entry.offset as u64);
+        // The bytes of the field from at on, for error messages
+        let rest = |at: usize| escape(field_data.get(at..field_end).unwrap_or_default());
+        // Parses subfields, the first of which is subfield first of the field
+        let mut parse = |first: usize,
+                         subfields: &[(String, ParseData)],
+                         spans: Option<&mut Vec<_>>| {
+            let mut row = Vec::with_capacity(subfields.len());
+            let mut row_spans = Vec::with_capacity(subfields.len());
+            for (i, (name, parser)) in subfields.iter().enumerate() {
+                // Subfields left out at the end of the field are missing rather than empty
+                if cur.position() as usize >= field_end {
+                    let here = base + cur.position() as usize;
+                    row_spans.push((name.clone(), here..here));
+                    row.push(Data::Missing);
+                    continue;
+                }
+                let at = cur.position() as usize;
+                let start = base + at + parser.padding();
+                let data = parser.parse(&mut cur, encoding).with_context(|_| {
+                    ErrorKind::BadSubfield(dir_entry.id.clone(), first + i, name.clone(), rest(at))
+                })?;
+                let mut end = base + cur.position() as usize;
+                // Leave the unit terminator of variable length subfields out of the span
+                if parser.is_delimited() {
+                    let terminator = match (encoding, &data) {
+                        (Encoding::Ucs2, Data::String(_)) => 2,
+                        _ => 1,
+                    };
+                    end = end.saturating_sub(terminator).max(start);
+                }
+                row_spans.push((name.clone(), start..end));
+                row.push(ddf_entry.fic.scale(data));
+            }
+            if let Some(spans) = spans {
+                spans.extend(row_spans);
+            }
+            Ok::<_, Error>((row, cur.position() as usize))
+        };
+        let (once, group_foc) = ddf_entry.foc.split_at(group.unwrap_or(ddf_entry.foc.len()));
+        let (mut values, mut position) = parse(0, once, Some(&mut spans))?;
+        // Bytes left after the subfields of a field without a repeating group are subfields the
+        // DDR does not describe
+        if group.is_none() && position < field_end {
+            let extra = field_data[position..field_end]
+                .split(|&b| b == UNIT_SEPARATOR)
+                .enumerate()
+                .filter(|(i, unit)| *i == 0 || !unit.is_empty())
+                .count();
+            return Err(ErrorKind::SubfieldCountMismatch(
+                dir_entry.id.clone(),
+                once.len(),
+                once.len() + extra,
+                rest(dir_entry.offset),
+            )
+            .into());
+        }
+        let mut rows = Rows::new();
+        // A repeating group repeats until the field terminator, its first repetition being
+        // part of the field as well
+        while group.is_some() && position < field_end {
+            let spans = if rows.is_empty() {
+                Some(&mut spans)
+            } else {
+                None
+            };
+            let (row, end) = parse(once.len(), group_foc, spans)?;
+            if rows.is_empty() {
+                values.extend(row.iter().cloned());
+            }
+            rows.push(row);
+            position = end;
+        }
+        let field = ddf_entry
+            .foc
+            .iter()
+            .map(|(name, _)| name.clone())
+            .zip(values)
+            .collect();
+        Ok(DecodedField {
+            field,
+            rows: group.map(|_| rows),
+            spans,
+        })
+    }
+}
+
+// A field decoded by DDR::decode_field.
+struct DecodedField {
+    field: Field,
+    rows: Option<Rows>, // The repetitions of the repeating group, if the field has one
+    spans: Vec<(String, Range<usize>)>,
+}
+
+/// The character set of the character data in the field area, as declared by the Extended
+/// Character Set Indicator of the DDR leader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterSet {
+    Default, // ASCII, or UCS-2 for fields of lexical level 2, as S-57 declares with " ! "
+    Latin1,  // ISO 8859-1
+    Ucs2,    // ISO 10646 UCS-2 little endian
+    Utf8,    // ISO 10646 UTF-8
+}
+
+impl CharacterSet {
+    // The indicator holds the escape sequence, without ESC, designating the character set.
+    // Unknown ones, and the blank or " ! " of S-57, keep the default.
+    fn from_csi(csi: &[char; 3]) -> CharacterSet {
+        let csi: String = csi.iter().collect();
+        match csi.trim() {
+            "-A" => CharacterSet::Latin1,
+            "%/A" | "%/@" => CharacterSet::Ucs2,
+            "%G" | "%/G" => CharacterSet::Utf8,
+            _ => CharacterSet::Default,
+        }
+    }
+}
+
+/// Options for reading a file, see [`Iso8211Reader::with_config`].
+#[derive(Debug, Clone, Default)]
+pub struct ParserConfig {
+    /// Decode the character data in this character set, whatever the leader declares.
+    pub charset: Option<CharacterSet>,
+    /// Decode the fields that are there of a Data Record cut short by the end of the file, and
+    /// report it as a diagnostic, instead of failing with `ErrorKind::TruncatedRecord`.
+    pub partial_records: bool,
+    /// Fail with `ErrorKind::MemoryBudgetExceeded` instead of reading a Data Record that would
+    /// take the bytes read in one pass over this, see [`Iso8211Reader::with_memory_budget`].
+    pub memory_budget: Option<u64>,
+}
+
+impl ParserConfig {
+    fn apply<R: Read>(&self, reader: &mut Iso8211Reader<R>) {
+        if let Some(charset) = self.charset {
+            reader.ddr.charset = charset;
+        }
+        reader.partial_records = self.partial_records;
+        reader.budget = self.memory_budget;
+    }
+}
+
+/// The field definitions of a file, as declared by its Data Descriptive Record.
+#[derive(Debug)]
+pub struct Schema(DDR);
+
+/// The description of a single field in a [`Schema`].
+#[derive(Debug, PartialEq)]
+pub struct FieldDescription<'a> {
+    pub tag: &'a str,
+    pub name: &'a str,
+    pub labels: Vec<&'a str>,
+    pub formats: Vec<String>, // Format control of each subfield, e.g. "A(2)"
+    pub repeats_from: Option<usize>, // Label marked with '*', from which the subfields repeat
+    pub row_labels: Vec<&'a str>, // Rows of a Cartesian array descriptor, empty for others
+    pub auxiliary_controls: u8, // The auxiliary controls of the field controls, usually 0
+    pub printable_graphics: PrintableGraphics,
+}
+
+impl FieldDescription<'_> {
+    /// The format controls in ISO 8211 notation, with runs of equal formats collapsed, e.g.
+    /// "(A(2),2I(10),2R)".
+    pub fn format_controls(&self) -> String {
+        let mut runs: Vec<(usize, &str)> = Vec::new();
+        for format in self.formats.iter() {
+            match runs.last_mut() {
+                Some((n, last)) if last == format => *n += 1,
+                _ => runs.push((1, format)),
+            }
+        }
+        let runs: Vec<String> = runs
+            .into_iter()
+            .map(|(n, format)| match n {
+                1 => format.to_string(),
+                n => format!("{}{}", n, format),
+            })
+            .collect();
+        format!("({})", runs.join(","))
+    }
+
+    /// The array descriptor in ISO 8211 notation, the labels separated by '!' and any row labels
+    /// before them and '\\'. The record identifier field has an empty one.
+    pub fn array_descriptor(&self) -> String {
+        if self.labels == [DRID] {
+            String::new()
+        } else {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| match self.repeats_from {
+                    Some(from) if from == i => format!("*{}", label),
+                    _ => label.to_string(),
+                })
+                .collect();
+            if self.row_labels.is_empty() {
+                labels.join("!")
+            } else {
+                format!("{}\\\\{}", self.row_labels.join("!"), labels.join("!"))
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// The described fields in the order they appear in the DDR directory.
+    pub fn fields(&self) -> impl Iterator<Item = FieldDescription<'_>> {
+        self.0
+            .dirs
+            .iter()
+            .filter_map(move |dir| self.field(&dir.id))
+    }
+
+    pub fn field(&self, tag: &str) -> Option<FieldDescription<'_>> {
+        self.0
+            .data_descriptive_fields
+            .get_key_value(tag)
+            .map(|(tag, ddf)| FieldDescription {
+                tag,
+                name: &ddf.name,
+                labels: ddf.foc.iter().map(|(label, _)| label.as_str()).collect(),
+                formats: ddf.foc.iter().map(|(_, pd)| pd.to_string()).collect(),
+                repeats_from: ddf.repeat,
+                row_labels: ddf.rows.iter().map(String::as_str).collect(),
+                auxiliary_controls: ddf.fic.aux,
+                printable_graphics: ddf.fic.prt,
+            })
+    }
+
+    /// The leader of the DDR.
+    pub fn leader(&self) -> &LeaderInfo {
+        &self.0.leader
+    }
+
+    /// The title of the file from the file control field.
+    pub fn title(&self) -> Option<&str> {
+        self.0.file_control_field.as_ref().map(|f| f.title.as_str())
+    }
+
+    /// The (parent, child) field tag pairs of the file control field, describing which fields
+    /// nest under which.
+    pub fn tag_pairs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .file_control_field
+            .iter()
+            .flat_map(|f| f.pairs.iter())
+            .map(|(parent, child)| (parent.as_str(), child.as_str()))
+    }
+
+    /// The field hierarchy declared by the file control field.
+    pub fn field_tree(&self) -> FieldTree<'_> {
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut parents = HashMap::new();
+        for (parent, child) in self.tag_pairs() {
+            children.entry(parent).or_default().push(child);
+            parents.entry(child).or_insert(parent);
+        }
+        let roots = self
+            .0
+            .dirs
+            .iter()
+            .map(|dir| dir.id.as_str())
+            .filter(|tag| {
+                !parents.contains_key(tag) && self.0.data_descriptive_fields.contains_key(*tag)
+            })
+            .collect();
+        FieldTree {
+            roots,
+            children,
+            parents,
+        }
+    }
+
+    // The bytes of record as a Data Record of this schema, from the leader through the last
+    // field terminator. Subfields missing from the record are written empty.
+    pub(crate) fn encode_record(&self, record: &Record) -> Result<Vec<u8>> {
+        let mut entries = Vec::with_capacity(record.tags.len());
+        let mut field_area = Vec::new();
+        for tag in record.tags.iter() {
+            let ddf_entry = self
+                .0
+                .data_descriptive_fields
+                .get(tag)
+                .ok_or_else(|| ErrorKind::InvalidDDF(tag.clone()))?;
+            let encoding = self.0.encoding(&ddf_entry.fic);
+            let group = ddf_entry.repeating_group();
+            let (once, group_foc) = ddf_entry.foc.split_at(group.unwrap_or(ddf_entry.foc.len()));
+            let repeated = record.repeated.get(tag).into_iter().flatten();
+            let occurrences = std::iter::once((record.fields.get(tag), record.rows.get(tag)))
+                .chain(repeated.map(|(field, rows)| (Some(field), rows.as_ref())));
+            for (field, rows) in occurrences {
+                let start = field_area.len();
+                let mut format = |label: &str, parser: &ParseData, data: Option<&Data>| {
+                    let data = data.map(|data| ddf_entry.fic.unscale(data));
+                    field_area.extend(parser.format(label, data.as_ref(), encoding)?);
+                    Ok::<_, Error>(())
+                };
+                for (label, parser) in once.iter() {
+                    format(label, parser, field.and_then(|f| f.get(label)))?;
+                }
+                match rows {
+                    Some(rows) => {
+                        for row in rows.iter() {
+                            for ((label, parser), data) in group_foc.iter().zip(row.iter()) {
+                                format(label, parser, Some(data))?;
+                            }
+                        }
+                    }
+                    None => {
+                        for (label, parser) in group_foc.iter() {
+                            format(label, parser, field.and_then(|f| f.get(label)))?;
+                        }
+                    }
+                }
+                field_area.push(RECORD_SEPARATOR);
+                if encoding == Encoding::Ucs2 {
+                    field_area.push(0);
+                }
+                entries.push((tag, field_area.len() - start, start));
+            }
+        }
+        let digits = |n: usize| n.to_string().len();
+        let flf = digits(entries.iter().map(|e| e.1).max().unwrap_or(0));
+        let fpf = digits(field_area.len());
+        let ftf = record.tags.iter().map(String::len).max().unwrap_or(4);
+        if flf > 9 || fpf > 9 || ftf > 9 {
+            return Err(ErrorKind::BadDirectoryData.into());
+        }
+        let mut directory = Vec::new();
+        for (tag, length, offset) in entries {
+            directory
+                .extend(format!("{:>3$}{:04$}{:05$}", tag, length, offset, ftf, flf, fpf).bytes());
+        }
+        directory.push(RECORD_SEPARATOR);
+        let base = 24 + directory.len();
+        let length = base + field_area.len();
+        // Records too long to state their length have it written as zeroes
+        let length = if length > 99999 { 0 } else { length };
+        let mut bytes =
+            format!("{:05} D     {:05}   {}{}0{}", length, base, flf, fpf, ftf).into_bytes();
+        bytes.extend(directory);
+        bytes.extend(field_area);
+        Ok(bytes)
+    }
+}
+
+/// Which fields nest under which, built from the (parent, child) tag pairs of the file control
+/// field. Files without a file control field have every field as a root.
+#[derive(Debug, PartialEq)]
+pub struct FieldTree<'a> {
+    roots: Vec<&'a str>,
+    children: HashMap<&'a str, Vec<&'a str>>,
+    parents: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> FieldTree<'a> {
+    /// The fields that are not the child of any other, in DDR directory order.
+    pub fn roots(&self) -> &[&'a str] {
+        &self.roots
+    }
+
+    /// The fields directly under tag, in the order of the file control field.
+    pub fn children(&self, tag: &str) -> &[&'a str] {
+        self.children.get(tag).map_or(&[], Vec::as_slice)
+    }
+
+    /// The field that tag nests under, None for the roots.
+    pub fn parent(&self, tag: &str) -> Option<&'a str> {
+        self.parents.get(tag).copied()
+    }
+}
+
+/// Reads the Data Descriptive Record of a file up front, then iterates its Data Records.
+#[derive(Debug)]
+pub struct Iso8211Reader<R: Read> {
+    ddr: DDR,            // Data Descriptive Record
+    rdr: R,              // reader to ask for Data Records
+    read: u64,           // bytes of Data Records read so far
+    budget: Option<u64>, // maximum bytes of Data Records to read in one pass
+    records: usize,      // number of Data Records read so far
+    diagnostics: Vec<Diagnostic>,
+    on_error: Option<ErrorHandler>,
+    // Leader and directory that the DRs after one with leader identifier 'R' leave out, and
+    // the length of their field areas
+    shared: Option<(Vec<u8>, usize)>,
+    partial_records: bool,      // decode what there is of a truncated record
+    index: Option<RecordIndex>, // Where the Data Records are, once found by scan_records
+    // Stream position of the first Data Record. Known from the start when the reader is created
+    // from a seekable one, otherwise found on the first seek.
+    start: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordIndex {
+    offsets: Vec<u64>, // Byte offset of each Data Record, counting from the first
+    // The record with leader identifier 'R' whose leader and directory the ones after reuse
+    reused: Option<usize>,
+}
+
+/// What to do about a Data Record that could not be decoded, returned from the handler set with
+/// [`Iso8211Reader::on_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    Continue, // Skip the record and go on with the next
+    Abort,    // Yield the error
+}
+
+type ErrorCallback = dyn FnMut(&Error, &[u8]) -> ErrorAction;
+
+struct ErrorHandler(Box<ErrorCallback>);
+
+impl std::fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Record {
+    tags: Vec<String>, // Tags of the fields in directory order
+    fields: HashMap<String, Field>,
+    // Further fields with the tag of one before them, with the rows of their repeating groups
+    repeated: HashMap<String, Vec<(Field, Option<Rows>)>>,
+    labels: HashMap<String, Vec<String>>, // Labels of each field in the order of the DDR
+    rows: HashMap<String, Rows>,          // All subfield sets of multi-dimensional fields
+    spans: HashMap<String, Vec<(String, Range<usize>)>>, // Byte span of each subfield, in order
+    leader: Option<LeaderInfo>,           // Of the Data Record the record was read from
+    raw: HashMap<String, Vec<Vec<u8>>>,   // Each occurrence of a field as read, unterminated
+}
+
+pub type Field = HashMap<String, Data>;
+
+// Bytes as text, with the ones that are not printable ASCII escaped.
+fn escape(bytes: &[u8]) -> String {
+    bytes.escape_ascii().to_string()
+}
+
+fn wrong_type(label: &str, expected: &'static str, data: &Data) -> Error {
+    ErrorKind::WrongSubfieldType(label.to_string(), expected, data.describe()).into()
+}
+
+/// A field of a [`Record`] together with its tag.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldView<'a> {
+    tag: &'a str,
+    field: &'a Field,
+    labels: Option<&'a [String]>, // In the order of the DDR, for fields read from a file
+    raw: Option<&'a [u8]>,
+    rows: Option<&'a Rows>,
+}
+
+impl<'a> FieldView<'a> {
+    pub fn tag(&self) -> &'a str {
+        self.tag
+    }
+
+    pub fn as_field(&self) -> &'a Field {
+        self.field
+    }
+
+    /// All rows of the repeating group of this occurrence of the field, see [`Record::rows`].
+    pub fn rows(&self) -> Option<&'a Rows> {
+        self.rows
+    }
+
+    /// The field as read from the file, without its field terminator. `None` if the record was
+    /// not read from a file or the field has been changed since.
+    pub fn raw_bytes(&self) -> Option<&'a [u8]> {
+        self.raw
+    }
+
+    /// The value of the subfield `label`, as named by the array descriptor of the field.
+    pub fn get(&self, label: &str) -> Option<&'a Data> {
+        self.field.get(label)
+    }
+
+    /// The subfield `label` as a string, failing if it is missing or not a character subfield.
+    pub fn get_str(&self, label: &str) -> Result<&'a str> {
+        match self.subfield(label)? {
+            Data::String(s) => Ok(s),
+            data => Err(wrong_type(label, "a string", data)),
+        }
+    }
+
+    /// The subfield `label` as an integer, `None` if it is empty or left out of the field. Fails
+    /// if it is missing or not an integer subfield.
+    pub fn get_i64(&self, label: &str) -> Result<Option<i64>> {
+        match self.subfield(label)? {
+            Data::Integer(i) => Ok(*i),
+            Data::Missing => Ok(None),
+            data => Err(wrong_type(label, "an integer", data)),
+        }
+    }
+
+    /// The subfield `label` as a float, `None` if it is empty or left out of the field. Integer
+    /// subfields are converted. Fails if it is missing or not a numeric subfield.
+    pub fn get_f64(&self, label: &str) -> Result<Option<f64>> {
+        match self.subfield(label)? {
+            Data::Float(f) => Ok(*f),
+            Data::Integer(i) => Ok(i.map(|i| i as f64)),
+            Data::Missing => Ok(None),
+            data => Err(wrong_type(label, "a float", data)),
+        }
+    }
+
+    fn subfield(&self, label: &str) -> Result<&'a Data> {
+        self.get(label).ok_or_else(|| {
+            ErrorKind::MissingSubfield(self.tag.to_string(), label.to_string()).into()
+        })
+    }
+
+    /// The labels of the subfields, in the order of [`FieldView::subfields`].
+    pub fn labels(&self) -> Vec<&'a str> {
+        self.subfields()
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect()
+    }
+
+    /// The (label, value) pairs of the field. For a field read from a file they are in the
+    /// order of the DDR, subfields added with [`Record::set`] go last. For other fields the
+    /// order is by label.
+    pub fn subfields(&self) -> Vec<(&'a str, &'a Data)> {
+        let field = self.field;
+        match self.labels {
+            Some(labels) => labels
+                .iter()
+                .filter_map(|label| Some((label.as_str(), field.get(label)?)))
+                .collect(),
+            None => {
+                let mut subfields: Vec<_> = field.iter().map(|(l, d)| (l.as_str(), d)).collect();
+                subfields.sort_by_key(|(label, _)| *label);
+                subfields
+            }
+        }
+    }
+}
+
+/// The subfields of a repeating group, one row per repetition, each in the order of the group's
+/// labels. The group is the subfields from the one marked with '*' in the array descriptor, or
+/// the whole subfield set of a multi-dimensional field.
+pub type Rows = Vec<Vec<Data>>;
+
+impl Record {
+    /// An empty record, to be filled in with [`Record::insert_field`].
+    pub fn new() -> Record {
+        Record::default()
+    }
+
+    pub fn id(&self) -> Option<i64> {
+        self.tags
+            .iter()
+            .find(|tag| is_record_identifier(tag))
+            .and_then(|tag| self.fields.get(tag))
+            .and_then(|m| m.get(DRID))
+            .and_then(|v| if let Data::Integer(i) = v { *i } else { None })
+    }
+
+    /// Sets the record identifier of the record, the "0001" field unless the record already has
+    /// one with a tag of another size.
+    pub fn set_id(&mut self, id: i64) {
+        let tag = match self.tags.iter().find(|tag| is_record_identifier(tag)) {
+            Some(tag) => tag.clone(),
+            None => TOPLVL.to_string(),
+        };
+        self.set(&tag, DRID, Data::Integer(Some(id)));
+    }
+
+    pub fn get(&self, arr_desc: &str) -> Option<&Field> {
+        self.fields.get(arr_desc)
+    }
+
+    /// The leader of the Data Record the record was read from, `None` for records built with
+    /// [`Record::new`].
+    pub fn leader(&self) -> Option<&LeaderInfo> {
+        self.leader.as_ref()
+    }
+
+    /// The tags of the fields in the record, in the order of its directory.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// The fields of the record in the order of its directory, except that a field that occurs
+    /// more than once has all its occurrences together.
+    pub fn fields(&self) -> impl Iterator<Item = FieldView<'_>> {
+        self.tags
+            .iter()
+            .flat_map(move |tag| self.fields_with_tag(tag))
+    }
+
+    /// The first field with the tag `tag`.
+    pub fn field(&self, tag: &str) -> Option<FieldView<'_>> {
+        self.fields_with_tag(tag).next()
+    }
+
+    /// Every field with the tag `tag`, in the order of the directory. Most tags occur at most
+    /// once in a record.
+    pub fn fields_with_tag<'a>(&'a self, tag: &str) -> impl Iterator<Item = FieldView<'a>> {
+        let raw = self.raw.get(tag);
+        type Occurrence<'a> = (&'a String, &'a Field, Option<&'a Rows>);
+        let view = move |(i, (tag, field, rows)): (usize, Occurrence<'a>)| FieldView {
+            tag,
+            field,
+            labels: self.labels.get(tag).map(Vec::as_slice),
+            raw: raw.and_then(|raw| raw.get(i)).map(Vec::as_slice),
+            rows,
+        };
+        let first = self
+            .fields
+            .get_key_value(tag)
+            .map(|(tag, field)| (tag, field, self.rows.get(tag)));
+        let repeated = first
+            .and_then(|(tag, _, _)| self.repeated.get(tag).map(|fields| (tag, fields)))
+            .into_iter()
+            .flat_map(|(tag, fields)| {
+                fields
+                    .iter()
+                    .map(move |(field, rows)| (tag, field, rows.as_ref()))
+            });
+        first.into_iter().chain(repeated).enumerate().map(view)
+    }
+
+    /// Occurrence `n` of the field `tag`, counting from 0 in the order of the directory. See
+    /// [`Record::fields_with_tag`] for all of them.
+    pub fn field_occurrence(&self, tag: &str, n: usize) -> Option<FieldView<'_>> {
+        self.fields_with_tag(tag).nth(n)
+    }
+
+    /// The subfield `label` of the first field `tag` as a string, see [`FieldView::get_str`].
+    pub fn get_str(&self, tag: &str, label: &str) -> Result<&str> {
+        self.required_field(tag)?.get_str(label)
+    }
+
+    /// The subfield `label` of the first field `tag` as an integer, see
+    /// [`FieldView::get_i64`].
+    pub fn get_i64(&self, tag: &str, label: &str) -> Result<Option<i64>> {
+        self.required_field(tag)?.get_i64(label)
+    }
+
+    /// The subfield `label` of the first field `tag` as a float, see [`FieldView::get_f64`].
+    pub fn get_f64(&self, tag: &str, label: &str) -> Result<Option<f64>> {
+        self.required_field(tag)?.get_f64(label)
+    }
+
+    fn required_field(&self, tag: &str) -> Result<FieldView<'_>> {
+        self.field(tag)
+            .ok_or_else(|| ErrorKind::MissingField(tag.to_string()).into())
+    }
+
+    /// The (label, value) pairs of the first field with the tag `tag`, see
+    /// [`FieldView::subfields`].
+    pub fn subfields<'a>(&'a self, tag: &str) -> Vec<(&'a str, &'a Data)> {
+        self.field(tag)
+            .map_or_else(Vec::new, |field| field.subfields())
+    }
+
+    /// Every subfield of the record as (tag, label, value), field by field in the order of
+    /// [`Record::fields`]. Unlike [`FieldView::subfields`] a repeating group gives all its rows.
+    pub fn all_subfields(&self) -> impl Iterator<Item = (&str, &str, &Data)> {
+        self.fields().flat_map(|field| {
+            let subfields = match (field.rows, field.labels) {
+                (Some(rows), Some(labels)) => {
+                    let width = rows.first().map_or(0, Vec::len);
+                    let (once, group) = labels.split_at(labels.len().saturating_sub(width));
+                    once.iter()
+                        .filter_map(|label| Some((label.as_str(), field.get(label)?)))
+                        .chain(
+                            rows.iter()
+                                .flat_map(|row| group.iter().map(String::as_str).zip(row.iter())),
+                        )
+                        .collect()
+                }
+                _ => field.subfields(),
+            };
+            let tag = field.tag;
+            subfields
+                .into_iter()
+                .map(move |(label, data)| (tag, label, data))
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_fields(fields: HashMap<String, Field>) -> Record {
+        let mut tags: Vec<String> = fields.keys().cloned().collect();
+        tags.sort();
+        Record {
+            tags,
+            fields,
+            repeated: HashMap::new(),
+            labels: HashMap::new(),
+            rows: HashMap::new(),
+            spans: HashMap::new(),
+            leader: None,
+            raw: HashMap::new(),
+        }
+    }
+
+    /// Sets the subfield `label` of the field `tag`, returning the previous value. The byte
+    /// spans of the field no longer apply and are dropped.
+    pub fn set(&mut self, tag: &str, label: &str, data: Data) -> Option<Data> {
+        self.spans.remove(tag);
+        self.raw.remove(tag);
+        self.rows.remove(tag);
+        if !self.fields.contains_key(tag) {
+            self.tags.push(tag.to_string());
+        }
+        if let Some(labels) = self.labels.get_mut(tag) {
+            if !labels.iter().any(|l| l == label) {
+                labels.push(label.to_string());
+            }
+        }
+        self.fields
+            .entry(tag.to_string())
+            .or_default()
+            .insert(label.to_string(), data)
+    }
+
+    /// Adds the field `tag`, replacing any field with the same tag. A new field goes last.
+    pub fn insert_field(&mut self, tag: &str, field: Field) -> Option<Field> {
+        self.spans.remove(tag);
+        self.raw.remove(tag);
+        self.rows.remove(tag);
+        self.repeated.remove(tag);
+        self.labels.remove(tag);
+        if !self.fields.contains_key(tag) {
+            self.tags.push(tag.to_string());
+        }
+        self.fields.insert(tag.to_string(), field)
+    }
+
+    pub fn remove_field(&mut self, tag: &str) -> Option<Field> {
+        self.spans.remove(tag);
+        self.raw.remove(tag);
+        self.rows.remove(tag);
+        self.repeated.remove(tag);
+        self.labels.remove(tag);
+        self.tags.retain(|t| t != tag);
+        self.fields.remove(tag)
+    }
+
+    /// All rows of the repeating group of the field `tag`, None for fields without one. The
+    /// first row is also part of what [`Record::get`] returns for the field.
+    pub fn rows(&self, tag: &str) -> Option<&Rows> {
+        self.rows.get(tag)
+    }
+
+    /// Adds the field `tag` made up of only a repeating group with the given rows, each in the
+    /// order of `labels`, replacing any field with the same tag.
+    pub fn insert_rows(&mut self, tag: &str, labels: &[&str], rows: Rows) -> Option<Field> {
+        let field = match rows.first() {
+            Some(row) => labels
+                .iter()
+                .map(|label| label.to_string())
+                .zip(row.iter().cloned())
+                .collect(),
+            None => Field::new(),
+        };
+        let old = self.insert_field(tag, field);
+        self.rows.insert(tag.to_string(), rows);
+        self.labels.insert(
+            tag.to_string(),
+            labels.iter().map(|label| label.to_string()).collect(),
+        );
+        old
+    }
+
+    /// The subfields of the field `tag` in order, each with its byte span within the record.
+    /// The spans leave out unit terminators.
+    pub fn subfield_spans<'a>(
+        &'a self,
+        tag: &str,
+    ) -> impl Iterator<Item = (&'a str, &'a Data, Range<usize>)> + 'a {
+        let field = self.fields.get(tag);
+        self.spans
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .filter_map(move |(label, span)| {
+                let data = field?.get(label)?;
+                Some((label.as_str(), data, span.clone()))
+            })
+    }
+}
+
+impl<R: Read> Iso8211Reader<R> {
+    pub fn new(mut rdr: R) -> Result<Iso8211Reader<R>> {
+        let mut ddr = parse_ddr(&mut rdr).context(ErrorKind::CouldNotParseCatalog)?;
+        let diagnostics = std::mem::take(&mut ddr.diagnostics);
+        Ok(Iso8211Reader {
+            ddr,
+            rdr,
+            read: 0,
+            budget: None,
+            records: 0,
+            diagnostics,
+            on_error: None,
+            shared: None,
+            partial_records: false,
+            index: None,
+            start: None,
+        })
+    }
+
+    /// Like [`Iso8211Reader::new`], with the options of `config`.
+    pub fn with_config(rdr: R, config: &ParserConfig) -> Result<Iso8211Reader<R>> {
+        let mut reader = Iso8211Reader::new(rdr)?;
+        config.apply(&mut reader);
+        Ok(reader)
+    }
+
+    /// Like [`Iso8211Reader::new`], but iteration fails with `ErrorKind::MemoryBudgetExceeded`
+    /// instead of reading a Data Record that would take the bytes read in this pass over
+    /// `budget`. The record is skipped without keeping it, so the reader can still be rewound.
+    pub fn with_memory_budget(rdr: R, budget: u64) -> Result<Iso8211Reader<R>> {
+        let mut reader = Iso8211Reader::new(rdr)?;
+        reader.budget = Some(budget);
+        Ok(reader)
+    }
+
+    /// Parses only the Data Descriptive Record and returns the schema it declares, without
+    /// reading any data records.
+    pub fn read_schema_only(mut rdr: R) -> Result<Schema> {
+        let ddr = parse_ddr(&mut rdr).context(ErrorKind::CouldNotParseCatalog)?;
+        Ok(Schema(ddr))
+    }
+
+    /// The leader of the DDR.
+    pub fn leader(&self) -> &LeaderInfo {
+        &self.ddr.leader
+    }
+
+    /// The byte offset of the next Data Record, counting from the start of the DDR.
+    pub fn position(&self) -> u64 {
+        self.ddr.len as u64 + self.read
+    }
+
+    /// Iterates the Data Records together with the byte range each one occupies, counting from
+    /// the start of the DDR.
+    pub fn with_spans(self) -> Spanned<R> {
+        Spanned(self)
+    }
+
+    /// Iterates the Data Records as a stream of [`Event`]s, decoding one subfield at a time.
+    pub fn events(self) -> Iso8211Events<R> {
+        Iso8211Events {
+            reader: self,
+            record: None,
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// The diagnostics collected while parsing so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Reads every remaining Data Record. If any of them can not be read, the ones that can are
+    /// returned in a [`DiagnosticsReport`] together with the errors of the others.
+    pub fn read_all(&mut self) -> std::result::Result<Vec<Record>, DiagnosticsReport> {
+        let mut records = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            let (index, read) = (self.records, self.read);
+            match self.next() {
+                Some(Ok(record)) => records.push(record),
+                Some(Err(err)) => {
+                    errors.push((index, err));
+                    // Reading would fail the same way again if it did not get past the record
+                    if self.read == read {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        if errors.is_empty() {
+            Ok(records)
+        } else {
+            Err(DiagnosticsReport {
+                records,
+                errors,
+                diagnostics: self.diagnostics.clone(),
+            })
+        }
+    }
+
+    /// Parses every remaining Data Record, handing each decoded subfield to `visitor` instead of
+    /// collecting them into [`Record`]s. A record that can not be decoded goes to the handler set
+    /// with [`Iso8211Reader::on_error`], and if that skips it the visitor gets no `end_record`
+    /// for it.
+    pub fn parse_with<V: Visitor>(&mut self, visitor: &mut V) -> Result<()> {
+        while let Some(data) = self.read_raw_dr()? {
+            let index = self.records - 1;
+            let err = match self.visit_dr(&data, index, visitor) {
+                Ok(Flow::Stop) => return Ok(()),
+                Ok(_) => continue,
+                Err(err) => err,
+            };
+            let action = match self.on_error.as_mut() {
+                Some(handler) => (handler.0)(&err, &data),
+                None => ErrorAction::Abort,
+            };
+            if action == ErrorAction::Abort {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    // Hands the subfields of the Data Record data, the index-th one, to visitor. Returns
+    // Flow::Stop if the visitor wants no more records.
+    fn visit_dr<V: Visitor>(&mut self, data: &[u8], index: usize, visitor: &mut V) -> Result<Flow> {
+        let (_, dirs, field_data) = self.split_dr(data)?;
+        match visitor.record(index) {
+            Flow::Continue => (),
+            Flow::Skip => return Ok(Flow::Skip),
+            Flow::Stop => return Ok(Flow::Stop),
+        }
+        let base = data.len() - field_data.len();
+        let truncated = parse_to_usize(&data[..5]).is_ok_and(|rl| rl > data.len());
+        for dir_entry in dirs.iter() {
+            if truncated && dir_entry.offset + dir_entry.length > field_data.len() {
+                continue;
+            }
+            match visitor.field(&dir_entry.id) {
+                Flow::Continue => (),
+                Flow::Skip => continue,
+                Flow::Stop => return Ok(Flow::Stop),
+            }
+            let DecodedField {
+                mut field, rows, ..
+            } = self.ddr.decode_field(dir_entry, &field_data, base)?;
+            let ddf_entry = &self.ddr.data_descriptive_fields[&dir_entry.id];
+            // The subfields in the order of the field: those before the repeating group, then
+            // every repetition of the group
+            let group = ddf_entry.repeating_group().unwrap_or(ddf_entry.foc.len());
+            let once = ddf_entry.foc[..group]
+                .iter()
+                .map(|(label, _)| (label, field.remove(label).unwrap_or(Data::Missing)));
+            let repeated = rows.into_iter().flatten().flat_map(|row| {
+                ddf_entry.foc[group..]
+                    .iter()
+                    .map(|(label, _)| label)
+                    .zip(row)
+            });
+            for (label, data) in once.chain(repeated) {
+                if let Some(kind) = check_lexical_level(&ddf_entry.fic.tes, label, &data) {
+                    self.diagnostics.push(Diagnostic {
+                        record: Some(index),
+                        tag: Some(dir_entry.id.clone()),
+                        kind,
+                    });
+                }
+                match visitor.subfield(&dir_entry.id, label, data) {
+                    Flow::Continue => (),
+                    Flow::Skip => break,
+                    Flow::Stop => return Ok(Flow::Stop),
+                }
+            }
+        }
+        Ok(visitor.end_record(index))
+    }
+
+    /// Sets a handler called with the error and the bytes of every Data Record that can not be
+    /// decoded. If it returns `ErrorAction::Continue` the record is skipped, otherwise iteration
+    /// yields the error. Errors reading the file are never handed to the handler.
+    pub fn on_error<F>(&mut self, handler: F)
+    where
+        F: FnMut(&Error, &[u8]) -> ErrorAction + 'static,
+    {
+        self.on_error = Some(ErrorHandler(Box::new(handler)));
+    }
+
+    // Reads the next Data Record as stored. The records following one with leader identifier
+    // 'R' are only a field area, they get the leader and directory of that record put back.
+    fn read_raw_dr(&mut self) -> Result<Option<Vec<u8>>> {
+        let limit = self.budget.map(|budget| budget.saturating_sub(self.read));
+        let mut rdr = Counted(&mut self.rdr, 0);
+        let read = match &self.shared {
+            Some((header, field_area_len)) => read_field_area(&mut rdr, *field_area_len, limit)
+                .map(|(field_area, missing)| {
+                    let mut data = header.clone();
+                    data.extend(field_area);
+                    (data, missing)
+                }),
+            None => read_partial_record(&mut rdr, limit),
+        };
+        // Counting what was read rather than the record length includes trailing padding, and
+        // what was read before failing
+        self.read += rdr.1;
+        let data = match read {
+            Ok((data, 0)) => data,
+            Ok((data, missing)) => {
+                if !self.partial_records {
+                    return Err(ErrorKind::TruncatedRecord(self.records, missing).into());
+                }
+                self.diagnostics.push(Diagnostic {
+                    record: Some(self.records),
+                    tag: None,
+                    kind: DiagnosticKind::TruncatedRecord { missing },
+                });
+                data
+            }
+            Err(err) => match err.kind() {
+                ErrorKind::EOF => return Ok(None),
+                _ => return Err(err),
+            },
+        };
+        if self.shared.is_none() && data.get(6) == Some(&b'R') {
+            let (_, _, field_area_idx) = parse_record_layout(&data)?;
+            let header = data[..field_area_idx].to_vec();
+            self.shared = Some((header, data.len() - field_area_idx));
+        }
+        self.records += 1;
+        Ok(Some(data))
+    }
+
+    // Splits a Data Record read by read_raw_dr into its leader, directory and field area.
+    fn split_dr(&mut self, data: &[u8]) -> Result<RecordParts> {
+        let (leader, dirs, field_area_idx) = parse_record_layout(data)?;
+        // The field area is found from the directory terminator, the leader only gets a say in
+        // the diagnostics
+        if leader.ba as usize != field_area_idx {
+            self.diagnostics.push(Diagnostic {
+                record: Some(self.records - 1),
+                tag: None,
+                kind: DiagnosticKind::BaseAddressMismatch {
+                    declared: leader.ba as usize,
+                    actual: field_area_idx,
+                },
+            });
+        }
+        Ok((leader, dirs, data[field_area_idx..].to_vec()))
+    }
+
+    // Reads the next Data Record, returning its directory and field area.
+    fn read_dr(&mut self) -> Result<Option<RecordParts>> {
+        match self.read_raw_dr()? {
+            Some(data) => Ok(Some(self.split_dr(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_dr(&mut self) -> Result<Option<Record>> {
+        self.parse_dr_where(&mut |_| true)
+    }
+
+    // Reads Data Records until one whose directory satisfies predicate, and decodes that one.
+    fn parse_dr_where(
+        &mut self,
+        predicate: &mut dyn FnMut(&LazyRecord) -> bool,
+    ) -> Result<Option<Record>> {
+        loop {
+            let data = match self.read_raw_dr()? {
+                Some(data) => data,
+                None => return Ok(None),
+            };
+            let decoded = self.split_dr(&data).and_then(|(leader, dirs, field_data)| {
+                let lazy = LazyRecord {
+                    ddr: &self.ddr,
+                    leader: leader.info(),
+                    dirs,
+                    base: data.len() - field_data.len(),
+                    field_data,
+                };
+                if !predicate(&lazy) {
+                    return Ok(None);
+                }
+                let LazyRecord {
+                    leader,
+                    dirs,
+                    field_data,
+                    ..
+                } = lazy;
+                self.decode_dr(&data, leader, &dirs, &field_data).map(Some)
+            });
+            let err = match decoded {
+                Ok(Some(record)) => return Ok(Some(record)),
+                Ok(None) => continue,
+                Err(err) => err,
+            };
+            let action = match self.on_error.as_mut() {
+                Some(handler) => (handler.0)(&err, &data),
+                None => ErrorAction::Abort,
+            };
+            if action == ErrorAction::Abort {
+                return Err(err);
+            }
+        }
+    }
+
+    // Decodes the fields of the Data Record data, already split into its leader, directory and
+    // field area.
+    fn decode_dr(
+        &mut self,
+        data: &[u8],
+        leader: LeaderInfo,
+        dirs: &[DirectoryEntry],
+        field_data: &[u8],
+    ) -> Result<Record> {
+        // Index of the field area in the record
+        let base = data.len() - field_data.len();
+        let field_area_len = field_data.len();
+        // The leader takes the length read as the record length, the declared one is longer
+        // if the record is truncated
+        let truncated = parse_to_usize(&data[..5]).is_ok_and(|rl| rl > data.len());
+        let mut record = Record::new();
+        record.leader = Some(leader);
+        for dir_entry in dirs.iter() {
+            // Leave out the fields a truncated record does not reach the end of
+            if truncated && dir_entry.offset + dir_entry.length > field_area_len {
+                continue;
+            }
+            let decoded = self.ddr.decode_field(dir_entry, field_data, base)?;
+            let ddf_entry = &self.ddr.data_descriptive_fields[&dir_entry.id];
+            let field_area = decoded.field;
+            record
+                .raw
+                .entry(dir_entry.id.clone())
+                .or_default()
+                .push(self.ddr.raw_field(dir_entry, field_data).to_vec());
+            for (label, data) in field_area.iter() {
+                if let Some(kind) = check_lexical_level(&ddf_entry.fic.tes, label, data) {
+                    self.diagnostics.push(Diagnostic {
+                        record: Some(self.records - 1),
+                        tag: Some(dir_entry.id.clone()),
+                        kind,
+                    });
+                }
+            }
+            if record.fields.contains_key(&dir_entry.id) {
+                record
+                    .repeated
+                    .entry(dir_entry.id.clone())
+                    .or_default()
+                    .push((field_area, decoded.rows));
+                continue;
+            }
+            record.tags.push(dir_entry.id.clone());
+            record.fields.insert(dir_entry.id.clone(), field_area);
+            record.labels.insert(
+                dir_entry.id.clone(),
+                ddf_entry
+                    .foc
+                    .iter()
+                    .map(|(label, _)| label.clone())
+                    .collect(),
+            );
+            record.spans.insert(dir_entry.id.clone(), decoded.spans);
+            if let Some(rows) = decoded.rows {
+                record.rows.insert(dir_entry.id.clone(), rows);
+            }
+        }
+        Ok(record)
+    }
+}
+
+impl<R: Read> Iso8211Reader<R> {
+    /// Reads the next Data Record, parsing only its leader and directory. See [`LazyRecord`].
+    pub fn next_lazy(&mut self) -> Option<Result<LazyRecord<'_>>> {
+        let data = match self.read_raw_dr() {
+            Ok(Some(data)) => data,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        let (leader, dirs, field_data) = match self.split_dr(&data) {
+            Ok(parts) => parts,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(Ok(LazyRecord {
+            ddr: &self.ddr,
+            leader: leader.info(),
+            dirs,
+            base: data.len() - field_data.len(),
+            field_data,
+        }))
+    }
+
+    /// Iterates the Data Records whose leader and directory satisfy `predicate`, e.g.
+    /// `|record| record.has_field("CATD")`. The fields of the other records are not decoded.
+    pub fn records_where<'a, F>(
+        &'a mut self,
+        mut predicate: F,
+    ) -> impl Iterator<Item = Result<Record>> + 'a
+    where
+        F: FnMut(&LazyRecord) -> bool + 'a,
+    {
+        std::iter::from_fn(move || self.parse_dr_where(&mut predicate).transpose())
+    }
+}
+
+/// A Data Record of which only the leader and directory have been parsed, returned by
+/// [`Iso8211Reader::next_lazy`]. A field is decoded when it is asked for, for callers that
+/// only need a few fields of each record.
+#[derive(Debug)]
+pub struct LazyRecord<'a> {
+    ddr: &'a DDR,
+    leader: LeaderInfo,
+    dirs: Vec<DirectoryEntry>,
+    field_data: Vec<u8>,
+    base: usize, // Index of the field area in the record
+}
+
+impl LazyRecord<'_> {
+    pub fn leader(&self) -> &LeaderInfo {
+        &self.leader
+    }
+
+    /// The tags of the fields in the order of the directory, a repeated field once for each
+    /// occurrence.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.dirs.iter().map(|dir| dir.id.as_str())
+    }
+
+    pub fn has_field(&self, tag: &str) -> bool {
+        self.dirs.iter().any(|dir| dir.id == tag)
+    }
+
+    /// The first field `tag` as read from the file, without its field terminator and without
+    /// decoding it.
+    pub fn raw_bytes(&self, tag: &str) -> Option<&[u8]> {
+        let dir = self.dirs.iter().find(|dir| dir.id == tag)?;
+        Some(self.ddr.raw_field(dir, &self.field_data))
+    }
+
+    /// Decodes the first field with the tag `tag`.
+    pub fn field(&self, tag: &str) -> Result<Option<Field>> {
+        match self.dirs.iter().find(|dir| dir.id == tag) {
+            Some(dir) => Ok(Some(
+                self.ddr
+                    .decode_field(dir, &self.field_data, self.base)?
+                    .field,
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<R: Read + Seek> Iso8211Reader<R> {
+    /// Seeks back to the first Data Record so that the catalog can be iterated again without
+    /// re-parsing the DDR.
+    pub fn rewind(&mut self) -> Result<()> {
+        let start = self.start()?;
+        self.rdr
+            .seek(SeekFrom::Start(start))
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        self.read = 0;
+        self.records = 0;
+        self.shared = None;
+        Ok(())
+    }
+
+    /// A reader of the same file through `rdr`, e.g. another handle to it, positioned at the
+    /// start of the DDR like the one this reader was created with. It starts out at the first
+    /// Data Record without parsing the DDR again, and keeps the options and scan of this
+    /// reader, but not its error handler.
+    pub fn restart_with(&self, mut rdr: R) -> Result<Iso8211Reader<R>> {
+        let start = rdr
+            .seek(SeekFrom::Current(self.ddr.len as i64))
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        Ok(Iso8211Reader {
+            ddr: self.ddr.clone(),
+            rdr,
+            read: 0,
+            budget: self.budget,
+            records: 0,
+            diagnostics: Vec::new(),
+            on_error: None,
+            shared: None,
+            partial_records: self.partial_records,
+            index: self.index.clone(),
+            start: Some(start),
+        })
+    }
+
+    // The stream position of the first Data Record. Unless the reader was created from a
+    // seekable one, it is found from the bytes read since, the first time it is needed.
+    fn start(&mut self) -> Result<u64> {
+        if let Some(start) = self.start {
+            return Ok(start);
+        }
+        let here = self
+            .rdr
+            .stream_position()
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        let start = here - self.read;
+        self.start = Some(start);
+        Ok(start)
+    }
+
+    /// Finds where each Data Record starts from the record lengths alone, without decoding
+    /// anything, and returns the number of Data Records in the file. Afterwards the iterators
+    /// over the records know how many are left.
+    pub fn scan_records(&mut self) -> Result<usize> {
+        let io_error = |err: &std::io::Error| ErrorKind::IOError(err.kind());
+        let first = self.start()?;
+        let here = self.rdr.stream_position().with_context(io_error)?;
+        let end = self.rdr.seek(SeekFrom::End(0)).with_context(io_error)?;
+        let mut offsets = Vec::new();
+        let mut reused = None;
+        let mut pos = first;
+        // Length of the field areas of the records after one with leader identifier 'R'
+        let mut shared = None;
+        while pos < end {
+            let len = match shared {
+                Some(len) => len,
+                None => {
+                    self.rdr.seek(SeekFrom::Start(pos)).with_context(io_error)?;
+                    let mut leader = Vec::with_capacity(24);
+                    (&mut self.rdr)
+                        .take(24)
+                        .read_to_end(&mut leader)
+                        .with_context(io_error)?;
+                    if leader.iter().all(|&b| b == 0 || b == 0x1a) {
+                        break;
+                    }
+                    let len = match parse_to_usize(&leader[..leader.len().min(5)]) {
+                        Ok(len) if len >= 24 => len,
+                        // Unsized records can only be measured by reading them
+                        _ => {
+                            self.rdr.seek(SeekFrom::Start(pos)).with_context(io_error)?;
+                            read_record(&mut self.rdr, None)?.len()
+                        }
+                    };
+                    if leader.get(6) == Some(&b'R') {
+                        let ba = leader.get(12..17).ok_or(ErrorKind::InvalidLeader)?;
+                        match len.checked_sub(parse_to_usize(ba)?) {
+                            Some(field_area_len) if field_area_len > 0 => {
+                                shared = Some(field_area_len);
+                                reused = Some(offsets.len());
+                            }
+                            _ => return Err(ErrorKind::InvalidLeader.into()),
+                        }
+                    }
+                    len
+                }
+            };
+            offsets.push(pos - first);
+            pos += len as u64;
+        }
+        self.rdr
+            .seek(SeekFrom::Start(here))
+            .with_context(io_error)?;
+        let count = offsets.len();
+        self.index = Some(RecordIndex { offsets, reused });
+        Ok(count)
+    }
+
+    /// The number of Data Records in the file, counted from their record lengths alone like
+    /// [`Iso8211Reader::scan_records`] does, unless the file has been scanned already. Where
+    /// the reader is in the file does not change.
+    pub fn count_records(&mut self) -> Result<usize> {
+        match &self.index {
+            Some(index) => Ok(index.offsets.len()),
+            None => self.scan_records(),
+        }
+    }
+
+    /// Seeks to the Data Record with index `n` and reads it, scanning the file first if that
+    /// has not been done. Iteration goes on from the record after it.
+    pub fn record(&mut self, n: usize) -> Result<Option<Record>> {
+        if self.index.is_none() {
+            self.scan_records()?;
+        }
+        let (offset, reused) = match &self.index {
+            Some(index) if n < index.offsets.len() => (
+                index.offsets[n],
+                index
+                    .reused
+                    .filter(|&r| r < n)
+                    .map(|r| (r, index.offsets[r])),
+            ),
+            _ => return Ok(None),
+        };
+        self.shared = None;
+        // A record without leader and directory needs those of the one it reuses them from
+        if let Some((r, reused_offset)) = reused {
+            self.seek_dr(reused_offset)?;
+            self.records = r;
+            self.read_raw_dr()?;
+        }
+        self.seek_dr(offset)?;
+        self.records = n;
+        self.parse_dr()
+    }
+
+    // Seeks to the Data Record at offset, counting from the first.
+    fn seek_dr(&mut self, offset: u64) -> Result<()> {
+        let start = self.start()?;
+        self.rdr
+            .seek(SeekFrom::Start(start + offset))
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        self.read = offset;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Iso8211Reader<R> {
+    type Item = Result<Record>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parse_dr() {
+            Ok(Some(dr)) => Some(Ok(dr)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    // Known once the file has been scanned. Records skipped by an error handler yield nothing,
+    // so there is no lower bound with one.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.index {
+            Some(index) => {
+                let left = index.offsets.len().saturating_sub(self.records);
+                match self.on_error {
+                    Some(_) => (0, Some(left)),
+                    None => (left, Some(left)),
+                }
+            }
+            None => (0, None),
+        }
+    }
+}
+
+impl<R: Read> Iso8211Reader<R> {
+    /// Iterates the Data Records from the next one on. See [`Records`].
+    pub fn records(&mut self) -> Records<'_, R> {
+        Records {
+            reader: self,
+            peeked: None,
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the Data Records of an [`Iso8211Reader`], returned by
+/// [`Iso8211Reader::records`]. A record that can not be read or decoded is yielded as an
+/// error, after which iteration goes on with the next record if there is one to be read. Once
+/// it has returned `None` it always will. Its length is known after
+/// [`Iso8211Reader::scan_records`].
+#[derive(Debug)]
+pub struct Records<'a, R: Read> {
+    reader: &'a mut Iso8211Reader<R>,
+    peeked: Option<Result<Record>>, // Read by peek but not yet yielded
+    done: bool,
+}
+
+impl<R: Read> Records<'_, R> {
+    /// The record the next call to `next` yields, without consuming it.
+    pub fn peek(&mut self) -> Option<&Result<Record>> {
+        if self.peeked.is_none() && !self.done {
+            self.peeked = self.reader.next();
+            self.done = self.peeked.is_none();
+        }
+        self.peeked.as_ref()
+    }
+}
+
+impl<R: Read> Iterator for Records<'_, R> {
+    type Item = Result<Record>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+        if self.done {
+            return None;
+        }
+        let next = self.reader.next();
+        self.done = next.is_none();
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let peeked = usize::from(self.peeked.is_some());
+        if self.done {
+            (peeked, Some(peeked))
+        } else {
+            let (lower, upper) = self.reader.size_hint();
+            (lower + peeked, upper.map(|upper| upper + peeked))
+        }
+    }
+}
+
+impl<R: Read> FusedIterator for Records<'_, R> {}
+
+/// Iterator over the Data Records of an [`Iso8211Reader`] borrowed immutably, for readers that
+/// can be read through a shared reference, like `File`. Each one starts at the first Data
+/// Record, so the reader can be iterated any number of times. The position of the reader is
+/// restored when the iterator is dropped, but two of them must not be used at the same time.
+#[derive(Debug)]
+pub struct Iter<'a, R: Read>
+where
+    &'a R: Read + Seek,
+{
+    reader: Iso8211Reader<&'a R>,
+    resume: Option<u64>,  // Where to seek the reader back to when done
+    error: Option<Error>, // Failing to find the first Data Record, yielded first
+}
+
+impl<'a, R: Read> IntoIterator for &'a Iso8211Reader<R>
+where
+    &'a R: Read + Seek,
+{
+    type Item = Result<Record>;
+    type IntoIter = Iter<'a, R>;
+
+    fn into_iter(self) -> Iter<'a, R> {
+        let mut rdr = &self.rdr;
+        let position = rdr.stream_position().and_then(|here| {
+            let start = self.start.unwrap_or(here - self.read);
+            rdr.seek(SeekFrom::Start(start))?;
+            Ok((here, start))
+        });
+        let reader = Iso8211Reader {
+            ddr: self.ddr.clone(),
+            rdr,
+            read: 0,
+            budget: self.budget,
+            records: 0,
+            diagnostics: Vec::new(),
+            on_error: None,
+            shared: None,
+            partial_records: self.partial_records,
+            index: self.index.clone(),
+            start: position.as_ref().ok().map(|&(_, start)| start),
+        };
+        match position {
+            Ok((here, _)) => Iter {
+                reader,
+                resume: Some(here),
+                error: None,
+            },
+            Err(err) => Iter {
+                reader,
+                resume: None,
+                error: Some(ErrorKind::IOError(err.kind()).into()),
+            },
+        }
+    }
+}
+
+impl<'a, R: Read> Iterator for Iter<'a, R>
+where
+    &'a R: Read + Seek,
+{
+    type Item = Result<Record>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.error.take() {
+            Some(err) => Some(Err(err)),
+            None if self.resume.is_some() => self.reader.next(),
+            None => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match (&self.error, self.resume) {
+            (Some(_), _) => (1, Some(1)),
+            (None, Some(_)) => self.reader.size_hint(),
+            (None, None) => (0, Some(0)),
+        }
+    }
+}
+
+impl<'a, R: Read> Drop for Iter<'a, R>
+where
+    &'a R: Read + Seek,
+{
+    fn drop(&mut self) {
+        if let Some(here) = self.resume {
+            let _ = self.reader.rdr.seek(SeekFrom::Start(here));
+        }
+    }
+}
+
+/// Iterator returned by [`Iso8211Reader::with_spans`].
+#[derive(Debug)]
+pub struct Spanned<R: Read>(Iso8211Reader<R>);
+
+impl<R: Read> Spanned<R> {
+    pub fn into_inner(self) -> Iso8211Reader<R> {
+        self.0
+    }
+}
+
+impl<R: Read> Iterator for Spanned<R> {
+    type Item = Result<(Range<u64>, Record)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.0.position();
+        let record = self.0.next()?;
+        Some(record.map(|record| (start..self.0.position(), record)))
+    }
+}
+
+/// Iterator returned by [`Iso8211Reader::events`]. It stops after the first error.
+#[derive(Debug)]
+pub struct Iso8211Events<R: Read> {
+    reader: Iso8211Reader<R>,
+    record: Option<EventRecord>, // The record being gone through
+    index: usize,                // Of the record being gone through
+    done: bool,
+}
+
+// Where Iso8211Events is in the current record.
+#[derive(Debug)]
+struct EventRecord {
+    dirs: Vec<DirectoryEntry>,
+    field_data: Vec<u8>,
+    dir: usize, // The field being gone through
+    // The next subfield of the field, by its index in the format controls, and where it
+    // starts in the field area. None before the field has been started.
+    subfield: Option<(usize, usize)>,
+}
+
+impl<R: Read> Iso8211Events<R> {
+    pub fn into_inner(self) -> Iso8211Reader<R> {
+        self.reader
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>> {
+        loop {
+            let record = match &mut self.record {
+                Some(record) => record,
+                None => {
+                    return match self.reader.read_dr()? {
+                        Some((_, dirs, field_data)) => {
+                            self.record = Some(EventRecord {
+                                dirs,
+                                field_data,
+                                dir: 0,
+                                subfield: None,
+                            });
+                            Ok(Some(Event::StartRecord(self.index)))
+                        }
+                        None => Ok(None),
+                    }
+                }
+            };
+            let dir_entry = match record.dirs.get(record.dir) {
+                Some(dir_entry) => dir_entry,
+                None => {
+                    self.record = None;
+                    self.index += 1;
+                    return Ok(Some(Event::EndRecord));
+                }
+            };
+            let (i, at) = match record.subfield {
+                Some(next) => next,
+                None => {
+                    record.subfield = Some((0, dir_entry.offset));
+                    return Ok(Some(Event::Field(dir_entry.id.clone())));
+                }
+            };
+            let ddf_entry = self
+                .reader
+                .ddr
+                .data_descriptive_fields
+                .get(&dir_entry.id)
+                .ok_or(ErrorKind::InvalidDR)?;
+            let encoding = self.reader.ddr.encoding(&ddf_entry.fic);
+            let field_end = (dir_entry.offset + dir_entry.length).saturating_sub(match encoding {
+                Encoding::Ucs2 => 2,
+                _ => 1,
+            });
+            // A repeating group starts over until the field terminator
+            let i = match ddf_entry.repeating_group() {
+                Some(group) if i == ddf_entry.foc.len() && at < field_end => group,
+                _ => i,
+            };
+            let (label, parser) = match ddf_entry.foc.get(i) {
+                Some(subfield) => subfield,
+                None => {
+                    record.dir += 1;
+                    record.subfield = None;
+                    continue;
+                }
+            };
+            let (data, end) = if at >= field_end {
+                (Data::Missing, at)
+            } else {
+                let mut cur = std::io::Cursor::new(&record.field_data[..]);
+                cur.set_position(at as u64);
+                let data = parser.parse(&mut cur, encoding).with_context(|_| {
+                    ErrorKind::BadSubfield(
+                        dir_entry.id.clone(),
+                        i,
+                        label.clone(),
+                        escape(record.field_data.get(at..field_end).unwrap_or_default()),
+                    )
+                })?;
+                (ddf_entry.fic.scale(data), cur.position() as usize)
+            };
+            if let Some(kind) = check_lexical_level(&ddf_entry.fic.tes, label, &data) {
+                self.reader.diagnostics.push(Diagnostic {
+                    record: Some(self.index),
+                    tag: Some(dir_entry.id.clone()),
+                    kind,
+                });
+            }
+            record.subfield = Some((i + 1, end));
+            return Ok(Some(Event::Subfield(label.clone(), data)));
+        }
+    }
+}
+
+impl<R: Read> Iterator for Iso8211Events<R> {
+    type Item = Result<Event>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event().transpose();
+        self.done = !matches!(event, Some(Ok(_)));
+        event
+    }
+}
+
+impl<R: Read> FusedIterator for Iso8211Events<R> {}
+
+// A reader keeping count of the bytes read through it.
+struct Counted<'a, R: Read>(&'a mut R, u64);
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.read(buf)?;
+        self.1 += n as u64;
+        Ok(n)
+    }
+}
+
+// Reads past the next len bytes without keeping them.
+fn skip<R: Read>(rdr: &mut R, len: u64) -> Result<()> {
+    std::io::copy(&mut rdr.take(len), &mut std::io::sink())
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    Ok(())
+}
+
+// Reads a whole record, from the record length through the last field terminator.
+pub(crate) fn read_record<R: Read>(rdr: &mut R, limit: Option<u64>) -> Result<Vec<u8>> {
+    match read_partial_record(rdr, limit)? {
+        (data, 0) => Ok(data),
+        _ => Err(ErrorKind::IOError(std::io::ErrorKind::UnexpectedEof).into()),
+    }
+}
+
+// Like read_record, but a record cut short by the end of the input is returned as far as it
+// goes, together with the number of bytes missing from it.
+fn read_partial_record<R: Read>(rdr: &mut R, limit: Option<u64>) -> Result<(Vec<u8>, usize)> {
+    // Read the length of the record, stored in the first 5 bytes
+    let mut len_bytes = [0; 5];
+    let nr_of_bytes = rdr
+        .read(&mut len_bytes)
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    // Some files are padded with NULs or ^Z after the last record
+    let is_padding = |bytes: &[u8]| bytes.iter().all(|&b| b == 0 || b == 0x1a);
+    if nr_of_bytes > 0 && is_padding(&len_bytes[..nr_of_bytes]) {
+        let mut rest = Vec::new();
+        rdr.read_to_end(&mut rest)
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        if is_padding(&rest) {
+            return Err(ErrorKind::EOF.into());
+        }
+        return Err(ErrorKind::InvalidLeader.into());
+    }
+    match nr_of_bytes {
+        0 => return Err(ErrorKind::EOF.into()),
+        5 => (),
+        _ => return Err(ErrorKind::IOError(std::io::ErrorKind::UnexpectedEof).into()),
+    }
+
+    // Records longer than 99999 bytes can't state their length, some producers write something
+    // non-numeric instead and the extent has to be found from the directory.
+    let length = match parse_to_usize(&len_bytes) {
+        Ok(length) if length >= 24 => length,
+        _ => return read_unsized_record(rdr, len_bytes, limit).map(|data| (data, 0)),
+    };
+    if let Some(limit) = limit {
+        if length as u64 > limit {
+            skip(rdr, length as u64 - 5)?;
+            return Err(ErrorKind::MemoryBudgetExceeded(limit).into());
+        }
+    }
+    let mut data = Vec::with_capacity(length);
+    data.extend_from_slice(&len_bytes);
+    rdr.take(length as u64 - 5)
+        .read_to_end(&mut data)
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    let missing = length - data.len();
+    Ok((data, missing))
+}
+
+// Reads the len bytes of a field area of a record without leader and directory, returning it
+// together with the number of bytes missing if the input ends before it does.
+fn read_field_area<R: Read>(
+    rdr: &mut R,
+    len: usize,
+    limit: Option<u64>,
+) -> Result<(Vec<u8>, usize)> {
+    if let Some(limit) = limit {
+        if len as u64 > limit {
+            skip(rdr, len as u64)?;
+            return Err(ErrorKind::MemoryBudgetExceeded(limit).into());
+        }
+    }
+    let mut data = Vec::with_capacity(len);
+    let read = rdr
+        .take(len as u64)
+        .read_to_end(&mut data)
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    match read {
+        0 if len > 0 => Err(ErrorKind::EOF.into()),
+        read => Ok((data, len - read)),
+    }
+}
+
+fn read_unsized_record<R: Read>(
+    rdr: &mut R,
+    len_bytes: [u8; 5],
+    limit: Option<u64>,
+) -> Result<Vec<u8>> {
+    let mut data = len_bytes.to_vec();
+    data.resize(24, 0);
+    rdr.read_exact(&mut data[5..])
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    let leader = parse_leader(&data[5..24], 0)?;
+    let mut byte = [0; 1];
+    while byte[0] != RECORD_SEPARATOR {
+        rdr.read_exact(&mut byte)
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        data.push(byte[0]);
+    }
+    let dirs = parse_directory(&data[24..data.len() - 1], &leader)?;
+    let field_area_len = dirs.iter().map(|d| d.offset + d.length).max().unwrap_or(0);
+    let start = data.len();
+    if let Some(limit) = limit {
+        if (start + field_area_len) as u64 > limit {
+            skip(rdr, field_area_len as u64)?;
+            return Err(ErrorKind::MemoryBudgetExceeded(limit).into());
+        }
+    }
+    data.resize(start + field_area_len, 0);
+    rdr.read_exact(&mut data[start..])
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    Ok(data)
+}
+
+// Parses the leader and directory of a record read by read_record. Returns them together with
+// the index of the field area in the record.
+pub(crate) fn parse_record_layout(data: &[u8]) -> Result<(Leader, Vec<DirectoryEntry>, usize)> {
+    if data.len() < 24 {
+        return Err(ErrorKind::InvalidLeader.into());
+    }
+    let mut leader = parse_leader(&data[5..24], data.len())?;
+    leader.drl = parse_to_usize(&data[..5]).ok();
+    let field_area_idx = match data[24..].iter().position(|&b| b == RECORD_SEPARATOR) {
+        Some(index) => 24 + index,
+        None => return Err(ErrorKind::BadDirectoryData.into()),
+    };
+    let dirs = parse_directory(&data[24..field_area_idx], &leader)?;
+    Ok((leader, dirs, field_area_idx + 1))
+}
+
+fn parse_dir_and_field_area<R: Read>(rdr: &mut R, limit: Option<u64>) -> Result<RecordParts> {
+    let data = read_record(rdr, limit)?;
+    let (leader, dirs, field_area_idx) = parse_record_layout(&data)?;
+    // The field area of the DDR has to be where the leader says, or the schema of the whole
+    // file is likely off
+    if leader.ba as usize != field_area_idx {
+        return Err(ErrorKind::BadBaseAddress(leader.ba as usize, field_area_idx).into());
+    }
+    Ok((leader, dirs, data[field_area_idx..].to_vec()))
+}
+
+fn parse_ddr<R: Read>(rdr: &mut R) -> Result<DDR> {
+    let (leader, mut dirs, field_area) = parse_dir_and_field_area(rdr, None)?;
+    let diagnostics = repair_directory(&mut dirs, &field_area);
+    let fcl = leader.field_control_length()?;
+    let data_descriptive_fields =
+        parse_ddfs(&field_area, &dirs, fcl).context(ErrorKind::InvalidDDR)?;
+    let file_control_field = match dirs.iter().find(|dir| is_file_control_field(dir)) {
+        Some(dir) => Some(
+            parse_file_control_field(field_slice(&field_area, dir)?, leader.ftf, fcl)
+                .context(ErrorKind::InvalidDDR)?,
+        ),
+        None => None,
+    };
+
+    Ok(DDR {
+        len: leader.rl,
+        leader: leader.info(),
+        diagnostics,
+        code_extension: leader.cei == 'E',
+        charset: CharacterSet::from_csi(&leader.csi),
+        dirs,
+        file_control_field,
+        data_descriptive_fields,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_test_leader() -> Leader {
+        Leader {
+            rl: 241,
+            drl: None,
+            il: '3',
+            li: 'L',
+            cei: 'E',
+            vn: '1',
+            ai: ' ',
+            fcl: ['0', '9'],
+            ba: 58,
+            csi: [' ', '!', ' '],
+            flf: 3,
+            fpf: 4,
+            rsv: '0',
+            ftf: 4,
+        }
+    }
+
+    fn get_test_directory() -> Vec<DirectoryEntry> {
+        vec![
+            DirectoryEntry {
+                id: "0000".to_string(),
+                length: 19,
+                offset: 0,
+            },
+            DirectoryEntry {
+                id: "0001".to_string(),
+                length: 44,
+                offset: 19,
+            },
+            DirectoryEntry {
+                id: "CATD".to_string(),
+                length: 120,
+                offset: 63,
+            },
+        ]
+    }
+
+    fn get_test_field_controls() -> FieldControls {
+        FieldControls {
+            dsc: DataStructureCode::LS,
+            dtc: DataTypeCode::MDT,
+            aux: 0,
+            prt: PrintableGraphics::from_field_controls(b"1600;&"),
+            tes: TruncEscSeq::LE1,
+        }
+    }
+
+    fn get_test_format_controls() -> Vec<ParseData> {
+        vec![
+            ParseData::Fixed(ParseType::String, 2),
+            ParseData::Fixed(ParseType::Integer, 10),
+            ParseData::Fixed(ParseType::Integer, 10),
+            ParseData::Variable(ParseType::Float),
+            ParseData::Variable(ParseType::Float),
+        ]
+    }
+
+    #[test]
+    fn test_parse_leader() {
+        let length = 241;
+        let leader = "3LE1 0900058 ! 3404".as_bytes();
+        let expected = get_test_leader();
+        let actual = parse_leader(leader, length).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_directory() {
+        let leader = get_test_leader();
+        let directory = "0000019000000010440019CATD1200063".as_bytes();
+        let expected = get_test_directory();
+        let actual = parse_directory(directory, &leader).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_field_controls() {
+        let field_controls = "1600;&-A ".as_bytes();
+        let expected = get_test_field_controls();
+        let actual = parse_field_controls(field_controls).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_array_descriptor() {
+        let array_descriptor =
+            "RCNM!RCID!FILE!LFIL!VOLM!IMPL!SLAT!WLON!NLAT!ELON!CRCS!COMT".as_bytes();
+        let expected = vec![
+            "RCNM", "RCID", "FILE", "LFIL", "VOLM", "IMPL", "SLAT", "WLON", "NLAT", "ELON", "CRCS",
+            "COMT",
+        ];
+        let actual = parse_array_descriptors(array_descriptor).unwrap();
+        assert_eq!(actual.labels, expected);
+        assert_eq!(actual.repeat, None);
+        let actual = parse_array_descriptors(b"*YCOO!XCOO").unwrap();
+        assert_eq!(actual.labels, ["YCOO", "XCOO"]);
+        assert_eq!(actual.repeat, Some(0));
+        let actual = parse_array_descriptors(b"DEPTH!TEMP\\\\*JAN!FEB!MAR").unwrap();
+        assert_eq!(actual.rows, ["DEPTH", "TEMP"]);
+        assert_eq!(actual.labels, ["JAN", "FEB", "MAR"]);
+        assert_eq!(actual.repeat, None);
+    }
+
+    #[test]
+    fn test_parse_format_controls_with_empty() {
+        let array_descriptor = &[0u8; 0];
+        assert!(parse_format_controls(array_descriptor).is_err())
+    }
+
+    #[test]
+    fn test_parse_format_controls() {
+        let format_controls = "(A(2),2I(10),2R)".as_bytes();
+        let expected = get_test_format_controls();
+        let actual = parse_format_controls(format_controls).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_nested_format_controls() {
+        let actual = parse_format_controls(b"(A(2),3(I(2),R(4)))").unwrap();
+        assert_eq!(actual.len(), 7);
+        assert_eq!(actual[0], ParseData::Fixed(ParseType::String, 2));
+        assert_eq!(actual[5], ParseData::Fixed(ParseType::Integer, 2));
+        assert_eq!(actual[6], ParseData::Fixed(ParseType::Float, 4));
+        let actual = parse_format_controls(b"(2(A,2(b11)),I)").unwrap();
+        assert_eq!(actual.len(), 7);
+        assert_eq!(actual[5], ParseData::Fixed(ParseType::Unsigned, 1));
+        assert!(parse_format_controls(b"(2(A,I)").is_err());
+    }
+
+    #[test]
+    fn test_format_controls_notation() {
+        let description = FieldDescription {
+            tag: "TEST",
+            name: "Test",
+            labels: vec!["A", "B", "C", "D", "E"],
+            formats: get_test_format_controls()
+                .iter()
+                .map(ParseData::to_string)
+                .collect(),
+            repeats_from: None,
+            row_labels: Vec::new(),
+            auxiliary_controls: 0,
+            printable_graphics: get_test_field_controls().prt,
+        };
+        assert_eq!(description.format_controls(), "(A(2),2I(10),2R)");
+        assert_eq!(description.array_descriptor(), "A!B!C!D!E");
+        let drid = FieldDescription {
+            labels: vec![DRID],
+            formats: vec!["I(5)".to_string()],
+            ..description
+        };
+        assert_eq!(drid.array_descriptor(), "");
+        assert_eq!(drid.format_controls(), "(I(5))");
+        let coordinates = FieldDescription {
+            labels: vec!["YCOO", "XCOO"],
+            formats: vec!["R".to_string(), "R".to_string()],
+            repeats_from: Some(0),
+            ..drid
+        };
+        assert_eq!(coordinates.array_descriptor(), "*YCOO!XCOO");
+    }
+
+    #[test]
+    fn test_check_lexical_level() {
+        let ascii = Data::String("Hejsa".to_string());
+        let latin1 = Data::String("Hejså".to_string());
+        let ucs = Data::String("Hejsa ✓".to_string());
+        assert_eq!(check_lexical_level(&TruncEscSeq::LE0, "COMT", &ascii), None);
+        assert_eq!(
+            check_lexical_level(&TruncEscSeq::LE0, "COMT", &latin1),
+            Some(DiagnosticKind::LexicalLevelMismatch {
+                label: "COMT".to_string(),
+                declared: 0
+            })
+        );
+        assert_eq!(
+            check_lexical_level(&TruncEscSeq::LE1, "COMT", &latin1),
+            None
+        );
+        assert!(check_lexical_level(&TruncEscSeq::LE1, "COMT", &ucs).is_some());
+        assert_eq!(check_lexical_level(&TruncEscSeq::LE2, "COMT", &ucs), None);
+    }
+
+    #[test]
+    fn test_parse_ddf_with_printable_graphics() {
+        let ddf = "1600;&-A Catalogue Directory Field&RCNM!RCID&(A(2),I(10))".as_bytes();
+        let actual = parse_ddf(ddf, 9).unwrap();
+        assert_eq!(actual.name, "Catalogue Directory Field");
+        assert_eq!(actual.fic, get_test_field_controls());
+        assert_eq!(
+            actual.foc,
+            vec![
+                ("RCNM".to_string(), ParseData::Fixed(ParseType::String, 2)),
+                ("RCID".to_string(), ParseData::Fixed(ParseType::Integer, 10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_description_controls() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("NAME", b"1000;&   Name\x1fNAME\x1f(A)\x1e"),
+                ("NOTE", b"1600|#-A Note#TEXT#(A)\x1e"),
+            ],
+        );
+        bytes.extend(record_bytes(
+            " D     ",
+            &[("NAME", b"Name\x1f\x1e"), ("NOTE", b"Note\x1f\x1e")],
+        ));
+        let schema = Iso8211Reader::read_schema_only(&bytes[..]).unwrap();
+        let name = schema.field("NAME").unwrap();
+        assert_eq!(name.auxiliary_controls, 0);
+        assert_eq!(
+            name.printable_graphics,
+            PrintableGraphics {
+                field_terminator: ';',
+                unit_terminator: '&',
+            }
+        );
+        let note = schema.field("NOTE").unwrap();
+        assert_eq!(note.name, "Note");
+        assert_eq!(note.labels, vec!["TEXT"]);
+        assert_eq!(note.printable_graphics.field_terminator, '|');
+        assert_eq!(note.printable_graphics.unit_terminator, '#');
+    }
+
+    // A record with the given leader characters from the interchange level through the field
+    // control length, e.g. "3L 1 09", and fields, each ending with its field terminator.
+    fn record_bytes(leader: &str, fields: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut area = Vec::new();
+        for (tag, field) in fields {
+            directory.extend(format!("{}{:03}{:04}", tag, field.len(), area.len()).bytes());
+            area.extend_from_slice(field);
+        }
+        directory.push(RECORD_SEPARATOR);
+        let base = 24 + directory.len();
+        let ftf = fields.first().map_or(4, |(tag, _)| tag.len());
+        let mut bytes =
+            format!("{:05}{}{:05}   340{}", base + area.len(), leader, base, ftf).into_bytes();
+        bytes.extend(directory);
+        bytes.extend(area);
+        bytes
+    }
+
+    #[test]
+    fn test_interchange_levels() {
+        let mut level2 = record_bytes(
+            "2L   06",
+            &[
+                ("0001", b"0100;&Record Identifier\x1e"),
+                ("NAME", b"1000;&Person\x1fFIRST!LAST\x1e"),
+                ("NOTE", b"0000;&Note\x1e"),
+            ],
+        );
+        level2.extend(record_bytes(
+            " D     ",
+            &[
+                ("0001", b"1\x1e"),
+                ("NAME", b"Ada\x1fLovelace\x1f\x1e"),
+                ("NOTE", b"Analyst\x1e"),
+            ],
+        ));
+        let record = Iso8211Reader::new(&level2[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let name = record.get("NAME").unwrap();
+        assert_eq!(name["LAST"], Data::String("Lovelace".to_string()));
+        assert_eq!(
+            record.get("NOTE").unwrap()[DRID],
+            Data::String("Analyst".to_string())
+        );
+
+        let mut level1 = record_bytes("1L     ", &[("TEXT", b"Free text\x1e")]);
+        level1.extend(record_bytes(" D     ", &[("TEXT", b"Hello, world\x1e")]));
+        let record = Iso8211Reader::new(&level1[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.get("TEXT").unwrap()[DRID],
+            Data::String("Hello, world".to_string())
+        );
+        let schema = Iso8211Reader::read_schema_only(&level1[..]).unwrap();
+        assert_eq!(schema.field("TEXT").unwrap().name, "Free text");
+    }
+
+    #[test]
+    fn test_character_set() {
+        let mut bytes = record_bytes("3L   09", &[("NAME", b"1000;&   Name\x1fNAME\x1f(A)\x1e")]);
+        bytes[17..20].copy_from_slice(b"-A ");
+        bytes.extend(record_bytes(" D     ", &[("NAME", b"G\xe4vle\x1f\x1e")]));
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.get("NAME").unwrap()["NAME"],
+            Data::String("G\u{e4}vle".to_string())
+        );
+
+        bytes[17..20].copy_from_slice(b" ! ");
+        assert!(Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_err());
+        let config = ParserConfig {
+            charset: Some(CharacterSet::Latin1),
+            ..Default::default()
+        };
+        let mut catalog = Iso8211Reader::with_config(&bytes[..], &config).unwrap();
+        assert!(catalog.next().unwrap().is_ok());
+
+        // The configured character set wins over the code extension of the leader
+        bytes[7] = b'E';
+        let record = Iso8211Reader::with_config(&bytes[..], &config)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.get("NAME").unwrap()["NAME"],
+            Data::String("G\u{e4}vle".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lexical_level_2_with_code_extension() {
+        let mut bytes = record_bytes(
+            "3LE1 09",
+            &[("NATF", b"1600;&%/ANational\x1fNAME!CODE\x1f(A,A)\x1e")],
+        );
+        bytes.extend(record_bytes(
+            " D     ",
+            &[("NATF", b"A\0b\0\x1f\0C\0\x1f\0\x1e\0")],
+        ));
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let natf = record.get("NATF").unwrap();
+        assert_eq!(natf["NAME"], Data::String("Ab".to_string()));
+        assert_eq!(natf["CODE"], Data::String("C".to_string()));
+    }
+
+    #[test]
+    fn test_field_control_length() {
+        let ddf = b"1600;&   Name\x1fNAME\x1f(A)";
+        assert!(parse_ddf(ddf, 9).is_ok());
+        let err = parse_ddf(ddf, 6).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadFieldControlLength(6)));
+        assert!(parse_ddf(b"1600;&Name\x1fNAME\x1f(A)", 9).is_err());
+        assert!(parse_ddf(b"1600;&Name\x1fNAME\x1f(A)", 6).is_ok());
+        let bytes = record_bytes("3L   12", &[("NAME", b"1600;&   Name\x1fNAME\x1f(A)\x1e")]);
+        assert!(Iso8211Reader::new(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_implicit_point() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[("DPTH", b"1102;&   Depth\x1fVALU\x1f(I(5))\x1e")],
+        );
+        bytes.extend(record_bytes(" D     ", &[("DPTH", b"01234\x1e")]));
+        let schema = Iso8211Reader::read_schema_only(&bytes[..]).unwrap();
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.get("DPTH").unwrap()["VALU"],
+            Data::Float(Some(12.34))
+        );
+        let encoded = schema.encode_record(&record).unwrap();
+        assert!(encoded.ends_with(b"01234\x1e"));
+    }
+
+    #[test]
+    fn test_trailing_padding() {
+        let mut bytes = record_bytes("3L   09", &[("NAME", b"1600;&   Name\x1fNAME\x1f(A)\x1e")]);
+        bytes.extend(record_bytes(" D     ", &[("NAME", b"Ada\x1f\x1e")]));
+        for padding in [&b"\0\0\0"[..], b"\x1a", &[0; 600]] {
+            let mut padded = bytes.clone();
+            padded.extend_from_slice(padding);
+            let mut reader = Iso8211Reader::new(std::io::Cursor::new(padded)).unwrap();
+            assert!(reader.next().unwrap().is_ok());
+            assert!(reader.next().is_none());
+            reader.rewind().unwrap();
+            assert!(reader.next().unwrap().is_ok());
+        }
+        bytes.extend(b"\0\0\0\0\0junk");
+        let records: Vec<_> = Iso8211Reader::new(&bytes[..]).unwrap().collect();
+        assert!(records[1].is_err());
+    }
+
+    #[test]
+    fn test_ddr_base_address() {
+        let mut bytes = record_bytes("3L   09", &[("NAME", b"1600;&   Name\x1fNAME\x1f(A)\x1e")]);
+        assert!(parse_ddr(&mut &bytes[..]).is_ok());
+        bytes[12..17].copy_from_slice(b"00042");
+        let err = parse_ddr(&mut &bytes[..]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadBaseAddress(42, 36)));
+    }
+
+    #[test]
+    fn test_repair_directory() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("NAME", b"1600;&   Name\x1fNAME\x1f(A)\x1e"),
+                ("NOTE", b"1600;&   Note\x1fNOTE\x1f(A)\x1e"),
+            ],
+        );
+        // Let NAME run into NOTE and put NOTE first
+        bytes[28..31].copy_from_slice(b"030");
+        let name: Vec<u8> = bytes[24..35].to_vec();
+        bytes.copy_within(35..46, 24);
+        bytes[35..46].copy_from_slice(&name);
+        bytes.extend(record_bytes(
+            " D     ",
+            &[("NAME", b"Ada\x1f\x1e"), ("NOTE", b"Hi\x1f\x1e")],
+        ));
+
+        let mut reader = Iso8211Reader::new(&bytes[..]).unwrap();
+        let kinds: Vec<_> = reader.diagnostics().iter().map(|d| &d.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                &DiagnosticKind::UnsortedDirectory,
+                &DiagnosticKind::OverlappingField {
+                    declared: 30,
+                    actual: 23
+                }
+            ]
+        );
+        assert_eq!(reader.diagnostics()[1].tag.as_deref(), Some("NAME"));
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(
+            record.get("NAME").unwrap()["NAME"],
+            Data::String("Ada".into())
+        );
+        assert_eq!(
+            record.get("NOTE").unwrap()["NOTE"],
+            Data::String("Hi".into())
+        );
+    }
+
+    #[test]
+    fn test_truncated_record() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("NAME", b"1600;&   Name\x1fNAME\x1f(A)\x1e"),
+                ("NOTE", b"1600;&   Note\x1fNOTE\x1f(A)\x1e"),
+            ],
+        );
+        let dr = record_bytes(
+            " D     ",
+            &[("NAME", b"Ada\x1f\x1e"), ("NOTE", b"Hi\x1f\x1e")],
+        );
+        bytes.extend(&dr);
+        bytes.extend(&dr[..dr.len() - 2]);
+
+        let mut reader = Iso8211Reader::new(&bytes[..]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::TruncatedRecord(1, 2)));
+        assert!(reader.next().is_none());
+
+        let config = ParserConfig {
+            partial_records: true,
+            ..Default::default()
+        };
+        let mut reader = Iso8211Reader::with_config(&bytes[..], &config).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(record.tags().collect::<Vec<_>>(), ["NAME"]);
+        assert!(record.get("NOTE").is_none());
+        assert!(reader.next().is_none());
+        assert_eq!(reader.diagnostics().len(), 1);
+        assert_eq!(reader.diagnostics()[0].record, Some(1));
+        assert_eq!(
+            reader.diagnostics()[0].kind,
+            DiagnosticKind::TruncatedRecord { missing: 2 }
+        );
+    }
+
+    #[test]
+    fn test_tag_sizes() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("000", b"0000;&   \x1f001NAM\x1e"),
+                ("001", b"0100;&   Record Identifier\x1f\x1f(I(2))\x1e"),
+                ("NAM", b"1000;&   Name\x1fNAME\x1f(A)\x1e"),
+            ],
+        );
+        bytes.extend(record_bytes(
+            " D     ",
+            &[("001", b"07\x1e"), ("NAM", b"Ada\x1f\x1e")],
+        ));
+        let schema = Iso8211Reader::read_schema_only(&bytes[..]).unwrap();
+        assert_eq!(schema.tag_pairs().collect::<Vec<_>>(), [("001", "NAM")]);
+        let mut record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.id(), Some(7));
+        record.set_id(8);
+        assert_eq!(record.tags().collect::<Vec<_>>(), ["001", "NAM"]);
+        let encoded = schema.encode_record(&record).unwrap();
+        assert_eq!(encoded[23], b'3');
+    }
+
+    #[test]
+    fn test_repeated_fields() {
+        let ddr = record_bytes(
+            "3L   09",
+            &[
+                ("0001", b"0100;&   Record Identifier\x1f\x1f(I(2))\x1e"),
+                ("NAME", b"1600;&   Name\x1fFIRST!LAST\x1f(2A)\x1e"),
+            ],
+        );
+        let mut bytes = ddr.clone();
+        bytes.extend(record_bytes(
+            " D     ",
+            &[
+                ("0001", b"01\x1e"),
+                ("NAME", b"Ada\x1fLovelace\x1f\x1e"),
+                ("NAME", b"Grace\x1fHopper\x1f\x1e"),
+            ],
+        ));
+        let names = |record: &Record| -> Vec<Vec<String>> {
+            record
+                .fields_with_tag("NAME")
+                .map(|field| {
+                    field
+                        .subfields()
+                        .iter()
+                        .map(|(_, d)| d.to_string())
+                        .collect()
+                })
+                .collect()
+        };
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(names(&record), [["Ada", "Lovelace"], ["Grace", "Hopper"]]);
+        assert_eq!(record.field("NAME").unwrap().tag(), "NAME");
+        assert_eq!(
+            record.fields().map(|f| f.tag()).collect::<Vec<_>>(),
+            ["0001", "NAME", "NAME"]
+        );
+        assert!(record.field("NOTE").is_none());
+
+        let schema = Iso8211Reader::read_schema_only(&ddr[..]).unwrap();
+        let mut encoded = ddr.clone();
+        encoded.extend(schema.encode_record(&record).unwrap());
+        let decoded = Iso8211Reader::new(&encoded[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(names(&decoded), names(&record));
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("0001", b"0100;&   Record Identifier\x1f\x1f(I(2))\x1e"),
+                ("BLOB", b"1000;&   Payload\x1fDATA\x1f(B(24))\x1e"),
+                ("NAME", b"1000;&   Name\x1fNAME\x1f(A)\x1e"),
+            ],
+        );
+        bytes.extend(record_bytes(
+            " D     ",
+            &[
+                ("0001", b"01\x1e"),
+                ("BLOB", b"\x00\x1f\x1e\x1e"),
+                ("NAME", b"Ada\x1e"),
+                ("NAME", b"Bob\x1f\x1e"),
+            ],
+        ));
+        let mut reader = Iso8211Reader::new(&bytes[..]).unwrap();
+        let lazy = reader.next_lazy().unwrap().unwrap();
+        assert_eq!(lazy.raw_bytes("BLOB"), Some(&b"\x00\x1f\x1e"[..]));
+        assert_eq!(lazy.raw_bytes("NOTE"), None);
+        let mut reader = Iso8211Reader::new(&bytes[..]).unwrap();
+        let mut record = reader.next().unwrap().unwrap();
+        let raw = |record: &Record| -> Vec<Option<Vec<u8>>> {
+            record
+                .fields()
+                .map(|f| f.raw_bytes().map(<[u8]>::to_vec))
+                .collect()
+        };
+        assert_eq!(
+            raw(&record),
+            [
+                Some(b"01".to_vec()),
+                Some(b"\x00\x1f\x1e".to_vec()),
+                Some(b"Ada".to_vec()),
+                Some(b"Bob\x1f".to_vec()),
+            ]
+        );
+        record.set("0001", "", Data::Integer(Some(2)));
+        assert_eq!(record.field("0001").unwrap().raw_bytes(), None);
+    }
+
+    #[test]
+    fn test_read_all() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[("0001", b"0100;&   Record Identifier\x1f\x1f(I(2))\x1e")],
+        );
+        for id in [&b"01\x1e"[..], b"02\x1e", b"03\x1e"] {
+            bytes.extend(record_bytes(" D     ", &[("0001", id)]));
+        }
+        let ids = |records: &[Record]| records.iter().map(Record::id).collect::<Vec<_>>();
+        let records = Iso8211Reader::new(&bytes[..]).unwrap().read_all().unwrap();
+        assert_eq!(ids(&records), [Some(1), Some(2), Some(3)]);
+
+        // The second record refers to a field the DDR does not describe
+        let second = bytes.len() - 2 * record_bytes(" D     ", &[("0001", b"01\x1e")]).len();
+        let dir = second + 24;
+        bytes[dir..dir + 4].copy_from_slice(b"NAME");
+        let report = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .read_all()
+            .unwrap_err();
+        assert_eq!(ids(&report.records), [Some(1), Some(3)]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, 1);
+    }
+
+    #[test]
+    fn test_missing_subfields() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("NAME", b"1600;&   Name\x1fFIRST!LAST!AGE\x1f(2A,I)\x1e"),
+                ("NOTE", b"1000;&   Note\x1fNOTE\x1f(A)\x1e"),
+            ],
+        );
+        bytes.extend(record_bytes(
+            " D     ",
+            &[("NAME", b"Ada\x1f\x1f\x1e"), ("NOTE", b"Hi\x1f\x1e")],
+        ));
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let name = record.field("NAME").unwrap();
+        assert_eq!(name.get("FIRST"), Some(&Data::String("Ada".into())));
+        assert_eq!(name.get("LAST"), Some(&Data::String("".into())));
+        assert_eq!(name.get("AGE"), Some(&Data::Missing));
+        assert_eq!(name.get_i64("AGE").unwrap(), None);
+        assert!(name.get_str("AGE").is_err());
+        assert_eq!(record.get_str("NOTE", "NOTE").unwrap(), "Hi");
+    }
+
+    #[test]
+    fn test_conformance_errors() {
+        let ddr = record_bytes(
+            "3L   09",
+            &[("NAME", b"1600;&   Name\x1fFIRST!AGE\x1f(A,I)\x1e")],
+        );
+        let error = |field: &[u8]| {
+            let mut bytes = ddr.clone();
+            bytes.extend(record_bytes(" D     ", &[("NAME", field)]));
+            let err = Iso8211Reader::new(&bytes[..])
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap_err();
+            err.to_string()
+        };
+        assert_eq!(
+            error(b"Ada\x1fold\x1f\x1e"),
+            "Subfield 1 (AGE) of field NAME can not be decoded from 'old\\x1f'"
+        );
+        assert_eq!(
+            error(b"Ada\x1f36\x1fLovelace\x1f\x1e"),
+            "Field NAME has 3 subfields where 2 are described: 'Ada\\x1f36\\x1fLovelace\\x1f'"
+        );
+    }
+
+    #[test]
+    fn test_field_occurrences() {
+        let ddr = record_bytes(
+            "3L   09",
+            &[
+                ("0001", b"0100;&   Record Identifier\x1f\x1f(I(2))\x1e"),
+                (
+                    "FSPT",
+                    b"1600;&   Pointers\x1f*NAME!ORNT\x1f(I(2),A(1))\x1e",
+                ),
+            ],
+        );
+        let mut bytes = ddr.clone();
+        bytes.extend(record_bytes(
+            " D     ",
+            &[
+                ("0001", b"01\x1e"),
+                ("FSPT", b"12F13R\x1e"),
+                ("FSPT", b"14F\x1e"),
+            ],
+        ));
+        let row = |name, ornt: &str| vec![Data::Integer(Some(name)), Data::String(ornt.into())];
+        let check = |record: &Record| {
+            let first = record.field_occurrence("FSPT", 0).unwrap();
+            assert_eq!(first.rows().unwrap(), &[row(12, "F"), row(13, "R")]);
+            let second = record.field_occurrence("FSPT", 1).unwrap();
+            assert_eq!(second.get("NAME"), Some(&Data::Integer(Some(14))));
+            assert_eq!(second.rows().unwrap(), &[row(14, "F")]);
+            assert!(record.field_occurrence("FSPT", 2).is_none());
+            assert!(record.field_occurrence("0001", 0).unwrap().rows().is_none());
+        };
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        check(&record);
+
+        let schema = Iso8211Reader::read_schema_only(&ddr[..]).unwrap();
+        let mut encoded = ddr.clone();
+        encoded.extend(schema.encode_record(&record).unwrap());
+        let decoded = Iso8211Reader::new(&encoded[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        check(&decoded);
+    }
+
+    #[test]
+    fn test_all_subfields() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[
+                ("0001", b"0100;&   Record Identifier\x1f\x1f(I(2))\x1e"),
+                (
+                    "VRPT",
+                    b"1600;&   Pointers\x1fRCID!*NAME!ORNT\x1f(I(2),I(2),A(1))\x1e",
+                ),
+            ],
+        );
+        bytes.extend(record_bytes(
+            " D     ",
+            &[("0001", b"01\x1e"), ("VRPT", b"0712F13R\x1e")],
+        ));
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let rows: Vec<String> = record
+            .all_subfields()
+            .map(|(tag, label, data)| format!("{},{},{}", tag, label, data))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "0001,DRID,1",
+                "VRPT,RCID,7",
+                "VRPT,NAME,12",
+                "VRPT,ORNT,F",
+                "VRPT,NAME,13",
+                "VRPT,ORNT,R",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_repeating_group() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[(
+                "VRPT",
+                b"1600;&   Pointers\x1fRCID!*NAME!ORNT\x1f(I(2),I(2),A(1))\x1e",
+            )],
+        );
+        bytes.extend(record_bytes(" D     ", &[("VRPT", b"0712F13R\x1e")]));
+        let subfield = |label: &str, data| Event::Subfield(label.to_string(), data);
+        let events: Vec<Event> = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .events()
+            .map(|event| event.unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                Event::StartRecord(0),
+                Event::Field("VRPT".to_string()),
+                subfield("RCID", Data::Integer(Some(7))),
+                subfield("NAME", Data::Integer(Some(12))),
+                subfield("ORNT", Data::String("F".into())),
+                subfield("NAME", Data::Integer(Some(13))),
+                subfield("ORNT", Data::String("R".into())),
+                Event::EndRecord,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visitor_repeating_group() {
+        struct Collect(Vec<String>);
+        impl Visitor for Collect {
+            fn subfield(&mut self, _tag: &str, label: &str, data: Data) -> Flow {
+                self.0.push(format!("{}={}", label, data));
+                Flow::Continue
+            }
+            fn end_record(&mut self, index: usize) -> Flow {
+                self.0.push(format!("end {}", index));
+                Flow::Stop
+            }
+        }
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[(
+                "VRPT",
+                b"1600;&   Pointers\x1fRCID!*NAME!ORNT\x1f(I(2),I(2),A(1))\x1e",
+            )],
+        );
+        for _ in 0..2 {
+            bytes.extend(record_bytes(" D     ", &[("VRPT", b"0712F13R\x1e")]));
+        }
+        let mut collect = Collect(Vec::new());
+        Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .parse_with(&mut collect)
+            .unwrap();
+        assert_eq!(
+            collect.0,
+            ["RCID=7", "NAME=12", "ORNT=F", "NAME=13", "ORNT=R", "end 0"]
+        );
+    }
+
+    #[test]
+    fn test_visitor_errors_and_index() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        struct Ends(Vec<usize>);
+        impl Visitor for Ends {
+            fn end_record(&mut self, index: usize) -> Flow {
+                self.0.push(index);
+                Flow::Continue
+            }
+        }
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[("VRPT", b"1600;&   Pointers\x1fRCID!NAME\x1f(I(2),I(2))\x1e")],
+        );
+        for field in [&b"0712\x1e"[..], b"0712\x1e", b"0x12\x1e", b"0713\x1e"] {
+            bytes.extend(record_bytes(" D     ", &[("VRPT", field)]));
+        }
+        let mut reader = Iso8211Reader::new(&bytes[..]).unwrap();
+        reader.next().unwrap().unwrap();
+        let mut ends = Ends(Vec::new());
+        let err = reader.parse_with(&mut ends).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadSubfield(tag, 0, ..) if tag == "VRPT"));
+        assert_eq!(ends.0, [1]);
+
+        let mut reader = Iso8211Reader::new(&bytes[..]).unwrap();
+        let skipped = Rc::new(RefCell::new(0));
+        let counter = skipped.clone();
+        reader.on_error(move |_, _| {
+            *counter.borrow_mut() += 1;
+            ErrorAction::Continue
+        });
+        let mut ends = Ends(Vec::new());
+        reader.parse_with(&mut ends).unwrap();
+        assert_eq!(ends.0, [0, 1, 3]);
+        assert_eq!(*skipped.borrow(), 1);
+    }
+
+    #[test]
+    fn test_reused_leader() {
+        let mut bytes = record_bytes(
+            "3L   09",
+            &[("NAME", b"1000;&   Name\x1fNAME\x1f(A(3))\x1e")],
+        );
+        bytes.extend(record_bytes(" R     ", &[("NAME", b"Ada\x1e")]));
+        bytes.extend(b"Bob\x1eEve\x1e");
+        let names: Vec<Data> = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .map(|r| r.unwrap().get("NAME").unwrap()["NAME"].clone())
+            .collect();
+        assert_eq!(
+            names,
+            ["Ada", "Bob", "Eve"].map(|name| Data::String(name.to_string()))
+        );
+        let mut reader = Iso8211Reader::new(std::io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(reader.scan_records().unwrap(), 3);
+        assert_eq!(reader.records().size_hint(), (3, Some(3)));
+        for n in [2, 0, 1] {
+            let record = reader.record(n).unwrap().unwrap();
+            assert_eq!(record.get("NAME").unwrap()["NAME"], names[n]);
+        }
+        assert_eq!(
+            reader.next().unwrap().unwrap().get("NAME").unwrap()["NAME"],
+            names[2]
+        );
+        assert!(reader.record(3).unwrap().is_none());
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        let last = Iso8211Reader::new(&truncated[..]).unwrap().nth(2).unwrap();
+        assert!(last.is_err());
+    }
+
+    #[test]
+    fn test_repeating_groups() {
+        use crate::test_support::FileBuilder;
+        let coordinates = vec![
+            vec![Data::Integer(Some(1)), Data::Integer(Some(2))],
+            vec![Data::Integer(Some(3)), Data::Integer(Some(4))],
+            vec![Data::Integer(Some(5)), Data::Integer(Some(6))],
+        ];
+        let mut record = Record::new();
+        record.set_id(1);
+        record.insert_rows("SG2D", &["YCOO", "XCOO"], coordinates.clone());
+        let bytes = FileBuilder::new()
+            .repeating_field("SG2D", "2-D Coordinate Field", "YCOO!XCOO", "(2I(3))")
+            .raw_record(record)
+            .build();
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.rows("SG2D"), Some(&coordinates));
+        assert_eq!(record.get("SG2D").unwrap()["XCOO"], Data::Integer(Some(2)));
+        assert_eq!(record.rows("0001"), None);
+
+        let pointers = vec![
+            vec![Data::Integer(Some(12)), Data::String("F".to_string())],
+            vec![Data::Integer(Some(13)), Data::String("R".to_string())],
+        ];
+        let mut record = Record::new();
+        record.set_id(1);
+        record.insert_rows("VRPT", &["NAME", "ORNT"], pointers.clone());
+        let bytes = FileBuilder::new()
+            .field(
+                "VRPT",
+                "Vector Record Pointer Field",
+                "*NAME!ORNT",
+                "(I(2),A(1))",
+            )
+            .raw_record(record)
+            .build();
+        let schema = Iso8211Reader::read_schema_only(&bytes[..]).unwrap();
+        let vrpt = schema.field("VRPT").unwrap();
+        assert_eq!(vrpt.array_descriptor(), "*NAME!ORNT");
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.rows("VRPT"), Some(&pointers));
+
+        let table = vec![
+            vec![Data::Integer(Some(10)), Data::Integer(Some(12))],
+            vec![Data::Integer(Some(4)), Data::Integer(Some(7))],
+        ];
+        let mut record = Record::new();
+        record.set_id(1);
+        record.insert_rows("MEAN", &["JAN", "FEB"], table.clone());
+        let bytes = FileBuilder::new()
+            .field("MEAN", "Monthly Means", "DEPTH!TEMP\\\\JAN!FEB", "(2I(2))")
+            .raw_record(record)
+            .build();
+        let schema = Iso8211Reader::read_schema_only(&bytes[..]).unwrap();
+        let mean = schema.field("MEAN").unwrap();
+        assert_eq!(mean.row_labels, ["DEPTH", "TEMP"]);
+        assert_eq!(mean.array_descriptor(), "DEPTH!TEMP\\\\JAN!FEB");
+        let record = Iso8211Reader::new(&bytes[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.rows("MEAN"), Some(&table));
+    }
+
+    #[test]
+    fn test_parse_file_control_field() {
+        let field = "0000;&   Title\x1f0001CATDCATDCATX".as_bytes();
+        let actual = parse_file_control_field(field, 4, 9).unwrap();
+        assert_eq!(actual.title, "Title");
+        assert_eq!(
+            actual.pairs,
+            vec![
+                ("0001".to_string(), "CATD".to_string()),
+                ("CATD".to_string(), "CATX".to_string())
+            ]
+        );
+        assert!(parse_file_control_field("0000;&   \x1f0001CAT".as_bytes(), 4, 9).is_err());
+    }
+}
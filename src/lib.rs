@@ -1,4 +1,45 @@
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod agency;
+pub mod apply;
+pub mod attributes;
 pub mod catalog;
+pub mod catd;
+pub mod cell_name;
+pub mod coverage;
+pub mod dataset;
+pub mod diagnostics;
+pub mod dsid;
+pub mod dspm;
 pub mod error;
+pub mod events;
+pub mod exchange_set;
+pub mod feature;
+pub mod geometry;
+pub mod history;
+pub mod iso8211;
+pub mod meta;
+pub mod model;
+pub mod national;
+pub mod objects;
+pub mod quality;
+pub mod raw;
+pub mod references;
+pub mod scale;
+pub mod skin;
+pub mod state;
+pub mod summary;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod units;
+pub mod updates;
+pub mod validation;
+pub mod vector;
+pub mod visitor;
+pub mod volume;
+pub mod writer;
+
+pub use crate::data_parser::Data;
 
+mod charset;
 mod data_parser;
@@ -0,0 +1,144 @@
+//! Extraction of the meta features of a cell, such as the data quality (M_QUAL), buoyage system
+//! (M_NSYS) and nautical publication (M_NPUB) zones, with their attributes and polygons.
+use crate::catalog::Result;
+use crate::dataset::DataSet;
+use crate::feature::FeatureRecord;
+use crate::geometry::Ring;
+
+/// The meta object classes, by OBJL code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetaClass {
+    Accuracy = 300,              // M_ACCY
+    CompilationScale = 301,      // M_CSCL
+    Coverage = 302,              // M_COVR
+    HorizontalDatum = 303,       // M_HDAT
+    HorizontalDatumShift = 304,  // M_HOPA
+    NauticalPublication = 305,   // M_NPUB
+    NavigationalSystem = 306,    // M_NSYS
+    ProductionInformation = 307, // M_PROD
+    Quality = 308,               // M_QUAL
+    SoundingDatum = 309,         // M_SDAT
+    SurveyReliability = 310,     // M_SREL
+    Units = 311,                 // M_UNIT
+    VerticalDatum = 312,         // M_VDAT
+}
+
+impl MetaClass {
+    pub fn from_code(objl: u16) -> Option<MetaClass> {
+        use MetaClass::*;
+        [
+            Accuracy,
+            CompilationScale,
+            Coverage,
+            HorizontalDatum,
+            HorizontalDatumShift,
+            NauticalPublication,
+            NavigationalSystem,
+            ProductionInformation,
+            Quality,
+            SoundingDatum,
+            SurveyReliability,
+            Units,
+            VerticalDatum,
+        ]
+        .iter()
+        .copied()
+        .find(|class| *class as u16 == objl)
+    }
+}
+
+/// A pointer from a feature to a spatial record, one entry of its FSPT field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpatialPointer {
+    pub rcnm: u8,        // Record name of the spatial record, 130 for an edge
+    pub rcid: u32,       // Record id of the spatial record
+    pub orientation: u8, // ORNT, 1 forward, 2 reverse, 255 null
+    pub usage: u8,       // USAG, 1 exterior, 2 interior, 3 exterior truncated by the cell limit
+    pub mask: u8,        // MASK, 1 mask, 2 show, 255 null
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaFeature {
+    pub class: MetaClass,
+    pub rcid: u32,                      // RCID of the feature record
+    pub attributes: Vec<(u16, String)>, // ATTL and ATVL of every ATTF entry
+    pub rings: Vec<Ring>,               // The polygon of the feature, empty if it has none
+}
+
+impl MetaFeature {
+    pub fn attribute(&self, attl: u16) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(code, _)| *code == attl)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The meta feature of `feature`, with its edges assembled into rings, None if it is not one.
+pub fn meta_feature(data_set: &DataSet, feature: &FeatureRecord) -> Result<Option<MetaFeature>> {
+    let class = match MetaClass::from_code(feature.objl) {
+        Some(class) => class,
+        None => return Ok(None),
+    };
+    Ok(Some(MetaFeature {
+        class,
+        rcid: feature.rcid,
+        attributes: feature.attributes.clone(),
+        rings: data_set.rings(feature)?,
+    }))
+}
+
+impl DataSet {
+    /// Every meta feature of the cell, in record name order.
+    pub fn meta_features(&self) -> Result<Vec<MetaFeature>> {
+        let mut features = Vec::new();
+        for feature in self.feature_records() {
+            if let Some(meta) = meta_feature(self, &feature?)? {
+                features.push(meta);
+            }
+        }
+        Ok(features)
+    }
+}
+
+/// The meta features of the class `class` among `features`.
+pub fn of_class(features: &[MetaFeature], class: MetaClass) -> impl Iterator<Item = &MetaFeature> {
+    features.iter().filter(move |f| f.class == class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_cell;
+
+    #[test]
+    fn meta_features() {
+        assert_eq!(MetaClass::from_code(308), Some(MetaClass::Quality));
+        assert_eq!(MetaClass::from_code(42), None);
+        let data_set = DataSet::read(&sample_cell()[..]).unwrap();
+        let features = data_set.meta_features().unwrap();
+        let classes: Vec<_> = features.iter().map(|f| (f.class, f.rcid)).collect();
+        assert_eq!(
+            classes,
+            vec![(MetaClass::Coverage, 4), (MetaClass::Quality, 5)]
+        );
+        let quality: Vec<_> = of_class(&features, MetaClass::Quality).collect();
+        assert_eq!(quality.len(), 1);
+        assert_eq!(quality[0].attribute(72), Some("4"));
+        // The whole cell, counter-clockwise from the south west
+        assert_eq!(quality[0].rings.len(), 1);
+        assert_eq!(
+            quality[0].rings[0].coordinates,
+            vec![
+                (51.0, 0.0),
+                (51.0, 0.5),
+                (51.0, 1.0),
+                (52.0, 1.0),
+                (52.0, 0.5),
+                (52.0, 0.0),
+                (51.0, 0.0),
+            ]
+        );
+        assert_eq!(features[0].attribute(18), Some("1"));
+    }
+}
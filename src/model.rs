@@ -0,0 +1,66 @@
+//! An in-memory model of a whole file that can be edited and written back. Records are changed
+//! through [`Record::set`], [`Record::insert_field`] and [`Record::remove_field`], and added or
+//! removed through [`Document::records`].
+use crate::catalog::{Catalog, ParserConfig, Record, Result, Schema};
+use crate::iso8211::read_record;
+use crate::writer::Writer;
+use std::io::{Read, Write};
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    ddr: Vec<u8>, // The Data Descriptive Record as read
+    pub records: Vec<Record>,
+}
+
+impl Document {
+    /// Reads every record of `rdr`.
+    pub fn read<R: Read>(rdr: R) -> Result<Document> {
+        Document::read_with_config(rdr, &ParserConfig::default())
+    }
+
+    /// Like [`Document::read`], with the options of `config`. A memory budget covers the DDR
+    /// and the Data Records together.
+    pub fn read_with_config<R: Read>(mut rdr: R, config: &ParserConfig) -> Result<Document> {
+        let ddr = read_record(&mut rdr, config.memory_budget)?;
+        let config = ParserConfig {
+            memory_budget: config.memory_budget.map(|budget| budget - ddr.len() as u64),
+            ..config.clone()
+        };
+        let records = Catalog::with_config((&ddr[..]).chain(rdr), &config)?
+            .collect::<Result<Vec<Record>>>()?;
+        Ok(Document { ddr, records })
+    }
+
+    /// The schema declared by the DDR.
+    pub fn schema(&self) -> Result<Schema> {
+        Catalog::read_schema_only(&self.ddr[..])
+    }
+
+    /// Keeps only the records for which `keep` returns true and renumbers the record
+    /// identifiers of the rest from 1. Returns the number of records removed.
+    pub fn retain<F: FnMut(&Record) -> bool>(&mut self, keep: F) -> usize {
+        let before = self.records.len();
+        self.records.retain(keep);
+        self.renumber();
+        before - self.records.len()
+    }
+
+    /// Sets the record identifiers to 1, 2, 3... in the order of the records. Records without
+    /// a record identifier field are left alone.
+    pub fn renumber(&mut self) {
+        for (i, record) in self.records.iter_mut().enumerate() {
+            if record.id().is_some() {
+                record.set_id(i as i64 + 1);
+            }
+        }
+    }
+
+    /// Writes the DDR, unchanged, followed by every record.
+    pub fn write<W: Write>(&self, out: W) -> Result<W> {
+        let mut writer = Writer::new(out, &self.ddr)?;
+        for record in &self.records {
+            writer.write_record(record)?;
+        }
+        Ok(writer.into_inner())
+    }
+}
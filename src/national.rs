@@ -0,0 +1,166 @@
+//! Selection between the international (ATTF) and national (NATF) text of a feature. Text
+//! attributes such as OBJNAM have a national counterpart such as NOBJNM, which is in the
+//! national language and may use lexical level 2 (UCS-2). An application picks what it prefers
+//! and what it can display, falling back to the other when that is missing.
+use crate::catalog::Record;
+use crate::data_parser::Data;
+use crate::dataset::rows;
+
+/// Pairs of international and national text attributes, as (ATTF ATTL, NATF ATTL).
+pub const TEXT_ATTRIBUTES: &[(u16, u16)] = &[
+    (102, 300), // INFORM, NINFOM
+    (116, 301), // OBJNAM, NOBJNM
+];
+
+/// Which text to show when a feature has both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguagePreference {
+    pub national: bool, // Prefer the national text over the international one
+    pub max_level: u8,  // Highest lexical level the application can display
+}
+
+impl Default for LanguagePreference {
+    fn default() -> LanguagePreference {
+        LanguagePreference {
+            national: true,
+            max_level: 2,
+        }
+    }
+}
+
+/// The lowest lexical level able to represent `s`: 0 for ASCII, 1 for ISO 8859-1 and 2 for
+/// anything else.
+pub fn lexical_level(s: &str) -> u8 {
+    if s.is_ascii() {
+        0
+    } else if s.chars().all(|c| (c as u32) <= 0xff) {
+        1
+    } else {
+        2
+    }
+}
+
+/// Picks between the international text `international` and the national text `national`
+/// according to `pref`. Text above `pref.max_level` is only used when there is nothing else.
+pub fn select_text<'a>(
+    international: Option<&'a str>,
+    national: Option<&'a str>,
+    pref: LanguagePreference,
+) -> Option<&'a str> {
+    let (first, second) = if pref.national {
+        (national, international)
+    } else {
+        (international, national)
+    };
+    let candidates = [first, second];
+    let present = candidates.iter().flatten().filter(|s| !s.is_empty());
+    present
+        .clone()
+        .find(|s| lexical_level(s) <= pref.max_level)
+        .or_else(|| present.clone().next())
+        .copied()
+}
+
+// The ATVL of the entry of the attribute fields tag whose ATTL is attl.
+fn attribute_value<'a>(record: &'a Record, tag: &str, attl: u16) -> Option<&'a str> {
+    rows(record, tag, &["ATTL", "ATVL"])
+        .ok()?
+        .into_iter()
+        .find_map(|row| match (row[0], row[1]) {
+            (Data::Integer(Some(code)), Data::String(value)) if *code == i64::from(attl) => {
+                Some(value.as_str())
+            }
+            _ => None,
+        })
+}
+
+impl Record {
+    /// The value of the attribute `attl` of a feature, from its ATTF field.
+    pub fn attribute(&self, attl: u16) -> Option<&str> {
+        attribute_value(self, "ATTF", attl)
+    }
+
+    /// The value of the national attribute `attl` of a feature, from its NATF field.
+    pub fn national_attribute(&self, attl: u16) -> Option<&str> {
+        attribute_value(self, "NATF", attl)
+    }
+
+    /// The text of the international attribute `attl`, e.g. 116 for OBJNAM, or of its national
+    /// counterpart from [`TEXT_ATTRIBUTES`], whichever `pref` selects.
+    pub fn text(&self, attl: u16, pref: LanguagePreference) -> Option<&str> {
+        let national = TEXT_ATTRIBUTES
+            .iter()
+            .find(|(international, _)| *international == attl)
+            .and_then(|(_, national)| self.national_attribute(*national));
+        select_text(self.attribute(attl), national, pref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select() {
+        let pref = LanguagePreference::default();
+        assert_eq!(
+            select_text(Some("Gothenburg"), Some("Göteborg"), pref),
+            Some("Göteborg")
+        );
+        assert_eq!(
+            select_text(Some("Gothenburg"), Some(""), pref),
+            Some("Gothenburg")
+        );
+        let latin1 = LanguagePreference {
+            national: true,
+            max_level: 1,
+        };
+        assert_eq!(
+            select_text(Some("Athens"), Some("Αθήνα"), latin1),
+            Some("Athens")
+        );
+        assert_eq!(select_text(None, Some("Αθήνα"), latin1), Some("Αθήνα"));
+        let international = LanguagePreference {
+            national: false,
+            ..pref
+        };
+        assert_eq!(
+            select_text(Some("Gothenburg"), Some("Göteborg"), international),
+            Some("Gothenburg")
+        );
+        assert_eq!(select_text(None, None, pref), None);
+    }
+
+    #[test]
+    fn attributes_after_the_first() {
+        let string = |s: &str| Data::String(s.to_string());
+        let mut record = Record::new();
+        record.insert_rows(
+            "ATTF",
+            &["ATTL", "ATVL"],
+            vec![
+                vec![Data::Integer(Some(87)), string("5")],
+                vec![Data::Integer(Some(116)), string("Gothenburg")],
+            ],
+        );
+        record.insert_rows(
+            "NATF",
+            &["ATTL", "ATVL"],
+            vec![
+                vec![Data::Integer(Some(300)), string("Hamn")],
+                vec![Data::Integer(Some(301)), string("Göteborg")],
+            ],
+        );
+        assert_eq!(record.attribute(87), Some("5"));
+        assert_eq!(record.attribute(116), Some("Gothenburg"));
+        assert_eq!(record.attribute(179), None);
+        assert_eq!(record.national_attribute(301), Some("Göteborg"));
+        let pref = LanguagePreference::default();
+        assert_eq!(record.text(116, pref), Some("Göteborg"));
+        let international = LanguagePreference {
+            national: false,
+            ..pref
+        };
+        assert_eq!(record.text(116, international), Some("Gothenburg"));
+    }
+}
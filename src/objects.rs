@@ -0,0 +1,209 @@
+//! Lookup of object classes. The OBJL subfield of FRID is a numeric code from the S-57 object
+//! catalogue, this maps it to the acronym (e.g. "DEPARE"), the name and the attributes the
+//! class may have.
+//!
+//! The catalogue is loaded from a CSV in the layout of the widely distributed
+//! `s57objectclasses.csv`. With the `objects` feature the whole table, attribute sets and
+//! primitives included, is embedded in the crate.
+use crate::catalog::Result;
+use crate::error::ErrorKind;
+use crate::feature::Primitive;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+#[cfg(feature = "objects")]
+use lazy_static::lazy_static;
+
+/// The kind of an object class, from the Class column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    Geo,          // G
+    Meta,         // M
+    Collection,   // C
+    Cartographic, // $
+}
+
+/// A class of the S-57 object catalogue. `attributes` is None when the catalogue leaves the
+/// attribute columns of the class empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectClass {
+    pub code: u16,                       // OBJL
+    pub acronym: String,                 // e.g. "DEPARE"
+    pub name: String,                    // e.g. "Depth area"
+    pub kind: ObjectKind,                // Class
+    pub attributes: Option<Vec<String>>, // Acronyms of the attributes of sets A, B and C
+    pub primitives: Vec<Primitive>,      // The primitives features of the class may have
+}
+
+impl ObjectClass {
+    /// Whether the class may have the attribute `acronym`, or None if the catalogue does not list
+    /// the attributes of the class.
+    pub fn allows(&self, acronym: &str) -> Option<bool> {
+        let attributes = self.attributes.as_ref()?;
+        Some(attributes.iter().any(|a| a == acronym))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ObjectCatalog(HashMap<u16, ObjectClass>);
+
+#[cfg(feature = "objects")]
+lazy_static! {
+    static ref BUILTIN: ObjectCatalog =
+        ObjectCatalog::from_csv(&include_bytes!("../data/objects.csv")[..]).unwrap();
+}
+
+// The columns of a CSV line. Columns may be quoted, with "" for a quote within one.
+pub(crate) fn split_csv(line: &str) -> Vec<String> {
+    let mut columns = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                columns.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => columns.push(String::new()),
+            c => columns.last_mut().unwrap().push(c),
+        }
+    }
+    columns
+}
+
+// A list column such as "DRVAL1;DRVAL2;", without the empty entries.
+pub(crate) fn split_list(column: &str) -> impl Iterator<Item = &str> {
+    column.split(';').map(str::trim).filter(|s| !s.is_empty())
+}
+
+impl ObjectCatalog {
+    /// Reads a catalogue from CSV with the columns Code, ObjectClass, Acronym, Attribute_A,
+    /// Attribute_B, Attribute_C, Class and Primitives. The header line, empty lines and lines
+    /// starting with '#' are skipped.
+    pub fn from_csv<R: BufRead>(rdr: R) -> Result<ObjectCatalog> {
+        let mut catalog = HashMap::new();
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line.map_err(|err| ErrorKind::IOError(err.kind()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || ErrorKind::BadObjectCatalog(i + 1);
+            let columns = split_csv(line);
+            if columns[0] == "Code" {
+                continue;
+            }
+            if columns.len() < 8 {
+                return Err(bad().into());
+            }
+            let kind = match columns[6].trim() {
+                "G" => ObjectKind::Geo,
+                "M" => ObjectKind::Meta,
+                "C" => ObjectKind::Collection,
+                "$" => ObjectKind::Cartographic,
+                _ => return Err(bad().into()),
+            };
+            let primitives = split_list(&columns[7])
+                .map(|primitive| match primitive {
+                    "Point" => Ok(Primitive::Point),
+                    "Line" => Ok(Primitive::Line),
+                    "Area" => Ok(Primitive::Area),
+                    _ => Err(bad()),
+                })
+                .collect::<std::result::Result<Vec<Primitive>, ErrorKind>>()?;
+            // A class without any attribute has its attributes left out of the catalogue
+            let attributes: Vec<String> = columns[3..6]
+                .iter()
+                .flat_map(|set| split_list(set))
+                .map(String::from)
+                .collect();
+            let class = ObjectClass {
+                code: columns[0].trim().parse().map_err(|_| bad())?,
+                acronym: columns[2].trim().to_string(),
+                name: columns[1].trim().to_string(),
+                kind,
+                attributes: Some(attributes).filter(|attributes| !attributes.is_empty()),
+                primitives,
+            };
+            catalog.insert(class.code, class);
+        }
+        Ok(ObjectCatalog(catalog))
+    }
+
+    /// The catalogue embedded with the `objects` feature.
+    #[cfg(feature = "objects")]
+    pub fn builtin() -> &'static ObjectCatalog {
+        &BUILTIN
+    }
+
+    pub fn get(&self, objl: u16) -> Option<&ObjectClass> {
+        self.0.get(&objl)
+    }
+
+    /// Every class of the catalogue, in no particular order.
+    pub fn classes(&self) -> impl Iterator<Item = &ObjectClass> {
+        self.0.values()
+    }
+
+    pub fn by_acronym(&self, acronym: &str) -> Option<&ObjectClass> {
+        self.0.values().find(|class| class.acronym == acronym)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_csv() {
+        let csv = concat!(
+            "\"Code\",\"ObjectClass\",\"Acronym\",\"Attribute_A\",\"Attribute_B\",",
+            "\"Attribute_C\",\"Class\",\"Primitives\"\n",
+            "42,Depth area,DEPARE,DRVAL1;DRVAL2;,INFORM;,SORDAT;,G,Line;Area;\n",
+            "5,\"Beacon, cardinal\",BCNCAR,BCNSHP;,,,G,Point;\n",
+        );
+        let catalog = ObjectCatalog::from_csv(csv.as_bytes()).unwrap();
+        let depare = catalog.get(42).unwrap();
+        assert_eq!(depare.acronym, "DEPARE");
+        assert_eq!(
+            depare.attributes.as_ref().unwrap(),
+            &["DRVAL1", "DRVAL2", "INFORM", "SORDAT"]
+        );
+        assert_eq!(depare.allows("DRVAL2"), Some(true));
+        assert_eq!(depare.allows("VALSOU"), Some(false));
+        assert_eq!(depare.primitives, vec![Primitive::Line, Primitive::Area]);
+        assert_eq!(
+            catalog.by_acronym("BCNCAR").unwrap().name,
+            "Beacon, cardinal"
+        );
+        assert!(ObjectCatalog::from_csv("42,Depth area,DEPARE".as_bytes()).is_err());
+        assert!(ObjectCatalog::from_csv("42,Depth area,DEPARE,,,,X,".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn csv_quotes() {
+        assert_eq!(
+            split_csv("a,\"b, \"\"c\"\"\",d"),
+            vec!["a", "b, \"c\"", "d"]
+        );
+        assert_eq!(split_csv(""), vec![""]);
+    }
+
+    #[cfg(feature = "objects")]
+    #[test]
+    fn builtin() {
+        let catalog = ObjectCatalog::builtin();
+        assert_eq!(catalog.get(42).unwrap().acronym, "DEPARE");
+        assert_eq!(catalog.by_acronym("M_QUAL").unwrap().kind, ObjectKind::Meta);
+        assert_eq!(catalog.get(504).unwrap().kind, ObjectKind::Cartographic);
+        let depare = catalog.get(42).unwrap();
+        assert_eq!(depare.allows("DRVAL1"), Some(true));
+        assert_eq!(depare.allows("VALSOU"), Some(false));
+        assert_eq!(depare.primitives, vec![Primitive::Line, Primitive::Area]);
+        let uwtroc = catalog.by_acronym("UWTROC").unwrap();
+        assert_eq!(uwtroc.allows("VALSOU"), Some(true));
+        assert_eq!(uwtroc.primitives, vec![Primitive::Point]);
+        assert!(catalog.get(402).unwrap().primitives.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+//! Data quality attributes: the positional accuracy (POSACC) and quality of position (QUAPOS) of
+//! features and spatial records, and the zone of confidence (CATZOC) of the M_QUAL meta features
+//! covering them.
+use crate::catalog::Record;
+use std::convert::TryFrom;
+
+pub const CATZOC: u16 = 72;
+pub const POSACC: u16 = 401;
+pub const QUAPOS: u16 = 402;
+
+/// QUAPOS, the quality of position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityOfPosition {
+    Surveyed = 1,
+    Unsurveyed = 2,
+    InadequatelySurveyed = 3,
+    Approximated = 4,
+    PositionDoubtful = 5,
+    Unreliable = 6,
+    ReportedNotSurveyed = 7,
+    ReportedNotConfirmed = 8,
+    Estimated = 9,
+    PreciselyKnown = 10,
+    Calculated = 11,
+}
+
+/// CATZOC, the category of zone of confidence in data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ZoneOfConfidence {
+    A1 = 1,
+    A2 = 2,
+    B = 3,
+    C = 4,
+    D = 5,
+    U = 6, // Not assessed
+}
+
+impl TryFrom<i64> for QualityOfPosition {
+    type Error = i64;
+    fn try_from(code: i64) -> std::result::Result<QualityOfPosition, i64> {
+        use QualityOfPosition::*;
+        [
+            Surveyed,
+            Unsurveyed,
+            InadequatelySurveyed,
+            Approximated,
+            PositionDoubtful,
+            Unreliable,
+            ReportedNotSurveyed,
+            ReportedNotConfirmed,
+            Estimated,
+            PreciselyKnown,
+            Calculated,
+        ]
+        .iter()
+        .copied()
+        .find(|q| *q as i64 == code)
+        .ok_or(code)
+    }
+}
+
+impl TryFrom<i64> for ZoneOfConfidence {
+    type Error = i64;
+    fn try_from(code: i64) -> std::result::Result<ZoneOfConfidence, i64> {
+        use ZoneOfConfidence::*;
+        [A1, A2, B, C, D, U]
+            .iter()
+            .copied()
+            .find(|z| *z as i64 == code)
+            .ok_or(code)
+    }
+}
+
+impl QualityOfPosition {
+    /// Whether the position is reliable enough to navigate by, i.e. surveyed, precisely known
+    /// or calculated.
+    pub fn is_reliable(self) -> bool {
+        matches!(
+            self,
+            QualityOfPosition::Surveyed
+                | QualityOfPosition::PreciselyKnown
+                | QualityOfPosition::Calculated
+        )
+    }
+}
+
+impl ZoneOfConfidence {
+    /// The position accuracy in metres the zone guarantees, None for D and U.
+    pub fn position_accuracy(self) -> Option<f64> {
+        match self {
+            ZoneOfConfidence::A1 => Some(5.0),
+            ZoneOfConfidence::A2 => Some(20.0),
+            ZoneOfConfidence::B => Some(50.0),
+            ZoneOfConfidence::C => Some(500.0),
+            ZoneOfConfidence::D | ZoneOfConfidence::U => None,
+        }
+    }
+}
+
+/// The quality attributes of a feature. Any of them may be missing.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quality {
+    pub accuracy: Option<f64>, // POSACC, in the positional units of the data set
+    pub position: Option<QualityOfPosition>,
+    pub zone: Option<ZoneOfConfidence>,
+}
+
+impl Quality {
+    /// Fills in what is missing from `fallback`, typically the quality of the M_QUAL feature
+    /// covering this one.
+    pub fn or(self, fallback: Quality) -> Quality {
+        Quality {
+            accuracy: self.accuracy.or(fallback.accuracy),
+            position: self.position.or(fallback.position),
+            zone: self.zone.or(fallback.zone),
+        }
+    }
+
+    /// The best known accuracy in metres, from POSACC or else from the zone of confidence.
+    /// POSACC is assumed to be in metres.
+    pub fn accuracy_in_metres(&self) -> Option<f64> {
+        self.accuracy
+            .or_else(|| self.zone.and_then(ZoneOfConfidence::position_accuracy))
+    }
+}
+
+impl Record {
+    /// The quality attributes of the feature, from any entry of its ATTF field.
+    pub fn quality(&self) -> Quality {
+        let code = |attl| self.attribute(attl)?.trim().parse::<i64>().ok();
+        Quality {
+            accuracy: self
+                .attribute(POSACC)
+                .and_then(|value| value.trim().parse().ok()),
+            position: code(QUAPOS).and_then(|c| QualityOfPosition::try_from(c).ok()),
+            zone: code(CATZOC).and_then(|c| ZoneOfConfidence::try_from(c).ok()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_parser::Data;
+
+    #[test]
+    fn codes() {
+        assert_eq!(
+            QualityOfPosition::try_from(4),
+            Ok(QualityOfPosition::Approximated)
+        );
+        assert_eq!(QualityOfPosition::try_from(12), Err(12));
+        assert!(QualityOfPosition::PreciselyKnown.is_reliable());
+        assert_eq!(ZoneOfConfidence::try_from(3), Ok(ZoneOfConfidence::B));
+        assert!(ZoneOfConfidence::A1 < ZoneOfConfidence::C);
+    }
+
+    #[test]
+    fn fallback() {
+        let feature = Quality {
+            position: Some(QualityOfPosition::Surveyed),
+            ..Quality::default()
+        };
+        let zone = Quality {
+            zone: Some(ZoneOfConfidence::A2),
+            position: Some(QualityOfPosition::Estimated),
+            ..Quality::default()
+        };
+        let quality = feature.or(zone);
+        assert_eq!(quality.position, Some(QualityOfPosition::Surveyed));
+        assert_eq!(quality.accuracy_in_metres(), Some(20.0));
+        assert_eq!(Quality::default().accuracy_in_metres(), None);
+    }
+
+    #[test]
+    fn from_record() {
+        let entry = |attl, atvl: &str| vec![Data::Integer(Some(attl)), Data::String(atvl.into())];
+        let mut record = Record::new();
+        record.insert_rows(
+            "ATTF",
+            &["ATTL", "ATVL"],
+            vec![
+                entry(116, "Rock"),
+                entry(402, "4"),
+                entry(72, "3"),
+                entry(401, "2.5"),
+            ],
+        );
+        let quality = record.quality();
+        assert_eq!(quality.position, Some(QualityOfPosition::Approximated));
+        assert_eq!(quality.zone, Some(ZoneOfConfidence::B));
+        assert_eq!(quality.accuracy, Some(2.5));
+        assert_eq!(Record::new().quality(), Quality::default());
+    }
+}
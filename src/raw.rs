@@ -0,0 +1,120 @@
+//! Access to records as the bytes they are stored as, together with where the leader, directory
+//! entries and fields are within them. Useful for debugging producer issues at the byte level
+//! and for copying records out untouched.
+use crate::error::ErrorKind;
+use crate::iso8211::{parse_record_layout, read_record, Result};
+use failure::ResultExt;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawField {
+    pub tag: String,
+    pub entry: Range<usize>, // The directory entry of the field
+    pub data: Range<usize>,  // The field, including its field terminator
+}
+
+/// A record as stored in the file. All ranges are indices into `bytes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawRecord {
+    pub bytes: Vec<u8>, // The record, from the leader through the last field terminator
+    pub field_area: usize, // Start of the field area, just after the directory terminator
+    pub fields: Vec<RawField>,
+}
+
+impl RawRecord {
+    pub(crate) fn from_bytes(bytes: Vec<u8>) -> Result<RawRecord> {
+        let (leader, dirs, field_area) = parse_record_layout(&bytes)?;
+        let entry_size = leader.entry_size();
+        let fields = dirs
+            .into_iter()
+            .enumerate()
+            .map(|(i, dir)| {
+                let start = field_area + dir.offset;
+                if start + dir.length > bytes.len() {
+                    return Err(ErrorKind::BadDirectoryData.into());
+                }
+                Ok(RawField {
+                    tag: dir.id,
+                    entry: 24 + i * entry_size..24 + (i + 1) * entry_size,
+                    data: start..start + dir.length,
+                })
+            })
+            .collect::<Result<Vec<RawField>>>()?;
+        Ok(RawRecord {
+            bytes,
+            field_area,
+            fields,
+        })
+    }
+
+    pub fn has_field(&self, tag: &str) -> bool {
+        self.fields.iter().any(|f| f.tag == tag)
+    }
+
+    /// The bytes of the first field with the tag `tag`, including the field terminator.
+    pub fn field_bytes(&self, tag: &str) -> Option<&[u8]> {
+        self.fields
+            .iter()
+            .find(|f| f.tag == tag)
+            .map(|f| &self.bytes[f.data.clone()])
+    }
+}
+
+/// Iterator over the raw records of a file, the DDR first.
+#[derive(Debug)]
+pub struct RawRecords<R: Read> {
+    rdr: R,
+}
+
+/// Reads every record of `rdr`, starting with the DDR, without decoding any fields.
+pub fn raw_records<R: Read>(rdr: R) -> RawRecords<R> {
+    RawRecords { rdr }
+}
+
+impl<R: Read> Iterator for RawRecords<R> {
+    type Item = Result<RawRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match read_record(&mut self.rdr, None) {
+            Ok(bytes) => Some(RawRecord::from_bytes(bytes)),
+            Err(err) => match err.kind() {
+                ErrorKind::EOF => None,
+                _ => Some(Err(err)),
+            },
+        }
+    }
+}
+
+/// The Data Records of `rdr` that contain a field with the tag `tag`, byte for byte as stored.
+pub fn records_with_tag<'a, R: Read + 'a>(
+    rdr: R,
+    tag: &'a str,
+) -> impl Iterator<Item = Result<RawRecord>> + 'a {
+    raw_records(rdr).skip(1).filter(move |record| match record {
+        Ok(record) => record.has_field(tag),
+        Err(_) => true,
+    })
+}
+
+/// Writes the DDR of `rdr` followed by every Data Record containing a field with the tag `tag`,
+/// all untouched, to `out`. The result is a minimal file reproducing whatever is wrong with
+/// those records. Returns the number of Data Records written.
+pub fn extract_records_with_tag<R: Read, W: Write>(rdr: R, tag: &str, mut out: W) -> Result<usize> {
+    let mut records = raw_records(rdr);
+    let ddr = match records.next() {
+        Some(ddr) => ddr?,
+        None => return Err(ErrorKind::EOF.into()),
+    };
+    out.write_all(&ddr.bytes)
+        .with_context(|err| ErrorKind::IOError(err.kind()))?;
+    let mut written = 0;
+    for record in records {
+        let record = record?;
+        if record.has_field(tag) {
+            out.write_all(&record.bytes)
+                .with_context(|err| ErrorKind::IOError(err.kind()))?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
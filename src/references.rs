@@ -0,0 +1,92 @@
+//! Resolution of the text and picture files that belong to a cell. Such files are listed in the
+//! catalogue next to the cell (IMPL "TXT" or "TIF") and are referred to from the TXTDSC, NTXTDS
+//! and PICREP attributes by their file name.
+use crate::catalog::Record;
+use crate::data_parser::Data;
+use crate::volume::resolve_file;
+use std::fs::File;
+use std::path::Path;
+
+impl Record {
+    /// The long file name of a catalogue entry, from the LFIL subfield of its CATD field.
+    pub fn long_file_name(&self) -> Option<&str> {
+        self.catd_str("LFIL")
+    }
+
+    /// The implementation of a catalogue entry, e.g. "BIN", "ASC", "TXT" or "TIF", from the IMPL
+    /// subfield of its CATD field.
+    pub fn implementation(&self) -> Option<&str> {
+        self.catd_str("IMPL")
+    }
+
+    /// Whether the entry is a text or picture file.
+    pub fn is_auxiliary_file(&self) -> bool {
+        matches!(self.implementation(), Some("TXT") | Some("TIF"))
+    }
+
+    pub(crate) fn catd_str(&self, label: &str) -> Option<&str> {
+        match self.get("CATD")?.get(label)? {
+            Data::String(s) if !s.is_empty() => Some(s),
+            _ => None,
+        }
+    }
+}
+
+// The directory part of a catalogue file name, "" for files in the exchange set root.
+fn directory(file: &str) -> &str {
+    file.rfind('\\').map_or("", |i| &file[..i])
+}
+
+fn base_name(file: &str) -> &str {
+    file.rfind('\\').map_or(file, |i| &file[i + 1..])
+}
+
+/// The text and picture files stored in the same directory as `cell`.
+pub fn associated_files<'a>(records: &'a [Record], cell: &Record) -> Vec<&'a Record> {
+    let dir = match cell.file() {
+        Some(file) => directory(file),
+        None => return Vec::new(),
+    };
+    records
+        .iter()
+        .filter(|record| record.is_auxiliary_file())
+        .filter(|record| record.file().map(directory) == Some(dir))
+        .collect()
+}
+
+/// The entry referred to by an attribute value such as a TXTDSC, matched case insensitively
+/// against the base name of FILE or against LFIL.
+pub fn find_referenced<'a>(records: &'a [Record], name: &str) -> Option<&'a Record> {
+    records.iter().find(|record| {
+        record
+            .file()
+            .map(base_name)
+            .into_iter()
+            .chain(record.long_file_name())
+            .any(|candidate| candidate.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Opens the file of a catalogue entry in the exchange set rooted at `root`.
+pub fn open(root: &Path, record: &Record) -> std::io::Result<File> {
+    match record.file() {
+        Some(file) => File::open(resolve_file(root, file)),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "catalogue entry has no FILE",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_file_names() {
+        assert_eq!(directory("GB\\4\\GB4X0000.000"), "GB\\4");
+        assert_eq!(directory("README.TXT"), "");
+        assert_eq!(base_name("GB\\4\\GB4X0000.TXT"), "GB4X0000.TXT");
+        assert_eq!(base_name("README.TXT"), "README.TXT");
+    }
+}
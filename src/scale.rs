@@ -0,0 +1,103 @@
+//! Chart selection by compilation scale. The compilation scale of a cell is the CSCL subfield of
+//! its DSPM field, given as the denominator (22000 for 1:22000). Like an ECDIS, the cell to show
+//! at a display scale is the one compiled closest to it without being overscaled, or the most
+//! detailed one there is when every cell is overscaled.
+use crate::catalog::Record;
+use crate::coverage::BoundingBox;
+use crate::data_parser::Data;
+
+/// The area and compilation scale of a cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    pub file: String, // FILE of the catalogue entry
+    pub scale: u32,   // CSCL, the compilation scale denominator
+    pub bounds: BoundingBox,
+}
+
+impl Record {
+    /// The compilation scale of a cell, from the CSCL subfield of its DSPM field.
+    pub fn compilation_scale(&self) -> Option<u32> {
+        match self.get("DSPM")?.get("CSCL")? {
+            Data::Integer(Some(i)) if *i > 0 => Some(*i as u32),
+            _ => None,
+        }
+    }
+}
+
+impl Coverage {
+    /// The coverage of the cell listed by the catalogue entry `record`, with the compilation
+    /// scale `scale` of the cell.
+    pub fn from_catalog(record: &Record, scale: u32) -> Option<Coverage> {
+        Some(Coverage {
+            file: record.file()?.to_string(),
+            scale,
+            bounds: record.bounds()?,
+        })
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        self.bounds.contains(lat, lon)
+    }
+
+    /// Whether showing the cell at the scale `display_scale` (a denominator) is overscale, i.e.
+    /// zoomed in beyond what it was compiled for.
+    pub fn is_overscale(&self, display_scale: u32) -> bool {
+        display_scale < self.scale
+    }
+}
+
+/// The cell to show at the position at the scale `display_scale` (a denominator).
+pub fn best_cell(cells: &[Coverage], lat: f64, lon: f64, display_scale: u32) -> Option<&Coverage> {
+    let covering = cells.iter().filter(|cell| cell.contains(lat, lon));
+    covering
+        .clone()
+        .filter(|cell| !cell.is_overscale(display_scale))
+        .max_by_key(|cell| cell.scale)
+        .or_else(|| covering.min_by_key(|cell| cell.scale))
+}
+
+/// The cells whose compilation scale is within `min..=max` (denominators).
+pub fn cells_in_scale_range(
+    cells: &[Coverage],
+    min: u32,
+    max: u32,
+) -> impl Iterator<Item = &Coverage> {
+    cells
+        .iter()
+        .filter(move |cell| min <= cell.scale && cell.scale <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(file: &str, scale: u32, south: f64, west: f64, north: f64, east: f64) -> Coverage {
+        Coverage {
+            file: file.to_string(),
+            scale,
+            bounds: BoundingBox {
+                south,
+                west,
+                north,
+                east,
+            },
+        }
+    }
+
+    #[test]
+    fn select_cell() {
+        let cells = vec![
+            cell("GB2X0000.000", 350_000, 50.0, -5.0, 60.0, 5.0),
+            cell("GB4X0000.000", 22_000, 51.0, 0.0, 52.0, 1.0),
+            cell("GB5X0000.000", 8_000, 51.4, 0.4, 51.6, 0.6),
+        ];
+        let best = |lat, lon, scale| best_cell(&cells, lat, lon, scale).map(|c| c.file.as_str());
+        assert_eq!(best(51.5, 0.5, 30_000), Some("GB4X0000.000"));
+        assert_eq!(best(51.5, 0.5, 10_000), Some("GB5X0000.000"));
+        assert_eq!(best(51.5, 0.5, 5_000), Some("GB5X0000.000"));
+        assert_eq!(best(51.5, 0.5, 1_000_000), Some("GB2X0000.000"));
+        assert_eq!(best(55.0, 0.5, 30_000), Some("GB2X0000.000"));
+        assert_eq!(best(0.0, 0.0, 10_000), None);
+        assert_eq!(cells_in_scale_range(&cells, 5_000, 50_000).count(), 2);
+    }
+}
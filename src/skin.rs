@@ -0,0 +1,96 @@
+//! Extraction of the Group 1 features of a cell, the "skin of the earth". These are the area
+//! features (LNDARE, DEPARE, DRGARE, FLODOC, HULKES, PONTON and UNSARE) that together cover the
+//! whole cell without overlapping, and are what a renderer draws first. Neighbouring features
+//! share the edges between them, so their rings tessellate the cell.
+use crate::catalog::Result;
+use crate::dataset::DataSet;
+use crate::feature::{FeatureRecord, Group};
+use crate::geometry::Ring;
+
+/// The object classes (OBJL codes) of Group 1: DEPARE, DRGARE, FLODOC, HULKES, LNDARE, PONTON
+/// and UNSARE.
+pub const GROUP_1_CLASSES: &[u16] = &[42, 46, 57, 65, 71, 95, 154];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceFeature {
+    pub objl: u16,
+    pub rcid: u32,                      // RCID of the feature record
+    pub attributes: Vec<(u16, String)>, // ATTL and ATVL of every ATTF entry
+    pub rings: Vec<Ring>,               // The polygon of the feature
+}
+
+/// The Group 1 feature of `feature`, with its edges assembled into rings, None if it is not
+/// one. A feature is in Group 1 when the GRUP subfield of its FRID is 1.
+pub fn surface_feature(
+    data_set: &DataSet,
+    feature: &FeatureRecord,
+) -> Result<Option<SurfaceFeature>> {
+    if feature.group != Group::SkinOfTheEarth {
+        return Ok(None);
+    }
+    Ok(Some(SurfaceFeature {
+        objl: feature.objl,
+        rcid: feature.rcid,
+        attributes: feature.attributes.clone(),
+        rings: data_set.rings(feature)?,
+    }))
+}
+
+impl DataSet {
+    /// Every Group 1 feature of the cell, in record name order.
+    pub fn skin_of_the_earth(&self) -> Result<Vec<SurfaceFeature>> {
+        let mut features = Vec::new();
+        for feature in self.feature_records() {
+            if let Some(surface) = surface_feature(self, &feature?)? {
+                features.push(surface);
+            }
+        }
+        Ok(features)
+    }
+}
+
+/// The Group 1 features whose class is not one of [`GROUP_1_CLASSES`], which points to a
+/// producer error.
+pub fn unexpected_classes(features: &[SurfaceFeature]) -> impl Iterator<Item = &SurfaceFeature> {
+    features
+        .iter()
+        .filter(|f| !GROUP_1_CLASSES.contains(&f.objl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_parser::Data;
+    use crate::test_support::sample_cell;
+
+    #[test]
+    fn skin_of_the_earth() {
+        let mut data_set = DataSet::read(&sample_cell()[..]).unwrap();
+        let skin = data_set.skin_of_the_earth().unwrap();
+        // LNDARE and DEPARE, but neither the rock in Group 2 nor the meta features
+        let classes: Vec<_> = skin.iter().map(|f| (f.objl, f.rcid)).collect();
+        assert_eq!(classes, vec![(71, 1), (42, 2)]);
+        assert_eq!(
+            skin[1].attributes,
+            vec![(87, "0".into()), (88, "10".into())]
+        );
+        assert!(unexpected_classes(&skin).next().is_none());
+        // The land goes up the coast and the depth area down it
+        let coast = [(51.0, 0.5), (51.5, 0.6), (52.0, 0.5)];
+        let land = &skin[0].rings[0].coordinates;
+        assert!(land.windows(3).any(|w| w == coast));
+        let water: Vec<_> = skin[1].rings[0].coordinates.iter().rev().copied().collect();
+        assert!(water.windows(3).any(|w| w == coast));
+        assert!(skin
+            .iter()
+            .all(|f| f.rings.len() == 1 && f.rings[0].exterior));
+
+        // The rock put in Group 1
+        let uwtroc = data_set.features.get_mut(&(100, 3)).unwrap();
+        uwtroc.set("FRID", "GRUP", Data::Integer(Some(1)));
+        let skin = data_set.skin_of_the_earth().unwrap();
+        let unexpected: Vec<_> = unexpected_classes(&skin).map(|f| f.rcid).collect();
+        assert_eq!(unexpected, vec![3]);
+        assert!(skin[2].rings.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! An on-disk record of which cells and updates have been ingested, so that a service that runs
+//! periodically only has to apply the updates that arrived since its last run.
+//!
+//! The store is a text file with one line per cell, `<cell> <edition> <update>`, e.g.
+//! `GB4X0000 3 12`. Empty lines and lines starting with '#' are skipped.
+use crate::catalog::Result;
+use crate::cell_name::CellName;
+use crate::error::ErrorKind;
+use crate::updates::{UpdateSequence, Version};
+use failure::ResultExt;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+pub struct StateStore {
+    path: PathBuf,
+    cells: BTreeMap<String, Version>, // The last ingested version of every cell
+}
+
+/// What has to be ingested to bring a cell up to date with an [`UpdateSequence`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pending {
+    pub reload: bool, // Whether the base cell has to be (re)loaded first
+    pub updates: Vec<PathBuf>,
+}
+
+impl Pending {
+    pub fn is_empty(&self) -> bool {
+        !self.reload && self.updates.is_empty()
+    }
+}
+
+// The update number of an update file, from its extension.
+fn update_number(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?.parse::<CellName>().ok()?;
+    Some(name.update)
+}
+
+impl StateStore {
+    /// Opens the store at `path`. A missing file is an empty store, it is created by
+    /// [`StateStore::save`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<StateStore> {
+        let path = path.as_ref().to_path_buf();
+        let cells = match std::fs::File::open(&path) {
+            Ok(file) => StateStore::read(BufReader::new(file))?,
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(ErrorKind::IOError(err.kind()).into()),
+        };
+        Ok(StateStore { path, cells })
+    }
+
+    fn read<R: BufRead>(rdr: R) -> Result<BTreeMap<String, Version>> {
+        let mut cells = BTreeMap::new();
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line.map_err(|err| ErrorKind::IOError(err.kind()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || ErrorKind::BadStateStore(i + 1);
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let (cell, edition, update) = match columns.as_slice() {
+                [cell, edition, update] => (cell, edition, update),
+                _ => return Err(bad().into()),
+            };
+            let version = Version {
+                edition: edition.parse().map_err(|_| bad())?,
+                update: update.parse().map_err(|_| bad())?,
+            };
+            cells.insert(cell.to_string(), version);
+        }
+        Ok(cells)
+    }
+
+    /// Writes the store back to its file. The file is replaced as a whole, so a crash while
+    /// saving leaves the previous state intact.
+    pub fn save(&self) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        let mut out =
+            std::fs::File::create(&tmp).with_context(|err| ErrorKind::IOError(err.kind()))?;
+        for (cell, version) in &self.cells {
+            writeln!(out, "{} {} {}", cell, version.edition, version.update)
+                .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        }
+        out.sync_all()
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        std::fs::rename(&tmp, &self.path).with_context(|err| ErrorKind::IOError(err.kind()))?;
+        Ok(())
+    }
+
+    /// The last ingested version of the cell `cell` (e.g. "GB4X0000").
+    pub fn get(&self, cell: &str) -> Option<Version> {
+        self.cells.get(cell).copied()
+    }
+
+    /// Records that the cell `cell` has been ingested up to and including `version`.
+    pub fn record(&mut self, cell: &str, version: Version) {
+        self.cells.insert(cell.to_string(), version);
+    }
+
+    /// Forgets the cell `cell`, e.g. after it has been cancelled.
+    pub fn remove(&mut self, cell: &str) -> Option<Version> {
+        self.cells.remove(cell)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (&str, Version)> {
+        self.cells
+            .iter()
+            .map(|(cell, version)| (cell.as_str(), *version))
+    }
+
+    /// What of `seq` has not been ingested yet for the cell `cell`. The base cell has to be
+    /// reloaded when the cell has never been ingested, when it is a new edition, or when it is a
+    /// reissue including updates past the ingested ones.
+    pub fn pending(&self, cell: &str, seq: &UpdateSequence) -> Pending {
+        match self.get(cell) {
+            Some(stored)
+                if stored.edition == seq.base_version.edition
+                    && stored.update >= seq.base_version.update =>
+            {
+                Pending {
+                    reload: false,
+                    updates: seq
+                        .updates
+                        .iter()
+                        .filter(|path| update_number(path).is_none_or(|n| n > stored.update))
+                        .cloned()
+                        .collect(),
+                }
+            }
+            _ => Pending {
+                reload: true,
+                updates: seq.updates.clone(),
+            },
+        }
+    }
+}
+
+impl UpdateSequence {
+    /// The version the cell is at once the base cell and every update of the sequence are
+    /// applied.
+    pub fn latest_version(&self) -> Version {
+        let update = self
+            .updates
+            .iter()
+            .filter_map(|path| update_number(path))
+            .max()
+            .unwrap_or(0);
+        Version {
+            edition: self.base_version.edition,
+            update: update.max(self.base_version.update),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::updates::CellState;
+
+    fn sequence(base_version: Version, updates: &[&str]) -> UpdateSequence {
+        UpdateSequence {
+            state: CellState::Active,
+            base: PathBuf::from("GB4X0000.000"),
+            base_version,
+            updates: updates.iter().map(PathBuf::from).collect(),
+            gap: None,
+            skipped: Vec::new(),
+            superseded: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn pending_updates() {
+        let mut store = StateStore::default();
+        let v = |edition, update| Version { edition, update };
+        let seq = sequence(v(2, 0), &["GB4X0000.001", "GB4X0000.002"]);
+        assert_eq!(store.pending("GB4X0000", &seq).updates.len(), 2);
+        assert!(store.pending("GB4X0000", &seq).reload);
+        assert_eq!(seq.latest_version(), v(2, 2));
+
+        store.record("GB4X0000", v(2, 1));
+        let pending = store.pending("GB4X0000", &seq);
+        assert!(!pending.reload);
+        assert_eq!(pending.updates, vec![PathBuf::from("GB4X0000.002")]);
+        store.record("GB4X0000", seq.latest_version());
+        assert!(store.pending("GB4X0000", &seq).is_empty());
+
+        // A new edition starts over
+        assert!(store.pending("GB4X0000", &sequence(v(3, 0), &[])).reload);
+    }
+
+    #[test]
+    fn save_and_open() {
+        let path = std::env::temp_dir().join(format!("rust-s57-state-{}", std::process::id()));
+        let mut store = StateStore::open(&path).unwrap();
+        assert_eq!(store.cells().count(), 0);
+        store.record(
+            "GB4X0000",
+            Version {
+                edition: 3,
+                update: 12,
+            },
+        );
+        store.save().unwrap();
+        let store = StateStore::open(&path).unwrap();
+        assert_eq!(store.get("GB4X0000").unwrap().update, 12);
+        std::fs::remove_file(&path).unwrap();
+        assert!(StateStore::read("GB4X0000 3".as_bytes()).is_err());
+    }
+}
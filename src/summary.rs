@@ -0,0 +1,101 @@
+//! Counts of the features of a cell per object class, broken down by geometric primitive. This
+//! is usually the first report made on a cell.
+use crate::catalog::Result;
+use crate::dataset::DataSet;
+use crate::feature::Primitive;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassCount {
+    pub points: usize, // PRIM 1
+    pub lines: usize,  // PRIM 2
+    pub areas: usize,  // PRIM 3
+    pub none: usize,   // PRIM 255, features without geometry
+}
+
+impl ClassCount {
+    pub fn total(&self) -> usize {
+        self.points + self.lines + self.areas + self.none
+    }
+
+    fn add(&mut self, primitive: Primitive) {
+        match primitive {
+            Primitive::Point => self.points += 1,
+            Primitive::Line => self.lines += 1,
+            Primitive::Area => self.areas += 1,
+            Primitive::None => self.none += 1,
+        }
+    }
+}
+
+impl DataSet {
+    /// The number of features of every object class (OBJL code) in the cell. Fails on the first
+    /// feature record that can not be decoded.
+    pub fn class_summary(&self) -> Result<BTreeMap<u16, ClassCount>> {
+        let mut summary: BTreeMap<u16, ClassCount> = BTreeMap::new();
+        for feature in self.feature_records() {
+            let feature = feature?;
+            summary
+                .entry(feature.objl)
+                .or_default()
+                .add(feature.primitive);
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_parser::Data;
+    use crate::test_support::sample_cell;
+
+    #[test]
+    fn count() {
+        let mut count = ClassCount::default();
+        for primitive in &[
+            Primitive::Point,
+            Primitive::Area,
+            Primitive::Area,
+            Primitive::None,
+        ] {
+            count.add(*primitive);
+        }
+        assert_eq!(
+            count,
+            ClassCount {
+                points: 1,
+                lines: 0,
+                areas: 2,
+                none: 1
+            }
+        );
+        assert_eq!(count.total(), 4);
+    }
+
+    #[test]
+    fn class_summary() {
+        let data_set = DataSet::read(&sample_cell()[..]).unwrap();
+        let summary = data_set.class_summary().unwrap();
+        let classes: Vec<_> = summary.keys().copied().collect();
+        assert_eq!(classes, vec![42, 71, 153, 302, 308]);
+        let area = ClassCount {
+            areas: 1,
+            ..ClassCount::default()
+        };
+        assert_eq!(summary[&42], area);
+        assert_eq!(summary[&302], area);
+        assert_eq!(summary[&153].points, 1);
+        assert_eq!(summary.values().map(ClassCount::total).sum::<usize>(), 5);
+
+        // A feature of another primitive of the same class counts separately
+        let mut data_set = data_set;
+        let uwtroc = data_set.features.get_mut(&(100, 3)).unwrap();
+        uwtroc.set("FRID", "OBJL", Data::Integer(Some(42)));
+        let summary = data_set.class_summary().unwrap();
+        assert_eq!(summary[&42].areas, 1);
+        assert_eq!(summary[&42].points, 1);
+        assert_eq!(summary[&42].total(), 2);
+        assert!(!summary.contains_key(&153));
+    }
+}
@@ -0,0 +1,449 @@
+//! Helpers for tests of code using this crate, enabled with the `test-support` feature. The
+//! [`FileBuilder`] makes small, valid ISO 8211 files in memory, so tests don't need real
+//! catalogues or cells.
+use crate::catalog::Record;
+use crate::data_parser::Data;
+use crate::iso8211::Rows;
+use crate::writer::Writer;
+
+struct FieldDefinition {
+    tag: String,
+    controls: &'static str, // Data structure and type code, e.g. "16" for mixed data
+    name: String,
+    array_descriptor: String,
+    format_controls: String,
+}
+
+/// Builds a file from field definitions and records. The record identifier field "0001" is
+/// defined and filled in automatically.
+pub struct FileBuilder {
+    fields: Vec<FieldDefinition>,
+    records: Vec<Record>,
+}
+
+impl Default for FileBuilder {
+    fn default() -> FileBuilder {
+        FileBuilder::new()
+    }
+}
+
+impl FileBuilder {
+    pub fn new() -> FileBuilder {
+        FileBuilder {
+            fields: vec![FieldDefinition {
+                tag: "0001".to_string(),
+                controls: "01",
+                name: "ISO/IEC 8211 Record Identifier".to_string(),
+                array_descriptor: String::new(),
+                format_controls: "(I(5))".to_string(),
+            }],
+            records: Vec::new(),
+        }
+    }
+
+    /// Defines the field `tag` with the labels `array_descriptor`, e.g. "RCNM!RCID", and
+    /// `format_controls`, e.g. "(A(2),I(10))".
+    pub fn field(
+        mut self,
+        tag: &str,
+        name: &str,
+        array_descriptor: &str,
+        format_controls: &str,
+    ) -> FileBuilder {
+        self.fields.push(FieldDefinition {
+            tag: tag.to_string(),
+            controls: "16",
+            name: name.to_string(),
+            array_descriptor: array_descriptor.to_string(),
+            format_controls: format_controls.to_string(),
+        });
+        self
+    }
+
+    /// Like [`FileBuilder::field`] but for a field of the multi-dimensional structure, whose
+    /// subfields repeat as rows, e.g. coordinates.
+    pub fn repeating_field(
+        mut self,
+        tag: &str,
+        name: &str,
+        array_descriptor: &str,
+        format_controls: &str,
+    ) -> FileBuilder {
+        self = self.field(tag, name, array_descriptor, format_controls);
+        self.fields.last_mut().unwrap().controls = "26";
+        self
+    }
+
+    /// Adds a Data Record with the given fields, each a tag with (label, value) pairs.
+    pub fn record(mut self, fields: &[(&str, &[(&str, Data)])]) -> FileBuilder {
+        let mut record = Record::new();
+        record.set_id(self.records.len() as i64 + 1);
+        for (tag, subfields) in fields {
+            for (label, data) in subfields.iter() {
+                record.set(tag, label, data.clone());
+            }
+        }
+        self.records.push(record);
+        self
+    }
+
+    /// Adds a Data Record as it is, without filling in its record identifier.
+    pub fn raw_record(mut self, record: Record) -> FileBuilder {
+        self.records.push(record);
+        self
+    }
+
+    fn ddr(&self) -> Vec<u8> {
+        let mut entries = Vec::new();
+        let mut area = Vec::new();
+        let mut push = |tag: &str, field: Vec<u8>| {
+            entries.push((tag.to_string(), field.len(), area.len()));
+            area.extend(field);
+        };
+        let mut control = b"0000;&   \x1f".to_vec();
+        for field in self.fields.iter().skip(1) {
+            control.extend(b"0001");
+            control.extend(field.tag.bytes());
+        }
+        control.push(0x1e);
+        push("0000", control);
+        for field in self.fields.iter() {
+            let description = format!(
+                "{}00;&   {}\x1f{}\x1f{}\x1e",
+                field.controls, field.name, field.array_descriptor, field.format_controls
+            );
+            push(&field.tag, description.into_bytes());
+        }
+        let mut directory: Vec<u8> = entries
+            .iter()
+            .flat_map(|(tag, length, offset)| {
+                format!("{}{:03}{:04}", tag, length, offset).into_bytes()
+            })
+            .collect();
+        directory.push(0x1e);
+        let base = 24 + directory.len();
+        let mut ddr = format!("{:05}3L 1 09{:05}   3404", base + area.len(), base).into_bytes();
+        ddr.extend(directory);
+        ddr.extend(area);
+        ddr
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new(), &self.ddr()).expect("builder made a bad DDR");
+        for record in self.records.iter() {
+            writer
+                .write_record(record)
+                .expect("record does not match the field definitions");
+        }
+        writer.into_inner()
+    }
+}
+
+/// A catalogue (CATALOG.031) listing itself and one cell, GB4X0000.000.
+pub fn sample_catalog() -> Vec<u8> {
+    let entry = |rcid: i64, file: &str, implementation: &str| {
+        vec![
+            ("RCNM", Data::String("CD".to_string())),
+            ("RCID", Data::Integer(Some(rcid))),
+            ("FILE", Data::String(file.to_string())),
+            ("VOLM", Data::String("V01X01".to_string())),
+            ("IMPL", Data::String(implementation.to_string())),
+        ]
+    };
+    let catalog = entry(1, "CATALOG.031", "ASC");
+    let mut cell = entry(2, "GB4X0000.000", "BIN");
+    cell.extend(vec![
+        ("SLAT", Data::Float(Some(51.0))),
+        ("WLON", Data::Float(Some(0.0))),
+        ("NLAT", Data::Float(Some(52.0))),
+        ("ELON", Data::Float(Some(1.0))),
+    ]);
+    FileBuilder::new()
+        .field(
+            "CATD",
+            "Catalogue Directory Field",
+            "RCNM!RCID!FILE!LFIL!VOLM!IMPL!SLAT!WLON!NLAT!ELON!CRCS!COMT",
+            "(A(2),I(10),3A,A(3),4R,2A)",
+        )
+        .record(&[("CATD", &catalog)])
+        .record(&[("CATD", &cell)])
+        .build()
+}
+
+// A Data Record with the rows of every field, e.g. the pointers of FSPT. Fields without rows
+// are left out.
+fn record(id: i64, fields: &[(&str, &[&str], Rows)]) -> Record {
+    let mut record = Record::new();
+    record.set_id(id);
+    for (tag, labels, rows) in fields.iter().filter(|(_, _, rows)| !rows.is_empty()) {
+        record.insert_rows(tag, labels, rows.clone());
+    }
+    record
+}
+
+/// A cell, GB4X0000.000, covering 51 to 52 N and 0 to 1 E, with coordinates multiplied by
+/// 10 000 000. It has
+/// - the connected nodes 1 to 6 at the corners of the cell and the middles of its northern and
+///   southern limits, counter-clockwise from the south west,
+/// - the edges 1 to 7 between them, edge 2 being the coast from node 2 to node 5 by way of
+///   (51.5, 0.6),
+/// - the isolated node 1 at (51.5, 0.8), and the edge 8 looping from the connected node 7 at
+///   (51.4, 0.7) around it, which no feature points to,
+/// - the features LNDARE (100, 1) west of the coast and DEPARE (100, 2) east of it in Group 1,
+///   UWTROC (100, 3) on the isolated node, and M_COVR (100, 4) and M_QUAL (100, 5) over the
+///   whole cell.
+pub fn sample_cell() -> Vec<u8> {
+    let int = |i: i64| Data::Integer(Some(i));
+    let text = |s: &str| Data::String(s.to_string());
+    let name = |rcnm: i64, rcid: i64| int(rcnm | rcid << 8);
+    let degrees = |d: f64| int((d * 10_000_000.0).round() as i64);
+    let vrid = |rcnm, rcid| vec![vec![int(rcnm), int(rcid), int(1), int(1)]];
+    let mut builder = FileBuilder::new()
+        .field(
+            "DSID",
+            "Data Set Identification Field",
+            "RCNM!RCID!DSNM",
+            "(b11,b14,A)",
+        )
+        .field(
+            "DSPM",
+            "Data Set Parameter Field",
+            "RCNM!RCID!HDAT!VDAT!SDAT!CSCL!DUNI!HUNI!PUNI!COUN!COMF!SOMF",
+            "(b11,b14,3b11,b14,4b11,2b14)",
+        )
+        .field(
+            "VRID",
+            "Vector Record Identifier Field",
+            "RCNM!RCID!RVER!RUIN",
+            "(b11,b14,b12,b11)",
+        )
+        .field(
+            "VRPT",
+            "Vector Record Pointer Field",
+            "*NAME!ORNT!USAG!TOPI!MASK",
+            "(B(40),4b11)",
+        )
+        .field("SG2D", "2-D Coordinate Field", "*YCOO!XCOO", "(2b24)")
+        .field(
+            "FRID",
+            "Feature Record Identifier Field",
+            "RCNM!RCID!PRIM!GRUP!OBJL!RVER!RUIN",
+            "(b11,b14,2b11,2b12,b11)",
+        )
+        .field(
+            "FOID",
+            "Feature Object Identifier Field",
+            "AGEN!FIDN!FIDS",
+            "(b12,b14,b12)",
+        )
+        .field(
+            "ATTF",
+            "Feature Record Attribute Field",
+            "*ATTL!ATVL",
+            "(b12,A)",
+        )
+        .field(
+            "FSPT",
+            "Feature Record to Spatial Record Pointer Field",
+            "*NAME!ORNT!USAG!MASK",
+            "(B(40),3b11)",
+        )
+        .record(&[(
+            "DSID",
+            &[
+                ("RCNM", int(10)),
+                ("RCID", int(1)),
+                ("DSNM", text("GB4X0000.000")),
+            ],
+        )]);
+    let mut dspm = vec![("RCNM", int(20)), ("RCID", int(1))];
+    for (label, value) in [
+        ("HDAT", 2),
+        ("VDAT", 3),
+        ("SDAT", 23),
+        ("CSCL", 22000),
+        ("DUNI", 1),
+        ("HUNI", 1),
+        ("PUNI", 1),
+        ("COUN", 1),
+        ("COMF", 10_000_000),
+        ("SOMF", 10),
+    ] {
+        dspm.push((label, int(value)));
+    }
+    builder = builder.record(&[("DSPM", &dspm)]);
+    let mut id = 3;
+    let mut next_id = || {
+        id += 1;
+        id - 1
+    };
+    let nodes = [
+        (51.0, 0.0),
+        (51.0, 0.5),
+        (51.0, 1.0),
+        (52.0, 1.0),
+        (52.0, 0.5),
+        (52.0, 0.0),
+        (51.4, 0.7),
+    ];
+    for (rcid, (lat, lon)) in (1..).zip(nodes) {
+        builder = builder.raw_record(record(
+            next_id(),
+            &[
+                ("VRID", &["RCNM", "RCID", "RVER", "RUIN"], vrid(120, rcid)),
+                (
+                    "SG2D",
+                    &["YCOO", "XCOO"],
+                    vec![vec![degrees(lat), degrees(lon)]],
+                ),
+            ],
+        ));
+    }
+    builder = builder.raw_record(record(
+        next_id(),
+        &[
+            ("VRID", &["RCNM", "RCID", "RVER", "RUIN"], vrid(110, 1)),
+            (
+                "SG2D",
+                &["YCOO", "XCOO"],
+                vec![vec![degrees(51.5), degrees(0.8)]],
+            ),
+        ],
+    ));
+    // Beginning and end node of every edge, and the coordinates in between
+    let edges = [
+        (1, 2, vec![]),
+        (2, 5, vec![(51.5, 0.6)]),
+        (5, 6, vec![]),
+        (6, 1, vec![]),
+        (2, 3, vec![]),
+        (3, 4, vec![]),
+        (4, 5, vec![]),
+        (7, 7, vec![(51.4, 0.9), (51.6, 0.9), (51.6, 0.7)]),
+    ];
+    for (rcid, (begin, end, between)) in (1..).zip(edges) {
+        let node = |rcid, topi| vec![name(120, rcid), int(255), int(255), int(topi), int(255)];
+        let coordinates = between
+            .iter()
+            .map(|&(lat, lon)| vec![degrees(lat), degrees(lon)])
+            .collect();
+        builder = builder.raw_record(record(
+            next_id(),
+            &[
+                ("VRID", &["RCNM", "RCID", "RVER", "RUIN"], vrid(130, rcid)),
+                (
+                    "VRPT",
+                    &["NAME", "ORNT", "USAG", "TOPI", "MASK"],
+                    vec![node(begin, 1), node(end, 2)],
+                ),
+                ("SG2D", &["YCOO", "XCOO"], coordinates),
+            ],
+        ));
+    }
+    // Object class, primitive, group, attributes and spatial records of every feature, the
+    // edges as (RCID, ORNT, USAG)
+    let outline = [
+        (1, 1, 3),
+        (5, 1, 3),
+        (6, 1, 3),
+        (7, 1, 3),
+        (3, 1, 3),
+        (4, 1, 3),
+    ];
+    let features = [
+        (
+            71,
+            3,
+            1,
+            vec![],
+            edges_of(&[(1, 1, 3), (2, 1, 1), (3, 1, 3), (4, 1, 3)]),
+        ),
+        (
+            42,
+            3,
+            1,
+            vec![(87, "0"), (88, "10")],
+            edges_of(&[(5, 1, 3), (6, 1, 3), (7, 1, 3), (2, 2, 1)]),
+        ),
+        (
+            153,
+            1,
+            2,
+            vec![(179, "2.5"), (187, "3")],
+            vec![vec![name(110, 1), int(255), int(255), int(255)]],
+        ),
+        (302, 3, 255, vec![(18, "1")], edges_of(&outline)),
+        (308, 3, 255, vec![(72, "4")], edges_of(&outline)),
+    ];
+    for (rcid, (objl, prim, grup, attributes, fspt)) in (1..).zip(features) {
+        let attributes = attributes
+            .iter()
+            .map(|&(attl, atvl)| vec![int(attl), text(atvl)])
+            .collect();
+        builder = builder.raw_record(record(
+            next_id(),
+            &[
+                (
+                    "FRID",
+                    &["RCNM", "RCID", "PRIM", "GRUP", "OBJL", "RVER", "RUIN"],
+                    vec![vec![
+                        int(100),
+                        int(rcid),
+                        int(prim),
+                        int(grup),
+                        int(objl),
+                        int(1),
+                        int(1),
+                    ]],
+                ),
+                (
+                    "FOID",
+                    &["AGEN", "FIDN", "FIDS"],
+                    vec![vec![int(540), int(rcid), int(1)]],
+                ),
+                ("ATTF", &["ATTL", "ATVL"], attributes),
+                ("FSPT", &["NAME", "ORNT", "USAG", "MASK"], fspt),
+            ],
+        ));
+    }
+    builder.build()
+}
+
+// The FSPT rows of pointers to the edges (RCID, ORNT, USAG).
+fn edges_of(edges: &[(i64, i64, i64)]) -> Rows {
+    let int = |i: i64| Data::Integer(Some(i));
+    edges
+        .iter()
+        .map(|&(rcid, ornt, usag)| vec![int(130 | rcid << 8), int(ornt), int(usag), int(255)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+
+    #[test]
+    fn read_sample_catalog() {
+        let bytes = sample_catalog();
+        let records: Vec<_> = Catalog::new(&bytes[..])
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].id(), Some(2));
+        assert_eq!(records[1].file(), Some("GB4X0000.000"));
+        assert!(records[1].bounds().unwrap().contains(51.5, 0.5));
+    }
+
+    #[test]
+    fn read_sample_cell() {
+        let bytes = sample_cell();
+        let records: Vec<_> = Catalog::new(&bytes[..])
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 23);
+        assert_eq!(records[22].id(), Some(23));
+    }
+}
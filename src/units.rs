@@ -0,0 +1,194 @@
+//! The units of measurement of a data set, from the DUNI, HUNI and PUNI subfields of the Data
+//! Set Parameter field (DSPM), and conversion of depth and height attribute values to metres.
+use crate::catalog::{Record, Result};
+use crate::data_parser::Data;
+use crate::dataset::{bad_value, integer};
+use crate::error::ErrorKind;
+use std::convert::TryFrom;
+
+const METRES_PER_FOOT: f64 = 0.3048;
+const METRES_PER_FATHOM: f64 = 1.8288;
+
+/// DUNI, the units of depth measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepthUnit {
+    Metres,           // 1
+    FathomsAndFeet,   // 2
+    Feet,             // 3
+    FathomsFractions, // 4, fathoms and fractions
+}
+
+/// HUNI, the units of height measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeightUnit {
+    Metres, // 1
+    Feet,   // 2
+}
+
+/// PUNI, the units of positional accuracy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositionalUnit {
+    Metres,       // 1
+    DegreesOfArc, // 2
+    Millimetres,  // 3
+    Feet,         // 4
+    Cables,       // 5
+}
+
+impl TryFrom<&Data> for DepthUnit {
+    type Error = crate::error::Error;
+    fn try_from(data: &Data) -> Result<DepthUnit> {
+        match integer("DUNI", data)? {
+            1 => Ok(DepthUnit::Metres),
+            2 => Ok(DepthUnit::FathomsAndFeet),
+            3 => Ok(DepthUnit::Feet),
+            4 => Ok(DepthUnit::FathomsFractions),
+            _ => Err(bad_value("DUNI", data)),
+        }
+    }
+}
+
+impl TryFrom<&Data> for HeightUnit {
+    type Error = crate::error::Error;
+    fn try_from(data: &Data) -> Result<HeightUnit> {
+        match integer("HUNI", data)? {
+            1 => Ok(HeightUnit::Metres),
+            2 => Ok(HeightUnit::Feet),
+            _ => Err(bad_value("HUNI", data)),
+        }
+    }
+}
+
+impl TryFrom<&Data> for PositionalUnit {
+    type Error = crate::error::Error;
+    fn try_from(data: &Data) -> Result<PositionalUnit> {
+        match integer("PUNI", data)? {
+            1 => Ok(PositionalUnit::Metres),
+            2 => Ok(PositionalUnit::DegreesOfArc),
+            3 => Ok(PositionalUnit::Millimetres),
+            4 => Ok(PositionalUnit::Feet),
+            5 => Ok(PositionalUnit::Cables),
+            _ => Err(bad_value("PUNI", data)),
+        }
+    }
+}
+
+impl DepthUnit {
+    /// Converts the depth `value` to metres. In fathoms and feet the whole part is fathoms and
+    /// the first decimal feet, so 5.3 is 5 fathoms and 3 feet.
+    pub fn to_metres(self, value: f64) -> f64 {
+        match self {
+            DepthUnit::Metres => value,
+            DepthUnit::FathomsAndFeet => {
+                let fathoms = value.trunc();
+                let feet = ((value - fathoms) * 10.0).round();
+                fathoms * METRES_PER_FATHOM + feet * METRES_PER_FOOT
+            }
+            DepthUnit::Feet => value * METRES_PER_FOOT,
+            DepthUnit::FathomsFractions => value * METRES_PER_FATHOM,
+        }
+    }
+}
+
+impl HeightUnit {
+    pub fn to_metres(self, value: f64) -> f64 {
+        match self {
+            HeightUnit::Metres => value,
+            HeightUnit::Feet => value * METRES_PER_FOOT,
+        }
+    }
+}
+
+impl PositionalUnit {
+    /// Converts the accuracy `value` to metres. Degrees of arc can not be converted without
+    /// knowing where on the earth the position is, so they give `None`.
+    pub fn to_metres(self, value: f64) -> Option<f64> {
+        match self {
+            PositionalUnit::Metres => Some(value),
+            PositionalUnit::DegreesOfArc => None,
+            PositionalUnit::Millimetres => Some(value / 1000.0),
+            PositionalUnit::Feet => Some(value * METRES_PER_FOOT),
+            PositionalUnit::Cables => Some(value * 185.2),
+        }
+    }
+}
+
+/// Attributes holding a depth (ATTL codes): DRVAL1, DRVAL2, VALDCO and VALSOU.
+pub const DEPTH_ATTRIBUTES: &[u16] = &[87, 88, 174, 179];
+
+/// Attributes holding a height or vertical clearance (ATTL codes): ELEVAT, HEIGHT, VERCLR,
+/// VERCCL and VERCOP.
+pub const HEIGHT_ATTRIBUTES: &[u16] = &[90, 95, 181, 182, 183];
+
+/// The units of a data set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Units {
+    pub depth: DepthUnit,
+    pub height: HeightUnit,
+    pub positional: PositionalUnit,
+}
+
+impl Units {
+    /// The units from the DSPM field of `record`, the data set parameter record of a cell.
+    pub fn from_dspm(record: &Record) -> Result<Units> {
+        let dspm = record
+            .get("DSPM")
+            .ok_or_else(|| ErrorKind::MissingField("DSPM".to_string()))?;
+        let subfield = |label: &str| {
+            dspm.get(label)
+                .ok_or_else(|| ErrorKind::MissingSubfield("DSPM".to_string(), label.to_string()))
+        };
+        Ok(Units {
+            depth: DepthUnit::try_from(subfield("DUNI")?)?,
+            height: HeightUnit::try_from(subfield("HUNI")?)?,
+            positional: PositionalUnit::try_from(subfield("PUNI")?)?,
+        })
+    }
+
+    /// Converts the value of the attribute `attl` to metres if it is a depth or height
+    /// attribute, otherwise it is returned as is.
+    pub fn to_metres(&self, attl: u16, value: f64) -> f64 {
+        if DEPTH_ATTRIBUTES.contains(&attl) {
+            self.depth.to_metres(value)
+        } else if HEIGHT_ATTRIBUTES.contains(&attl) {
+            self.height.to_metres(value)
+        } else {
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(
+            DepthUnit::try_from(&Data::Integer(Some(3))).unwrap(),
+            DepthUnit::Feet
+        );
+        assert_eq!(
+            HeightUnit::try_from(&Data::String("2".to_string())).unwrap(),
+            HeightUnit::Feet
+        );
+        assert!(PositionalUnit::try_from(&Data::Integer(Some(6))).is_err());
+        assert!(DepthUnit::try_from(&Data::Integer(None)).is_err());
+    }
+
+    #[test]
+    fn convert() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(DepthUnit::FathomsAndFeet.to_metres(5.3), 10.0584));
+        assert!(close(DepthUnit::Feet.to_metres(10.0), 3.048));
+        assert_eq!(PositionalUnit::DegreesOfArc.to_metres(1.0), None);
+        let units = Units {
+            depth: DepthUnit::FathomsFractions,
+            height: HeightUnit::Feet,
+            positional: PositionalUnit::Metres,
+        };
+        assert!(close(units.to_metres(87, 2.0), 3.6576));
+        assert!(close(units.to_metres(95, 100.0), 30.48));
+        assert!(close(units.to_metres(116, 2.0), 2.0));
+    }
+}
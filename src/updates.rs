@@ -0,0 +1,407 @@
+//! Assembling a cell from its base cell (.000) and update files (.001, .002, ...). Updates have
+//! to be applied in sequence, so a gap means no update past it may be applied.
+use crate::catalog::{Catalog, Result};
+use crate::cell_name::CellName;
+use crate::dataset::subfield_number;
+use crate::error::ErrorKind;
+use failure::ResultExt;
+use std::path::{Path, PathBuf};
+
+/// What to do when the update sequence has a gap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// Fail with `ErrorKind::MissingUpdate`.
+    Refuse,
+    /// Keep the updates before the gap and report the gap in `UpdateSequence::gap`.
+    Warn,
+}
+
+/// The edition and update number of a cell, from the EDTN and UPDN subfields of DSID. A base
+/// cell (.000) with an update number above zero is a reissue that already includes the updates
+/// up to that number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub edition: u32,
+    pub update: u32,
+}
+
+/// Whether a cell is still in use. A cell is cancelled by an update with edition 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellState {
+    Active,
+    Cancelled { update: u32 }, // The number of the cancelling update
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateSequence {
+    pub state: CellState,
+    pub base: PathBuf,
+    pub base_version: Version,
+    pub updates: Vec<PathBuf>, // The updates that may be applied, in order
+    pub gap: Option<u32>,      // The first missing update number, if updates past it exist
+    pub skipped: Vec<PathBuf>, // Updates past the gap that must not be applied
+    pub superseded: Vec<PathBuf>, // Updates a reissued base cell includes, or of an old edition
+}
+
+/// The first missing update number after `after`, if any update comes after it. Updates up to
+/// and including `after` are ignored and `updates` does not need to be sorted.
+pub fn find_gap(after: u32, updates: &[u32]) -> Option<u32> {
+    let mut sorted: Vec<u32> = updates.iter().copied().filter(|&u| u > after).collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+    sorted
+        .iter()
+        .zip(after + 1..)
+        .find(|(&update, expected)| update != *expected)
+        .map(|(_, expected)| expected)
+}
+
+/// Picks the base cell to start from: the latest edition and, within it, the latest reissue.
+pub fn choose_base(candidates: Vec<(PathBuf, Version)>) -> Option<(PathBuf, Version)> {
+    candidates.into_iter().max_by_key(|(_, version)| *version)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|err| ErrorKind::IOError(err.kind()))?;
+    for entry in entries {
+        let path = entry
+            .with_context(|err| ErrorKind::IOError(err.kind()))?
+            .path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn open(path: &Path) -> Result<std::fs::File> {
+    Ok(std::fs::File::open(path).with_context(|err| ErrorKind::IOError(err.kind()))?)
+}
+
+/// Finds the base cell and update files of the cell `cell` (e.g. "GB4X0000") anywhere below
+/// `dir`, handling a gap in the updates according to `policy`. When there are several base
+/// cells, e.g. the original and a reissue, the one to start from is picked with
+/// [`choose_base`] and the updates it already includes, or that belong to another edition, are
+/// left out.
+pub fn find_updates(dir: &Path, cell: &str, policy: GapPolicy) -> Result<UpdateSequence> {
+    find_updates_with(dir, cell, policy, |path| read_version(open(path)?))
+}
+
+/// Like [`find_updates`] but with the version of the base cells and updates read by
+/// `read_version`.
+pub fn find_updates_with<F>(
+    dir: &Path,
+    cell: &str,
+    policy: GapPolicy,
+    mut read_version: F,
+) -> Result<UpdateSequence>
+where
+    F: FnMut(&Path) -> Result<Version>,
+{
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    let mut bases = Vec::new();
+    let mut updates: Vec<(u32, PathBuf, u32)> = Vec::new(); // (Update number, path, edition)
+    for path in files {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let name = match name.parse::<CellName>() {
+            Ok(name) if name.base_name().eq_ignore_ascii_case(cell) => name,
+            _ => continue,
+        };
+        if name.is_base_cell() {
+            let version = read_version(&path)?;
+            bases.push((path, version));
+        } else {
+            let edition = read_version(&path)?.edition;
+            updates.push((name.update, path, edition));
+        }
+    }
+    let (base, base_version) =
+        choose_base(bases).ok_or_else(|| ErrorKind::MissingBaseCell(cell.to_string()))?;
+    updates.sort();
+    // Updates of another edition belong to a base cell the chosen one replaces. A cancellation
+    // (edition 0) applies to any edition.
+    let (superseded, updates): (Vec<_>, Vec<_>) =
+        updates.into_iter().partition(|(n, _, edition)| {
+            *n <= base_version.update || (*edition != 0 && *edition != base_version.edition)
+        });
+    let numbers: Vec<u32> = updates.iter().map(|(n, ..)| *n).collect();
+    let gap = find_gap(base_version.update, &numbers);
+    let (updates, skipped) = match gap {
+        Some(gap) if policy == GapPolicy::Refuse => {
+            return Err(ErrorKind::MissingUpdate(cell.to_string(), gap).into())
+        }
+        Some(gap) => updates.into_iter().partition(|(n, ..)| *n < gap),
+        None => (updates, Vec::new()),
+    };
+    // Nothing after a cancellation applies, and the cancellation itself is not applied either
+    let state = match updates
+        .iter()
+        .filter(|(_, _, edition)| *edition == 0)
+        .map(|(n, ..)| *n)
+        .min()
+    {
+        Some(update) => CellState::Cancelled { update },
+        None => CellState::Active,
+    };
+    let (updates, cancelled): (Vec<_>, Vec<_>) = match state {
+        CellState::Cancelled { update } => updates.into_iter().partition(|(n, ..)| *n < update),
+        CellState::Active => (updates, Vec::new()),
+    };
+    let skipped = cancelled.into_iter().chain(skipped).collect();
+    let paths = |v: Vec<(u32, PathBuf, u32)>| v.into_iter().map(|(_, path, _)| path).collect();
+    Ok(UpdateSequence {
+        state,
+        base,
+        base_version,
+        updates: paths(updates),
+        gap,
+        skipped: paths(skipped),
+        superseded: paths(superseded),
+    })
+}
+
+/// The edition and update number of the ENC file read from `rdr`.
+pub fn read_version<R: std::io::Read>(rdr: R) -> Result<Version> {
+    for record in Catalog::new(rdr)? {
+        let record = record?;
+        if record.get("DSID").is_some() {
+            return Ok(Version {
+                edition: subfield_number(&record, "DSID", "EDTN")?,
+                update: subfield_number(&record, "DSID", "UPDN")?,
+            });
+        }
+    }
+    Err(ErrorKind::MissingField("DSID".to_string()).into())
+}
+
+/// Checks that `update`, the version of the file `file`, is the next update of the cell `cell`
+/// at version `current`: the same edition and the update number one higher. A later update
+/// number fails with `ErrorKind::MissingUpdate` and an earlier one with
+/// `ErrorKind::UpdateOutOfOrder`.
+pub fn check_next(cell: &str, file: &str, current: Version, update: Version) -> Result<()> {
+    if update.edition != current.edition {
+        return Err(ErrorKind::EditionMismatch(
+            file.to_string(),
+            current.edition.to_string(),
+            update.edition.to_string(),
+        )
+        .into());
+    }
+    let expected = current.update + 1;
+    if update.update > expected {
+        return Err(ErrorKind::MissingUpdate(cell.to_string(), expected).into());
+    }
+    if update.update < expected {
+        return Err(ErrorKind::UpdateOutOfOrder(file.to_string(), update.update, expected).into());
+    }
+    Ok(())
+}
+
+/// Checks that `updates`, given as (file, version) pairs in order, follow on from the version
+/// `base` of the base cell of `cell` without gaps. A cancellation (edition 0) only has to have
+/// the right update number.
+pub fn check_sequence<P: AsRef<Path>>(
+    cell: &str,
+    base: Version,
+    updates: &[(P, Version)],
+) -> Result<()> {
+    let mut current = base;
+    for (file, version) in updates {
+        let version = match version.edition {
+            0 => Version {
+                edition: current.edition,
+                ..*version
+            },
+            _ => *version,
+        };
+        check_next(cell, &file.as_ref().display().to_string(), current, version)?;
+        current = version;
+    }
+    Ok(())
+}
+
+impl UpdateSequence {
+    /// Reads the version of the base cell and every update and checks with [`check_sequence`]
+    /// that the updates follow on from the base cell in order.
+    pub fn check_versions(&self) -> Result<()> {
+        let base_version = read_version(open(&self.base)?)?;
+        let updates = self
+            .updates
+            .iter()
+            .map(|path| Ok((path, read_version(open(path)?)?)))
+            .collect::<Result<Vec<(&PathBuf, Version)>>>()?;
+        let cell = self.base.display().to_string();
+        check_sequence(&cell, base_version, &updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gaps() {
+        assert_eq!(find_gap(0, &[]), None);
+        assert_eq!(find_gap(0, &[1, 2, 3]), None);
+        assert_eq!(find_gap(0, &[3, 1, 2]), None);
+        assert_eq!(find_gap(0, &[1, 2, 4]), Some(3));
+        assert_eq!(find_gap(0, &[2]), Some(1));
+        assert_eq!(find_gap(2, &[1, 3, 4]), None);
+        assert_eq!(find_gap(2, &[1, 4]), Some(3));
+    }
+
+    #[test]
+    fn find_updates_in_directory() {
+        let dir = std::env::temp_dir().join(format!("rust-s57-updates-{}", std::process::id()));
+        for file in &[
+            "0/GB4X0000.000",
+            "1/GB4X0000.001",
+            "2/GB4X0000.002",
+            "4/GB4X0000.004",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+        let original = |_: &Path| {
+            Ok(Version {
+                edition: 1,
+                update: 0,
+            })
+        };
+        assert!(find_updates_with(&dir, "GB4X0000", GapPolicy::Refuse, original).is_err());
+        let seq = find_updates_with(&dir, "GB4X0000", GapPolicy::Warn, original).unwrap();
+        assert_eq!(seq.base, dir.join("0/GB4X0000.000"));
+        assert_eq!(
+            seq.updates,
+            vec![dir.join("1/GB4X0000.001"), dir.join("2/GB4X0000.002")]
+        );
+        assert_eq!(seq.gap, Some(3));
+        assert_eq!(seq.skipped, vec![dir.join("4/GB4X0000.004")]);
+        assert!(find_updates_with(&dir, "GB4X0001", GapPolicy::Warn, original).is_err());
+
+        // A reissue including updates up to 3 makes the gap irrelevant
+        std::fs::create_dir_all(dir.join("R")).unwrap();
+        std::fs::write(dir.join("R/GB4X0000.000"), b"").unwrap();
+        let reissue = |path: &Path| {
+            Ok(Version {
+                edition: 1,
+                update: if path.starts_with(dir.join("R")) {
+                    3
+                } else {
+                    0
+                },
+            })
+        };
+        let seq = find_updates_with(&dir, "GB4X0000", GapPolicy::Refuse, reissue).unwrap();
+        assert_eq!(seq.base, dir.join("R/GB4X0000.000"));
+        assert_eq!(seq.updates, vec![dir.join("4/GB4X0000.004")]);
+        assert_eq!(seq.gap, None);
+        assert_eq!(
+            seq.superseded,
+            vec![dir.join("1/GB4X0000.001"), dir.join("2/GB4X0000.002")]
+        );
+        assert_eq!(seq.state, CellState::Active);
+
+        // Update 4 cancels the cell
+        let cancelled = |path: &Path| {
+            Ok(Version {
+                edition: if path.ends_with("GB4X0000.004") { 0 } else { 1 },
+                update: if path.starts_with(dir.join("R")) {
+                    3
+                } else {
+                    0
+                },
+            })
+        };
+        let seq = find_updates_with(&dir, "GB4X0000", GapPolicy::Refuse, cancelled).unwrap();
+        assert_eq!(seq.state, CellState::Cancelled { update: 4 });
+        assert!(seq.updates.is_empty());
+        assert_eq!(seq.skipped, vec![dir.join("4/GB4X0000.004")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn updates_of_an_old_edition() {
+        let dir = std::env::temp_dir().join(format!("rust-s57-editions-{}", std::process::id()));
+        for file in &[
+            "E2/GB4X0000.000",
+            "E2/GB4X0000.001",
+            "E2/GB4X0000.002",
+            "E3/GB4X0000.000",
+            "E3/GB4X0000.001",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+        let editions = |path: &Path| {
+            Ok(Version {
+                edition: if path.starts_with(dir.join("E3")) {
+                    3
+                } else {
+                    2
+                },
+                update: 0,
+            })
+        };
+        let seq = find_updates_with(&dir, "GB4X0000", GapPolicy::Refuse, editions).unwrap();
+        assert_eq!(seq.base, dir.join("E3/GB4X0000.000"));
+        assert_eq!(seq.updates, vec![dir.join("E3/GB4X0000.001")]);
+        assert_eq!(seq.gap, None);
+        assert_eq!(
+            seq.superseded,
+            vec![dir.join("E2/GB4X0000.001"), dir.join("E2/GB4X0000.002")]
+        );
+
+        // Without the new update, the stale one must not stand in for it
+        std::fs::remove_file(dir.join("E3/GB4X0000.001")).unwrap();
+        let seq = find_updates_with(&dir, "GB4X0000", GapPolicy::Refuse, editions).unwrap();
+        assert!(seq.updates.is_empty());
+        assert_eq!(seq.superseded.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sequences() {
+        let version = |edition, update| Version { edition, update };
+        let base = version(2, 0);
+        let updates = vec![
+            ("GB4X0000.001", version(2, 1)),
+            ("GB4X0000.002", version(2, 2)),
+            ("GB4X0000.003", version(0, 3)),
+        ];
+        assert!(check_sequence("GB4X0000", base, &updates).is_ok());
+        assert!(check_sequence("GB4X0000", version(2, 1), &updates[1..]).is_ok());
+        match check_sequence("GB4X0000", base, &updates[1..])
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::MissingUpdate(cell, 1) => assert_eq!(cell, "GB4X0000"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        match check_sequence("GB4X0000", version(2, 1), &updates)
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::UpdateOutOfOrder(file, 1, 2) => assert_eq!(file, "GB4X0000.001"),
+            kind => panic!("unexpected error: {}", kind),
+        }
+        match check_next("GB4X0000", "GB4X0000.001", base, version(3, 1))
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::EditionMismatch(file, expected, found) => assert_eq!(
+                (file.as_str(), expected.as_str(), found.as_str()),
+                ("GB4X0000.001", "2", "3")
+            ),
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+}
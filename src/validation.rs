@@ -0,0 +1,299 @@
+//! Validation of the attributes carried by features against the rules of their object class:
+//! which attributes (ATTL codes) a class (OBJL code) may carry and which are mandatory.
+//! Violations are reported as [`Diagnostic`]s, as are broken record identifier sequences. The
+//! rules can be built from the object and attribute catalogues and checked against every feature
+//! of a cell with [`DataSet::validate`](crate::dataset::DataSet::validate).
+use crate::attributes::{AttributeCatalog, AttributeClass};
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::objects::ObjectCatalog;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClassRules {
+    pub allowed: HashSet<u16>,   // ATTL codes the class may carry
+    pub mandatory: HashSet<u16>, // ATTL codes the class must carry
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AttributeRules(HashMap<u16, ClassRules>);
+
+/// The attributes the ENC Product Specification makes mandatory, by object class acronym. Only
+/// the unconditional ones are listed, e.g. VALSOU of WRECKS depends on its other attributes.
+pub const ENC_MANDATORY: &[(&str, &[&str])] = &[
+    ("BCNCAR", &["BCNSHP", "CATCAM", "COLOUR"]),
+    ("BCNISD", &["BCNSHP", "COLOUR"]),
+    ("BCNLAT", &["BCNSHP", "CATLAM", "COLOUR"]),
+    ("BCNSAW", &["BCNSHP", "COLOUR"]),
+    ("BCNSPP", &["BCNSHP", "COLOUR"]),
+    ("BOYCAR", &["BOYSHP", "CATCAM", "COLOUR"]),
+    ("BOYINB", &["BOYSHP", "COLOUR"]),
+    ("BOYISD", &["BOYSHP", "COLOUR"]),
+    ("BOYLAT", &["BOYSHP", "CATLAM", "COLOUR"]),
+    ("BOYSAW", &["BOYSHP", "COLOUR"]),
+    ("BOYSPP", &["BOYSHP", "COLOUR"]),
+    ("DAYMAR", &["COLOUR", "TOPSHP"]),
+    ("DEPARE", &["DRVAL1", "DRVAL2"]),
+    ("DEPCNT", &["VALDCO"]),
+    ("DRGARE", &["DRVAL1"]),
+    ("LIGHTS", &["COLOUR"]),
+    ("LNDELV", &["ELEVAT"]),
+    ("M_COVR", &["CATCOV"]),
+    ("M_CSCL", &["CSCALE"]),
+    ("M_HDAT", &["HORDAT"]),
+    ("M_NSYS", &["MARSYS"]),
+    ("M_QUAL", &["CATZOC"]),
+    ("M_SDAT", &["VERDAT"]),
+    ("M_VDAT", &["VERDAT"]),
+    ("NAVLNE", &["ORIENT"]),
+    ("SBDARE", &["NATSUR"]),
+    ("TOPMAR", &["COLOUR", "TOPSHP"]),
+    ("UWTROC", &["VALSOU", "WATLEV"]),
+];
+
+impl AttributeRules {
+    pub fn new() -> AttributeRules {
+        AttributeRules::default()
+    }
+
+    /// Rules for every class of `objects`, allowing the attributes the class lists that are in
+    /// `attributes`. A class without an attribute list may carry any feature attribute. The
+    /// catalogues do not tell which attributes are mandatory, those are taken from
+    /// [`ENC_MANDATORY`].
+    pub fn from_catalogs(objects: &ObjectCatalog, attributes: &AttributeCatalog) -> AttributeRules {
+        let codes: HashMap<&str, u16> = attributes
+            .attributes()
+            .map(|attribute| (attribute.acronym.as_str(), attribute.code))
+            .collect();
+        let feature_attributes: HashSet<u16> = attributes
+            .attributes()
+            .filter(|attribute| attribute.class == AttributeClass::Feature)
+            .map(|attribute| attribute.code)
+            .collect();
+        let mandatory: HashMap<&str, &[&str]> = ENC_MANDATORY.iter().copied().collect();
+        let mut rules = AttributeRules::new();
+        for class in objects.classes() {
+            let allowed = match &class.attributes {
+                Some(acronyms) => acronyms
+                    .iter()
+                    .filter_map(|acronym| codes.get(acronym.as_str()).copied())
+                    .collect(),
+                None => feature_attributes.clone(),
+            };
+            let mandatory = mandatory
+                .get(class.acronym.as_str())
+                .map(|acronyms| acronyms.iter())
+                .into_iter()
+                .flatten()
+                .filter_map(|acronym| codes.get(acronym).copied())
+                .collect();
+            rules.insert(class.code, ClassRules { allowed, mandatory });
+        }
+        rules
+    }
+
+    /// Rules from the catalogues embedded with the `objects` and `attributes` features.
+    #[cfg(all(feature = "objects", feature = "attributes"))]
+    pub fn builtin() -> AttributeRules {
+        AttributeRules::from_catalogs(ObjectCatalog::builtin(), AttributeCatalog::builtin())
+    }
+
+    /// Sets the rules for the object class `objl`. Mandatory attributes are implicitly allowed.
+    pub fn insert(&mut self, objl: u16, mut rules: ClassRules) {
+        rules.allowed.extend(rules.mandatory.iter().copied());
+        self.0.insert(objl, rules);
+    }
+
+    pub fn get(&self, objl: u16) -> Option<&ClassRules> {
+        self.0.get(&objl)
+    }
+
+    /// Checks the attributes `attls` of a feature of class `objl`, found in Data Record `record`.
+    pub fn validate(&self, record: Option<usize>, objl: u16, attls: &[u16]) -> Vec<Diagnostic> {
+        let diagnostic = |kind| Diagnostic {
+            record,
+            tag: Some("ATTF".to_string()),
+            kind,
+        };
+        let rules = match self.0.get(&objl) {
+            Some(rules) => rules,
+            None => return vec![diagnostic(DiagnosticKind::UnknownObjectClass { objl })],
+        };
+        let mut diagnostics: Vec<Diagnostic> = attls
+            .iter()
+            .filter(|attl| !rules.allowed.contains(attl))
+            .map(|&attl| diagnostic(DiagnosticKind::AttributeNotAllowed { objl, attl }))
+            .collect();
+        let mut missing: Vec<u16> = rules
+            .mandatory
+            .iter()
+            .copied()
+            .filter(|attl| !attls.contains(attl))
+            .collect();
+        missing.sort_unstable();
+        diagnostics.extend(
+            missing
+                .into_iter()
+                .map(|attl| diagnostic(DiagnosticKind::MissingMandatoryAttribute { objl, attl })),
+        );
+        diagnostics
+    }
+}
+
+/// Checks the record identifiers ("0001" field) of the Data Records of a file, in order, for
+/// duplicates. With `sequential` they also have to count up by one from the first, as in a
+/// catalogue. Records without an identifier are skipped.
+pub fn check_record_ids<I>(ids: I, sequential: bool) -> Vec<Diagnostic>
+where
+    I: IntoIterator<Item = Option<i64>>,
+{
+    let mut seen = HashSet::new();
+    let mut previous: Option<i64> = None;
+    let mut diagnostics = Vec::new();
+    for (record, id) in ids.into_iter().enumerate() {
+        let id = match id {
+            Some(id) => id,
+            None => continue,
+        };
+        let diagnostic = |kind| Diagnostic {
+            record: Some(record),
+            tag: Some("0001".to_string()),
+            kind,
+        };
+        if !seen.insert(id) {
+            diagnostics.push(diagnostic(DiagnosticKind::DuplicateRecordId { id }));
+        } else if let Some(expected) = previous.map(|p| p + 1).filter(|&e| sequential && e != id) {
+            diagnostics.push(diagnostic(DiagnosticKind::RecordIdGap {
+                expected,
+                found: id,
+            }));
+        }
+        previous = Some(id);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_attributes() {
+        let mut rules = AttributeRules::new();
+        rules.insert(
+            42,
+            ClassRules {
+                allowed: [116].iter().copied().collect(),
+                mandatory: [87, 88].iter().copied().collect(),
+            },
+        );
+        assert!(rules.validate(None, 42, &[87, 88, 116]).is_empty());
+        let kinds: Vec<_> = rules
+            .validate(Some(3), 42, &[87, 1])
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::AttributeNotAllowed { objl: 42, attl: 1 },
+                DiagnosticKind::MissingMandatoryAttribute { objl: 42, attl: 88 },
+            ]
+        );
+        assert_eq!(
+            rules.validate(None, 7, &[])[0].kind,
+            DiagnosticKind::UnknownObjectClass { objl: 7 }
+        );
+    }
+
+    #[test]
+    fn rules_from_catalogs() {
+        let objects = ObjectCatalog::from_csv(
+            "42,Depth area,DEPARE,DRVAL1;DRVAL2;,,,G,Area;\n302,Coverage,M_COVR,,,,M,Area;\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let attributes = AttributeCatalog::from_csv(
+            "87,Depth range value 1,DRVAL1,F,F\n88,Depth range value 2,DRVAL2,F,F\n\
+             179,Value of sounding,VALSOU,F,F\n402,Quality of position,QUAPOS,E,S\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let rules = AttributeRules::from_catalogs(&objects, &attributes);
+        assert!(rules.validate(None, 42, &[87, 88]).is_empty());
+        assert_eq!(
+            rules.get(42).unwrap().mandatory,
+            [87, 88].iter().copied().collect()
+        );
+        assert_eq!(
+            rules.validate(None, 42, &[87, 88, 179])[0].kind,
+            DiagnosticKind::AttributeNotAllowed {
+                objl: 42,
+                attl: 179
+            }
+        );
+        // Without an attribute list any feature attribute is allowed
+        assert!(rules.validate(None, 302, &[87, 179]).is_empty());
+        assert_eq!(rules.validate(None, 302, &[402]).len(), 1);
+        assert!(rules.get(302).unwrap().mandatory.is_empty());
+    }
+
+    #[cfg(all(feature = "objects", feature = "attributes"))]
+    #[test]
+    fn builtin_rules() {
+        let rules = AttributeRules::builtin();
+        assert!(rules.validate(None, 42, &[87, 88, 102]).is_empty());
+        assert_eq!(
+            rules.validate(None, 42, &[402])[0].kind,
+            DiagnosticKind::AttributeNotAllowed {
+                objl: 42,
+                attl: 402
+            }
+        );
+        let kinds: Vec<_> = rules
+            .validate(None, 42, &[87, 179])
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::AttributeNotAllowed {
+                    objl: 42,
+                    attl: 179
+                },
+                DiagnosticKind::MissingMandatoryAttribute { objl: 42, attl: 88 },
+            ]
+        );
+        // UWTROC carries VALSOU and must do so
+        assert_eq!(
+            rules.validate(None, 153, &[187])[0].kind,
+            DiagnosticKind::MissingMandatoryAttribute {
+                objl: 153,
+                attl: 179
+            }
+        );
+    }
+
+    #[test]
+    fn record_ids() {
+        assert!(check_record_ids(vec![Some(1), Some(2), None, Some(3)], true).is_empty());
+        assert!(check_record_ids(vec![Some(1), Some(5)], false).is_empty());
+        let kinds: Vec<_> = check_record_ids(vec![Some(1), Some(2), Some(2), Some(5)], true)
+            .into_iter()
+            .map(|d| (d.record, d.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (Some(2), DiagnosticKind::DuplicateRecordId { id: 2 }),
+                (
+                    Some(3),
+                    DiagnosticKind::RecordIdGap {
+                        expected: 3,
+                        found: 5
+                    }
+                ),
+            ]
+        );
+    }
+}
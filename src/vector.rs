@@ -0,0 +1,146 @@
+//! Vector records of an ENC cell as one typed struct: the vector record identifier (VRID), the
+//! attributes (ATTV), the pointers to other vector records (VRPT) and the coordinates (SG2D and
+//! SG3D), scaled to degrees and depth units by the DSPM of the cell.
+use crate::catalog::{Record, Result};
+use crate::dataset::{number, pointer_name, rows, RecordType};
+use crate::dspm::DataSetParameters;
+use crate::error::ErrorKind;
+use crate::feature::{attributes, code};
+use crate::history::UpdateInstruction;
+
+/// A pointer from a vector record to another, one entry of its VRPT field. An edge points to its
+/// beginning and end nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VectorPointer {
+    pub rcnm: u8,        // Record name of the vector record, 120 for a connected node
+    pub rcid: u32,       // Record id of the vector record
+    pub orientation: u8, // ORNT, 1 forward, 2 reverse, 255 null
+    pub usage: u8,       // USAG, 1 exterior, 2 interior, 3 exterior truncated by the cell limit
+    pub topology: u8,    // TOPI, 1 beginning node, 2 end node, 3 left face, 4 right face
+    pub mask: u8,        // MASK, 1 mask, 2 show, 255 null
+}
+
+/// An isolated node, connected node, edge or face.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorRecord {
+    pub rcnm: RecordType,
+    pub rcid: u32,
+    pub version: u32,                    // RVER
+    pub instruction: UpdateInstruction,  // RUIN, always insert in a base cell
+    pub attributes: Vec<(u16, String)>,  // ATTL and ATVL of every ATTV entry
+    pub pointers: Vec<VectorPointer>,    // VRPT
+    pub coordinates: Vec<(f64, f64)>,    // SG2D as (latitude, longitude)
+    pub soundings: Vec<(f64, f64, f64)>, // SG3D as (latitude, longitude, depth)
+}
+
+impl VectorRecord {
+    /// The vector record `record` with its coordinates scaled by `dspm`.
+    pub fn new(record: &Record, dspm: &DataSetParameters) -> Result<VectorRecord> {
+        let vrid = rows(record, "VRID", &["RCNM", "RCID", "RVER", "RUIN"])?;
+        let vrid = vrid
+            .first()
+            .ok_or_else(|| ErrorKind::MissingField("VRID".to_string()))?;
+        let pointers = rows(record, "VRPT", &["NAME", "ORNT", "USAG", "TOPI", "MASK"])?
+            .into_iter()
+            .map(|row| {
+                let (rcnm, rcid) = pointer_name(row[0]).ok_or_else(|| {
+                    ErrorKind::BadSubfieldValue("NAME".to_string(), row[0].to_string())
+                })?;
+                Ok(VectorPointer {
+                    rcnm: rcnm as u8,
+                    rcid,
+                    orientation: number("ORNT", row[1])?,
+                    usage: number("USAG", row[2])?,
+                    topology: number("TOPI", row[3])?,
+                    mask: number("MASK", row[4])?,
+                })
+            })
+            .collect::<Result<Vec<VectorPointer>>>()?;
+        Ok(VectorRecord {
+            rcnm: RecordType::from_data(vrid[0])?,
+            rcid: number("RCID", vrid[1])?,
+            version: number("RVER", vrid[2])?,
+            instruction: code("RUIN", vrid[3], UpdateInstruction::from_code)?,
+            attributes: attributes(record, "ATTV")?,
+            pointers,
+            coordinates: dspm.coordinates(record)?,
+            soundings: dspm.soundings(record)?,
+        })
+    }
+
+    pub fn attribute(&self, attl: u16) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(code, _)| *code == attl)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_node(&self) -> bool {
+        matches!(
+            self.rcnm,
+            RecordType::IsolatedNode | RecordType::ConnectedNode
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_parser::Data;
+    use crate::units::{DepthUnit, HeightUnit, PositionalUnit, Units};
+
+    fn int(i: i64) -> Data {
+        Data::Integer(Some(i))
+    }
+
+    #[test]
+    fn edge() {
+        let dspm = DataSetParameters {
+            horizontal_datum: 2,
+            vertical_datum: 3,
+            sounding_datum: 23,
+            compilation_scale: 22000,
+            units: Units {
+                depth: DepthUnit::Metres,
+                height: HeightUnit::Metres,
+                positional: PositionalUnit::Metres,
+            },
+            coordinate_units: 1,
+            coordinate_factor: 100,
+            sounding_factor: 10,
+        };
+        let mut record = Record::new();
+        for (label, value) in [("RCNM", 130), ("RCID", 3), ("RVER", 1), ("RUIN", 1)] {
+            record.set("VRID", label, int(value));
+        }
+        let pointer = |rcid: i64, topi| {
+            vec![
+                int(120 | rcid << 8),
+                int(255),
+                int(255),
+                int(topi),
+                int(255),
+            ]
+        };
+        record.insert_rows(
+            "VRPT",
+            &["NAME", "ORNT", "USAG", "TOPI", "MASK"],
+            vec![pointer(1, 1), pointer(2, 2)],
+        );
+        record.insert_rows(
+            "SG2D",
+            &["YCOO", "XCOO"],
+            vec![vec![int(5150), int(-25)], vec![int(5151), int(-26)]],
+        );
+        let edge = VectorRecord::new(&record, &dspm).unwrap();
+        assert_eq!(edge.rcnm, RecordType::Edge);
+        assert!(!edge.is_node());
+        assert_eq!(edge.coordinates, vec![(51.5, -0.25), (51.51, -0.26)]);
+        assert!(edge.soundings.is_empty());
+        let ends: Vec<_> = edge.pointers.iter().map(|p| (p.rcid, p.topology)).collect();
+        assert_eq!(ends, vec![(1, 1), (2, 2)]);
+
+        record.set("VRID", "RCNM", int(150));
+        assert!(VectorRecord::new(&record, &dspm).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! A push-style alternative to iterating a [`Catalog`](crate::catalog::Catalog). Instead of
+//! building a [`Record`](crate::catalog::Record) for every Data Record, the subfields are handed
+//! to a [`Visitor`] one at a time as they are decoded, so only the current value is kept in
+//! memory.
+use crate::data_parser::Data;
+
+/// Returned from the [`Visitor`] callbacks to steer the parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Keep going.
+    Continue,
+    /// Skip the rest of the current record (from `record`) or field (from `field` and
+    /// `subfield`).
+    Skip,
+    /// Stop parsing altogether.
+    Stop,
+}
+
+/// Callbacks invoked by [`Catalog::parse_with`](crate::catalog::Catalog::parse_with). All
+/// methods default to `Flow::Continue`, so only the interesting ones need to be implemented.
+pub trait Visitor {
+    /// Called at the start of every Data Record, `index` counting the Data Records of the file
+    /// from zero, including any read before.
+    fn record(&mut self, _index: usize) -> Flow {
+        Flow::Continue
+    }
+
+    /// Called at the start of every field in the current record.
+    fn field(&mut self, _tag: &str) -> Flow {
+        Flow::Continue
+    }
+
+    /// Called for every decoded subfield in the current field. The subfields of a repeating
+    /// group come once for every repetition.
+    fn subfield(&mut self, _tag: &str, _label: &str, _data: Data) -> Flow {
+        Flow::Continue
+    }
+
+    /// Called at the end of every Data Record that was not skipped. Only `Flow::Stop` makes a
+    /// difference here.
+    fn end_record(&mut self, _index: usize) -> Flow {
+        Flow::Continue
+    }
+}
@@ -0,0 +1,143 @@
+//! Handling of multi-volume exchange sets. The VOLM subfield of a catalogue entry names the
+//! volume (media) the file was delivered on, e.g. "V01X02" is the first of two volumes, so a
+//! CATALOG.031 can list files that are not present on the currently mounted media.
+use crate::catalog::{Catalog, Record, Result};
+use crate::data_parser::Data;
+use crate::error::ErrorKind;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Volume {
+    pub number: u32, // Number of this volume, starting at 1
+    pub total: u32,  // Total number of volumes in the exchange set
+}
+
+impl FromStr for Volume {
+    type Err = crate::error::Error;
+    fn from_str(value: &str) -> Result<Volume> {
+        let bad = || ErrorKind::BadVolume(value.to_string());
+        if value.len() != 6 || !value.is_ascii() || !value.starts_with('V') || &value[3..4] != "X" {
+            return Err(bad().into());
+        }
+        let number = value[1..3].parse().map_err(|_| bad())?;
+        let total = value[4..6].parse().map_err(|_| bad())?;
+        if number == 0 || number > total {
+            return Err(bad().into());
+        }
+        Ok(Volume { number, total })
+    }
+}
+
+impl Display for Volume {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "V{:02}X{:02}", self.number, self.total)
+    }
+}
+
+impl Record {
+    /// The volume of a catalogue entry, from the VOLM subfield of its CATD field.
+    pub fn volume(&self) -> Option<Result<Volume>> {
+        match self.get("CATD")?.get("VOLM")? {
+            Data::String(s) if !s.is_empty() => Some(s.parse()),
+            _ => None,
+        }
+    }
+
+    /// The file name of a catalogue entry, from the FILE subfield of its CATD field.
+    pub fn file(&self) -> Option<&str> {
+        self.catd_str("FILE")
+    }
+}
+
+impl<R: Read + Seek> Catalog<R> {
+    /// The catalogue entry of `file`, e.g. "GB\\GB4X0000.000", searching from the first record
+    /// on. '/' is accepted as separator as well, a leading "ENC_ROOT" is ignored and case does
+    /// not matter. Iteration goes on from the record after the entry.
+    pub fn find_file(&mut self, file: &str) -> Result<Option<Record>> {
+        let file = file.replace('/', "\\");
+        let file = file.strip_prefix("ENC_ROOT\\").unwrap_or(&file);
+        self.rewind()?;
+        for record in self.records() {
+            let record = record?;
+            if record.file().is_some_and(|f| f.eq_ignore_ascii_case(file)) {
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Groups catalogue entries by the volume they are on. Entries without a VOLM subfield are left
+/// out.
+pub fn group_by_volume<I>(records: I) -> Result<BTreeMap<Volume, Vec<Record>>>
+where
+    I: IntoIterator<Item = Record>,
+{
+    let mut volumes: BTreeMap<Volume, Vec<Record>> = BTreeMap::new();
+    for record in records {
+        if let Some(volume) = record.volume() {
+            volumes.entry(volume?).or_default().push(record);
+        }
+    }
+    Ok(volumes)
+}
+
+/// Resolves the FILE subfield of a catalogue entry against the exchange set root. The file names
+/// in a catalogue use '\' as separator regardless of platform.
+pub fn resolve_file(root: &Path, file: &str) -> PathBuf {
+    file.split('\\')
+        .fold(root.to_path_buf(), |path, part| path.join(part))
+}
+
+/// The entries whose files are missing under `root`, together with the volume they are on. When
+/// `root` is the mounted media these are the files that have to be read from another volume.
+pub fn missing_files<'a>(root: &Path, records: &'a [Record]) -> Vec<(Option<Volume>, &'a Record)> {
+    records
+        .iter()
+        .filter(|record| match record.file() {
+            Some(file) => !resolve_file(root, file).exists(),
+            None => false,
+        })
+        .map(|record| (record.volume().and_then(|v| v.ok()), record))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_volume() {
+        assert_eq!(
+            "V01X02".parse::<Volume>().unwrap(),
+            Volume {
+                number: 1,
+                total: 2
+            }
+        );
+        assert_eq!(
+            Volume {
+                number: 3,
+                total: 12
+            }
+            .to_string(),
+            "V03X12"
+        );
+        assert!("V03X02".parse::<Volume>().is_err());
+        assert!("V1X2".parse::<Volume>().is_err());
+        assert!("X01V02".parse::<Volume>().is_err());
+        assert!("V0éX2".parse::<Volume>().is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        assert_eq!(
+            resolve_file(Path::new("/media"), "GB\\GB4X0000.000"),
+            Path::new("/media").join("GB").join("GB4X0000.000")
+        );
+    }
+}
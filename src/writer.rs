@@ -0,0 +1,70 @@
+//! Writing ISO 8211 files. The Data Descriptive Record is written as given, the Data Records are
+//! encoded from [`Record`]s following the schema it declares.
+use crate::catalog::{Catalog, Record, Result, Schema};
+use crate::error::ErrorKind;
+use failure::ResultExt;
+use std::io::{Read, Write};
+
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    out: W,
+    schema: Schema,
+}
+
+impl<W: Write> Writer<W> {
+    /// Writes the DDR `ddr`, e.g. the bytes of the first [`RawRecord`](crate::raw::RawRecord)
+    /// of a file, to `out`.
+    pub fn new(mut out: W, ddr: &[u8]) -> Result<Writer<W>> {
+        let schema = Catalog::read_schema_only(ddr)?;
+        out.write_all(ddr)
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        Ok(Writer { out, schema })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Writes `record` as the next Data Record. The fields are written in the order of
+    /// [`Record::tags`] and every field has to be described by the DDR.
+    pub fn write_record(&mut self, record: &Record) -> Result<()> {
+        let bytes = self.schema.encode_record(record)?;
+        self.out
+            .write_all(&bytes)
+            .with_context(|err| ErrorKind::IOError(err.kind()))?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Copies the file read from `rdr` to `out` one record at a time, leaving out the Data Records
+/// for which `keep` returns false. The record identifiers of the records kept are renumbered
+/// from 1 and the directories and lengths are written anew. Returns the number of records
+/// removed.
+pub fn rewrite_without<R, W, F>(mut rdr: R, out: W, mut keep: F) -> Result<usize>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(&Record) -> bool,
+{
+    let ddr = crate::iso8211::read_record(&mut rdr, None)?;
+    let mut writer = Writer::new(out, &ddr)?;
+    let mut removed = 0;
+    let mut id = 0;
+    for record in Catalog::new((&ddr[..]).chain(rdr))? {
+        let mut record = record?;
+        if !keep(&record) {
+            removed += 1;
+            continue;
+        }
+        if record.id().is_some() {
+            id += 1;
+            record.set_id(id);
+        }
+        writer.write_record(&record)?;
+    }
+    Ok(removed)
+}
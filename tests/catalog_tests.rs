@@ -4,11 +4,11 @@ use std::fs::File;
 type Result<T> = std::result::Result<T, failure::Error>;
 
 fn print_error(err: &failure::Error) {
-    println!("");
+    println!();
     for c in err.iter_chain() {
         println!("{}", c);
     }
-    println!("");
+    println!();
 }
 
 #[test]
@@ -16,7 +16,7 @@ fn test_parse_catalog() {
     if let Err(e) = try_main() {
         println!("{}", e.backtrace());
         print_error(&e);
-        assert!(false)
+        panic!()
     }
 }
 
@@ -36,3 +36,623 @@ fn try_main() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_read_schema_only() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let schema = Catalog::read_schema_only(cf).unwrap();
+    let tags: Vec<&str> = schema.fields().map(|f| f.tag).collect();
+    assert_eq!(tags, vec!["0001", "CATD"]);
+    let catd = schema.field("CATD").unwrap();
+    assert_eq!(catd.name, "Catalogue Directory Field");
+    assert_eq!(catd.labels[2], "FILE");
+    assert_eq!(catd.labels.len(), 12);
+    assert_eq!(catd.format_controls(), "(A(2),I(10),3A,A(3),4R,2A)");
+    assert!(catd.array_descriptor().starts_with("RCNM!RCID!FILE!"));
+    assert_eq!(schema.field("0001").unwrap().array_descriptor(), "");
+    let tree = schema.field_tree();
+    assert_eq!(tree.roots(), ["0001"]);
+    assert_eq!(tree.children("0001"), ["CATD"]);
+    assert_eq!(tree.parent("CATD"), Some("0001"));
+    assert!(tree.children("CATD").is_empty());
+}
+
+#[test]
+fn test_leader_info() {
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let schema = Catalog::read_schema_only(&bytes[..]).unwrap();
+    let ddr = schema.leader();
+    assert_eq!(ddr.interchange_level, '3');
+    assert_eq!(ddr.leader_identifier, 'L');
+    assert_eq!(ddr.code_extension_indicator, 'E');
+    assert_eq!(ddr.application_indicator, ' ');
+    assert_eq!(ddr.reserved, '0');
+    let mut catalog = Catalog::new(&bytes[..]).unwrap();
+    assert_eq!(catalog.leader(), ddr);
+    let record = catalog.next().unwrap().unwrap();
+    let dr = record.leader().unwrap();
+    assert_eq!(dr.leader_identifier, 'D');
+    assert_eq!(dr.application_indicator, ' ');
+    assert_eq!(dr.reserved, '0');
+    assert_eq!(ddr.record_length, Some(262));
+    assert_eq!(ddr.base_address, 73);
+    assert_eq!(dr.record_length, Some(101));
+    assert_eq!(dr.base_address, 53);
+    assert!(rust_s57::catalog::Record::new().leader().is_none());
+}
+
+#[test]
+fn test_structured_access() {
+    use rust_s57::catalog::Record;
+    use rust_s57::Data;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let record = Catalog::new(cf).unwrap().next().unwrap().unwrap();
+    assert_eq!(record.id(), Some(1));
+    let tags: Vec<_> = record.fields().map(|field| field.tag()).collect();
+    assert_eq!(tags, ["0001", "CATD"]);
+    let catd = record.subfields("CATD");
+    let labels: Vec<_> = catd.iter().map(|(label, _)| *label).collect();
+    assert_eq!(&labels[..3], ["RCNM", "RCID", "FILE"]);
+    assert_eq!(catd[0].1, &Data::String("CD".into()));
+    assert!(record.subfields("NONE").is_empty());
+    let catd = record.field("CATD").unwrap();
+    assert_eq!(catd.get("FILE"), Some(&Data::String("CATALOG.031".into())));
+    assert_eq!(catd.get("VOLM"), Some(&Data::String("V01X01".into())));
+    assert!(catd.get("NONE").is_none());
+    assert_eq!(catd.labels()[11], "COMT");
+    assert_eq!(catd.get_str("IMPL").unwrap(), "ASC");
+    assert_eq!(record.get_i64("CATD", "RCID").unwrap(), Some(1));
+    assert_eq!(record.get_f64("CATD", "RCID").unwrap(), Some(1.0));
+    let err = record.get_i64("CATD", "FILE").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Subfield FILE is a string where an integer was expected"
+    );
+    assert!(record.get_str("CATD", "NONE").is_err());
+    assert!(record.get_str("DSID", "EDTN").is_err());
+
+    let mut record = Record::new();
+    record.set("NAME", "LAST", Data::String("Lovelace".into()));
+    record.set("NAME", "FIRST", Data::String("Ada".into()));
+    let labels: Vec<_> = record.subfields("NAME").iter().map(|(l, _)| *l).collect();
+    assert_eq!(labels, ["FIRST", "LAST"]);
+}
+
+#[test]
+fn test_records() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    assert_eq!(catalog.records().size_hint(), (0, None));
+    assert_eq!(catalog.scan_records().unwrap(), 4);
+    let mut records = catalog.records();
+    assert_eq!(records.size_hint(), (4, Some(4)));
+    assert_eq!(records.next().unwrap().unwrap().id(), Some(1));
+    assert_eq!(records.size_hint(), (3, Some(3)));
+    assert_eq!(records.peek().unwrap().as_ref().unwrap().id(), Some(2));
+    assert_eq!(records.size_hint(), (3, Some(3)));
+    assert_eq!(records.next().unwrap().unwrap().id(), Some(2));
+    assert_eq!(records.by_ref().count(), 2);
+    assert!(records.peek().is_none());
+    assert!(records.next().is_none());
+    assert_eq!(records.size_hint(), (0, Some(0)));
+    catalog.rewind().unwrap();
+    let ids: Vec<_> = catalog.records().map(|r| r.unwrap().id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+}
+
+#[test]
+fn test_borrowed_iteration() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(1));
+    let ids: Vec<_> = (&catalog).into_iter().map(|r| r.unwrap().id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+    let mut files = 0;
+    for record in &catalog {
+        files += record.unwrap().fields_with_tag("CATD").count();
+    }
+    assert_eq!(files, 4);
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(2));
+}
+
+#[test]
+fn test_read_all() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    let records = catalog.read_all().unwrap();
+    assert_eq!(records.len(), 4);
+    assert!(catalog.read_all().unwrap().is_empty());
+}
+
+#[test]
+fn test_count_records() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(1));
+    let position = catalog.position();
+    assert_eq!(catalog.count_records().unwrap(), 4);
+    assert_eq!(catalog.count_records().unwrap(), 4);
+    assert_eq!(catalog.position(), position);
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(2));
+}
+
+#[test]
+fn test_events() {
+    use rust_s57::events::Event;
+    use rust_s57::Data;
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let events: Vec<Event> = Catalog::new(&bytes[..])
+        .unwrap()
+        .events()
+        .map(|event| event.unwrap())
+        .collect();
+    assert_eq!(events[0], Event::StartRecord(0));
+    assert_eq!(events[1], Event::Field("0001".to_string()));
+    assert_eq!(
+        events[2],
+        Event::Subfield("DRID".to_string(), Data::Integer(Some(1)))
+    );
+    assert_eq!(events.last(), Some(&Event::EndRecord));
+    let starts = events
+        .iter()
+        .filter(|e| matches!(e, Event::StartRecord(_)))
+        .count();
+    assert_eq!(starts, 4);
+
+    let subfields: Vec<(String, Data)> = Catalog::new(&bytes[..])
+        .unwrap()
+        .flat_map(|record| {
+            let record = record.unwrap();
+            record
+                .all_subfields()
+                .map(|(_, label, data)| (label.to_string(), data.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let streamed: Vec<(String, Data)> = events
+        .into_iter()
+        .filter_map(|e| match e {
+            Event::Subfield(label, data) => Some((label, data)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(streamed, subfields);
+}
+
+#[test]
+fn test_catalog_records() {
+    use rust_s57::catd::{CatalogRecord, Implementation};
+    use std::convert::TryFrom;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let entries: Vec<CatalogRecord> = Catalog::new(cf)
+        .unwrap()
+        .map(|r| CatalogRecord::try_from(&r.unwrap()).unwrap())
+        .collect();
+    let cell = &entries[1];
+    assert_eq!(cell.file, std::path::Path::new("AA5OTHER.000"));
+    assert_eq!(cell.impl_, Some(Implementation::Binary));
+    assert_eq!(cell.crcs, Some(0x717B_D6F2));
+    let bounds = cell.bounds.unwrap();
+    assert_eq!((bounds.south, bounds.east), (10.0, 10.3333334));
+    assert_eq!(entries[3].impl_, Some(Implementation::Text));
+    assert_eq!(entries[3].comt, None);
+}
+
+#[test]
+fn test_random_access() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    assert_eq!(catalog.record(2).unwrap().unwrap().id(), Some(3));
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(4));
+    assert_eq!(catalog.record(0).unwrap().unwrap().id(), Some(1));
+    assert_eq!(catalog.position(), 363);
+    assert!(catalog.record(4).unwrap().is_none());
+}
+
+#[test]
+fn test_lazy_records() {
+    use rust_s57::Data;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    let mut files = Vec::new();
+    while let Some(record) = catalog.next_lazy() {
+        let record = record.unwrap();
+        assert_eq!(record.tags().collect::<Vec<_>>(), ["0001", "CATD"]);
+        assert!(record.field("DSID").unwrap().is_none());
+        match record.field("CATD").unwrap().unwrap().get("FILE") {
+            Some(Data::String(file)) => files.push(file.clone()),
+            _ => panic!("CATD without FILE"),
+        }
+    }
+    assert_eq!(files.len(), 4);
+    assert_eq!(files[0], "CATALOG.031");
+}
+
+#[test]
+fn test_records_where() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    let ids: Vec<_> = catalog
+        .records_where(|record| record.has_field("CATD"))
+        .map(|r| r.unwrap().id())
+        .collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+    catalog.rewind().unwrap();
+    assert_eq!(catalog.records_where(|r| r.has_field("DSID")).count(), 0);
+    catalog.rewind().unwrap();
+    let mut seen = 0;
+    let ids: Vec<_> = catalog
+        .records_where(|_| {
+            seen += 1;
+            seen % 2 == 0
+        })
+        .map(|r| r.unwrap().id())
+        .collect();
+    assert_eq!(ids, vec![Some(2), Some(4)]);
+}
+
+#[test]
+fn test_rewind() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    let first: Vec<_> = catalog.by_ref().map(|r| r.unwrap().id()).collect();
+    catalog.rewind().unwrap();
+    let second: Vec<_> = catalog.by_ref().map(|r| r.unwrap().id()).collect();
+    assert_eq!(first, vec![Some(1), Some(2), Some(3), Some(4)]);
+    assert_eq!(first, second);
+    let mut restarted = catalog
+        .restart_with(File::open("tests/CATALOG.031").unwrap())
+        .unwrap();
+    let third: Vec<_> = restarted.by_ref().map(|r| r.unwrap().id()).collect();
+    assert_eq!(first, third);
+    // Rewinding goes back to the first record from wherever the reader is
+    assert_eq!(restarted.record(2).unwrap().unwrap().id(), Some(3));
+    restarted.rewind().unwrap();
+    assert_eq!(restarted.next().unwrap().unwrap().id(), Some(1));
+}
+
+#[test]
+fn test_memory_budget() {
+    use rust_s57::error::ErrorKind;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::with_memory_budget(cf, 200).unwrap();
+    assert!(catalog.next().unwrap().is_ok());
+    match catalog.next().unwrap() {
+        Err(err) => match err.kind() {
+            ErrorKind::MemoryBudgetExceeded(left) => assert_eq!(*left, 99),
+            kind => panic!("unexpected error: {}", kind),
+        },
+        Ok(_) => panic!("budget was not enforced"),
+    }
+    // The record over the budget is skipped, the ones after it are read up to the budget too
+    for record in catalog.by_ref() {
+        match record.unwrap_err().kind() {
+            ErrorKind::MemoryBudgetExceeded(_) => (),
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+    catalog.rewind().unwrap();
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(1));
+}
+
+#[test]
+fn test_parse_with_visitor() {
+    use rust_s57::visitor::{Flow, Visitor};
+    use rust_s57::Data;
+
+    // Collects the FILE subfield of every CATD field and stops after the third record.
+    struct Files(Vec<String>);
+    impl Visitor for Files {
+        fn record(&mut self, index: usize) -> Flow {
+            if index == 3 {
+                Flow::Stop
+            } else {
+                Flow::Continue
+            }
+        }
+        fn field(&mut self, tag: &str) -> Flow {
+            if tag == "CATD" {
+                Flow::Continue
+            } else {
+                Flow::Skip
+            }
+        }
+        fn subfield(&mut self, _tag: &str, label: &str, data: Data) -> Flow {
+            if label == "FILE" {
+                self.0.push(data.to_string());
+                Flow::Skip
+            } else {
+                Flow::Continue
+            }
+        }
+    }
+
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    let mut files = Files(Vec::new());
+    catalog.parse_with(&mut files).unwrap();
+    assert_eq!(files.0, vec!["CATALOG.031", "AA5OTHER.000", "AAMNPUB1.TXT"]);
+}
+
+#[test]
+fn test_non_numeric_record_length() {
+    let mut bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    // The first Data Record starts right after the 262 bytes long DDR
+    bytes[262..267].copy_from_slice(b"     ");
+    let catalog = Catalog::new(std::io::Cursor::new(bytes)).unwrap();
+    let ids: Vec<_> = catalog.map(|r| r.unwrap().id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+}
+
+#[test]
+fn test_group_by_volume() {
+    use rust_s57::volume::{group_by_volume, missing_files, Volume};
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let records: Vec<_> = Catalog::new(cf).unwrap().map(|r| r.unwrap()).collect();
+    let v1 = Volume {
+        number: 1,
+        total: 1,
+    };
+    let missing: Vec<_> = missing_files(std::path::Path::new("tests"), &records)
+        .into_iter()
+        .map(|(volume, record)| (volume, record.file().unwrap()))
+        .collect();
+    assert_eq!(
+        missing,
+        vec![
+            (Some(v1), "AA5OTHER.000"),
+            (Some(v1), "AAMNPUB1.TXT"),
+            (Some(v1), "README.TXT")
+        ]
+    );
+    let volumes = group_by_volume(records).unwrap();
+    assert_eq!(volumes.len(), 1);
+    assert_eq!(volumes[&v1].len(), 4);
+}
+
+#[test]
+fn test_find_file() {
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(cf).unwrap();
+    let readme = catalog.find_file("ENC_ROOT/readme.txt").unwrap().unwrap();
+    assert_eq!(readme.id(), Some(4));
+    let first = catalog.find_file("CATALOG.031").unwrap().unwrap();
+    assert_eq!(first.id(), Some(1));
+    assert_eq!(catalog.next().unwrap().unwrap().id(), Some(2));
+    assert!(catalog.find_file("GB4X0000.000").unwrap().is_none());
+}
+
+#[test]
+fn test_associated_files() {
+    use rust_s57::references::{associated_files, find_referenced, open};
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let records: Vec<_> = Catalog::new(cf).unwrap().map(|r| r.unwrap()).collect();
+    let cell = &records[1];
+    let files: Vec<_> = associated_files(&records, cell)
+        .into_iter()
+        .map(|r| r.file().unwrap())
+        .collect();
+    assert_eq!(files, vec!["AAMNPUB1.TXT", "README.TXT"]);
+    assert_eq!(
+        find_referenced(&records, "readme.txt").unwrap().id(),
+        Some(4)
+    );
+    assert!(open(std::path::Path::new("tests"), &records[0]).is_ok());
+}
+
+#[test]
+fn test_raw_records() {
+    use rust_s57::raw::raw_records;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let records: Vec<_> = raw_records(cf).map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 5);
+    assert_eq!(records.iter().map(|r| r.bytes.len()).sum::<usize>(), 716);
+    let ddr = &records[0];
+    assert_eq!(ddr.field_area, 73);
+    assert_eq!(ddr.fields[1].tag, "0001");
+    assert_eq!(ddr.fields[1].entry, 40..56);
+    assert_eq!(
+        &ddr.bytes[ddr.fields[0].data.clone()],
+        b"0000;&   \x1f0001CATD\x1e"
+    );
+    assert_eq!(records[1].field_bytes("0001").unwrap(), b"00001\x1e");
+}
+
+#[test]
+fn test_subfield_spans() {
+    use rust_s57::Data;
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let mut catalog = Catalog::new(std::io::Cursor::new(&bytes)).unwrap();
+    let record = catalog.nth(1).unwrap().unwrap();
+    let spans: Vec<_> = record.subfield_spans("CATD").collect();
+    assert_eq!(spans.len(), 12);
+    let (label, data, span) = &spans[2];
+    assert_eq!(*label, "FILE");
+    assert_eq!(**data, Data::String("AA5OTHER.000".to_string()));
+    // The second Data Record starts after the DDR and the first Data Record
+    assert_eq!(&bytes[262 + 101..][span.clone()], b"AA5OTHER.000");
+    let (_, _, empty) = &spans[3];
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_extract_records_with_tag() {
+    use rust_s57::raw::{extract_records_with_tag, records_with_tag};
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let mut out = Vec::new();
+    assert_eq!(
+        extract_records_with_tag(&bytes[..], "CATD", &mut out).unwrap(),
+        4
+    );
+    assert_eq!(out, bytes);
+    out.clear();
+    assert_eq!(
+        extract_records_with_tag(&bytes[..], "VRID", &mut out).unwrap(),
+        0
+    );
+    assert_eq!(out, &bytes[..262]);
+    assert_eq!(records_with_tag(&bytes[..], "0001").count(), 4);
+}
+
+#[test]
+fn test_coverage_area() {
+    use rust_s57::coverage::CoverageArea;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let records: Vec<_> = Catalog::new(cf).unwrap().map(|r| r.unwrap()).collect();
+    assert!(records[1].bounds().is_some());
+    assert!(records[0].bounds().is_none());
+    let area = CoverageArea::from_catalog(&records);
+    assert_eq!(area.boxes().len(), 1);
+    assert!(area.contains(10.1, 10.2));
+    assert!(!area.contains(10.1, 10.0));
+}
+
+#[test]
+fn test_check_completeness() {
+    use rust_s57::exchange_set::ExchangeSet;
+    let set = ExchangeSet::open("tests").unwrap();
+    assert_eq!(set.records().len(), 4);
+    let completeness = set.check_completeness().unwrap();
+    assert!(!completeness.is_complete());
+    assert_eq!(
+        completeness.missing,
+        vec!["AA5OTHER.000", "AAMNPUB1.TXT", "README.TXT"]
+    );
+    assert_eq!(
+        completeness.unexpected,
+        vec![std::path::PathBuf::from("catalog_tests.rs")]
+    );
+}
+
+#[test]
+fn test_exchange_set_members() {
+    use rust_s57::exchange_set::{ExchangeSet, MemberKind};
+    let set = ExchangeSet::open("tests").unwrap();
+    let cells: Vec<_> = set.cells().map(|m| m.unwrap()).collect();
+    assert_eq!(cells.len(), 1);
+    assert_eq!(
+        cells[0].path,
+        std::path::Path::new("tests").join("AA5OTHER.000")
+    );
+    assert_eq!(cells[0].cell.as_ref().unwrap().producer, "AA");
+    assert_eq!(set.updates().count(), 0);
+    assert_eq!(set.text_files().count(), 2);
+    let first = set.members().next().unwrap().unwrap();
+    assert_eq!(first.kind, MemberKind::Catalog);
+}
+
+#[test]
+fn test_edit_and_write() {
+    use rust_s57::model::Document;
+    use rust_s57::Data;
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let mut doc = Document::read(&bytes[..]).unwrap();
+    assert_eq!(doc.records.len(), 4);
+    // Written back unchanged the file reads the same, the DDR byte for byte
+    let written = doc.write(Vec::new()).unwrap();
+    assert_eq!(&written[..262], &bytes[..262]);
+    let reread = Document::read(&written[..]).unwrap();
+    for (a, b) in reread.records.iter().zip(doc.records.iter()) {
+        for tag in a.tags() {
+            assert_eq!(a.get(tag), b.get(tag));
+        }
+    }
+
+    let old = doc.records[1].set("CATD", "FILE", Data::String("AA5OTHER.001".to_string()));
+    assert_eq!(old, Some(Data::String("AA5OTHER.000".to_string())));
+    doc.records.remove(2);
+    let written = doc.write(Vec::new()).unwrap();
+    let records: Vec<_> = Catalog::new(&written[..])
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1].file(), Some("AA5OTHER.001"));
+    assert_eq!(records[2].file(), Some("README.TXT"));
+    assert_eq!(records[1].tags().collect::<Vec<_>>(), vec!["0001", "CATD"]);
+}
+
+#[test]
+fn test_remove_records() {
+    use rust_s57::writer::rewrite_without;
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let mut out = Vec::new();
+    let removed = rewrite_without(&bytes[..], &mut out, |r| r.implementation() != Some("TXT"));
+    assert_eq!(removed.unwrap(), 2);
+    let records: Vec<_> = Catalog::new(&out[..])
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let ids: Vec<_> = records.iter().map(|r| r.id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2)]);
+    assert_eq!(records[1].file(), Some("AA5OTHER.000"));
+
+    let mut doc = rust_s57::model::Document::read(&bytes[..]).unwrap();
+    assert_eq!(doc.retain(|r| r.file() != Some("AA5OTHER.000")), 1);
+    let ids: Vec<_> = doc.records.iter().map(|r| r.id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+}
+
+#[test]
+fn test_record_spans() {
+    let bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    let catalog = Catalog::new(&bytes[..]).unwrap();
+    assert_eq!(catalog.position(), 262);
+    let spans: Vec<_> = catalog.with_spans().map(|r| r.unwrap().0).collect();
+    assert_eq!(spans[0], 262..363);
+    assert_eq!(spans.last().unwrap().end, 716);
+    assert!(spans.windows(2).all(|w| w[0].end == w[1].start));
+}
+
+#[test]
+fn test_base_address_mismatch() {
+    use rust_s57::diagnostics::DiagnosticKind;
+    let mut bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    // The base address of the first Data Record, 12 bytes into its leader
+    bytes[262 + 12..262 + 17].copy_from_slice(b"00099");
+    let mut catalog = Catalog::new(&bytes[..]).unwrap();
+    let ids: Vec<_> = catalog.by_ref().map(|r| r.unwrap().id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+    let diagnostics = catalog.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].record, Some(0));
+    assert_eq!(
+        diagnostics[0].kind,
+        DiagnosticKind::BaseAddressMismatch {
+            declared: 99,
+            actual: 53
+        }
+    );
+}
+
+#[test]
+fn test_error_handler() {
+    use rust_s57::catalog::ErrorAction;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    let mut bytes = std::fs::read("tests/CATALOG.031").unwrap();
+    // Make the RCID of the second Data Record non-numeric
+    bytes[430] = b'X';
+    let mut catalog = Catalog::new(std::io::Cursor::new(bytes.clone())).unwrap();
+    assert!(catalog.nth(1).unwrap().is_err());
+
+    let failed = Rc::new(RefCell::new(Vec::new()));
+    let mut catalog = Catalog::new(std::io::Cursor::new(bytes)).unwrap();
+    let seen = failed.clone();
+    catalog.on_error(move |_, data| {
+        seen.borrow_mut().push(data.len());
+        ErrorAction::Continue
+    });
+    let ids: Vec<_> = catalog.map(|r| r.unwrap().id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(3), Some(4)]);
+    assert_eq!(*failed.borrow(), vec![150]);
+}
+
+#[test]
+fn test_generic_reader() {
+    use rust_s57::iso8211::Iso8211Reader;
+    let cf = File::open("tests/CATALOG.031").unwrap();
+    let reader = Iso8211Reader::new(cf).unwrap();
+    let ids: Vec<_> = reader.map(|r| r.unwrap().id()).collect();
+    assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+}